@@ -0,0 +1,58 @@
+// Parses compact binary frames of broadcast_gate ws output, all little endian
+// Layout is TagMarketPictureBroadcast cut after last populated depth level
+
+const ENRICHED_PICTURE_CODE = 9002;
+// TagMessageHeader + TagInstrument
+const ENRICHED_PICTURE_OFFSET = 114;
+const MESSAGE_HEADER_LEN = 44;
+const DEPTH_INFO_LEN = 14;
+
+// Returns null for frames other than market pictures
+export function parse_picture(buffer: ArrayBuffer, depth_codes: number[]) {
+  let view = new DataView(buffer);
+  let message_code = view.getInt32(0, true);
+
+  let offset = 0;
+  if (message_code == ENRICHED_PICTURE_CODE) {
+    offset = ENRICHED_PICTURE_OFFSET;
+  } else if (!depth_codes.includes(message_code)) {
+    return null;
+  }
+
+  let i32 = (at: number) => view.getInt32(offset + at, true);
+  let i64 = (at: number) => Number(view.getBigInt64(offset + at, true));
+
+  let data = {
+    message_code: i32(0),
+    token: i64(MESSAGE_HEADER_LEN),
+    total_buy_qty: i64(52),
+    total_sell_qty: i64(60),
+    volume_traded_today: i64(68),
+    open_price: i32(76),
+    close_price: i32(80),
+    high_price: i32(84),
+    low_price: i32(88),
+    ltp: i32(92),
+    ltq: i32(96),
+    ltt: i32(100),
+    atp: i32(104),
+    indicative_close_price: i32(108),
+    lut: i64(112),
+    buy_depth_count: i32(120),
+    sell_depth_count: i32(124),
+    trading_status: view.getInt16(offset + 128, true),
+    market_depth_info: [] as { qty: number; price: number; number_of_orders: number }[],
+  };
+
+  for (let level = 0; level < data.buy_depth_count + data.sell_depth_count; level++) {
+    let at = 130 + level * DEPTH_INFO_LEN;
+
+    data.market_depth_info.push({
+      qty: i64(at),
+      price: i32(at + 8),
+      number_of_orders: view.getInt16(offset + at + 12, true),
+    });
+  }
+
+  return data;
+}
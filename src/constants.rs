@@ -1,6 +1,11 @@
 use std::io::ErrorKind;
 
 pub const BUF_SIZE: usize = 10240;
+// Buffer sizes of packet pool, each packet gets smallest which fits
+// Middle one fits market picture output, largest fits any datagram
+pub const PACKET_SIZE_CLASSES: [usize; 3] = [512, 4096, BUF_SIZE];
+// Free packet buffers kept for reuse of each size, more are allocated if needed
+pub const PACKET_POOL_SIZE: usize = 4096;
 pub const SKIP_BYTES: usize = 8;
 
 // For packet structures
@@ -82,12 +87,56 @@ pub const DEBT_MBP_UNCOMPRESSED_DATA_LEN: usize = 68;
 
 // For MCX
 pub const SNAPSHOT_TEMPLATE_ID: i32 = 101;
+pub const INSTRUMENT_STATE_TEMPLATE_ID: i32 = 103;
+pub const MASS_INSTRUMENT_STATE_TEMPLATE_ID: i32 = 104;
+pub const QUOTE_REQUEST_TEMPLATE_ID: i32 = 106;
+pub const CROSS_REQUEST_TEMPLATE_ID: i32 = 107;
+pub const PRODUCT_STATE_TEMPLATE_ID: i32 = 108;
+pub const INDEX_STATS_TEMPLATE_ID: i32 = 51;
+pub const TOP_OF_BOOK_IMPLIED_TEMPLATE_ID: i32 = 513;
 pub const MAX_INCR_TO_PROCESS: i32 = 0;
 
+// For recorder
+pub const JOURNAL_MAGIC: &[u8; 8] = b"BGJRNL01";
+pub const NS_PER_DAY: u64 = 86_400_000_000_000;
+
 // Custom types
 pub const MAX_MARKET_DEPTH_IDX: usize = 200; // Define this constant as per your requirement
 pub const TIMESTAMP_LEN: usize = 8; // Define this constant as per your requirement
 pub const MAX_BUY_SELL_DEPTH_IDX: usize = 5;
+pub const GAP_EVENT_CODE: i32 = 9001;
+pub const ENRICHED_PICTURE_CODE: i32 = 9002;
+pub const MASTER_CHANGE_CODE: i32 = 9003;
+pub const INSTRUMENT_SYMBOL_LEN: usize = 32;
+pub const INSTRUMENT_SERIES_LEN: usize = 8;
+pub const OPTION_TYPE_LEN: usize = 2;
+
+// For statistics
+pub const FEED_NAMES: [&str; 2] = ["primary", "secondary"];
+pub const OUTPUT_COUNT: usize = 5;
+pub const OUTPUT_NAMES: [&str; OUTPUT_COUNT] = ["udp", "kafka", "stdout", "counter", "ws"];
+pub const QUEUE_COUNT: usize = 7;
+pub const QUEUE_NAMES: [&str; QUEUE_COUNT] = [
+    "input",
+    "packet",
+    "mcx",
+    "ws",
+    "output",
+    "ws_client",
+    "recorder",
+];
+// Message codes of all exchanges fit in u16, last counter is for codes outside it
+pub const MESSAGE_CODE_COUNT: usize = 1 << 16;
+
+// For websocket clients, in milliseconds
+pub const WS_CLIENT_POLL_INTERVAL: u64 = 10;
+
+// For kafka, in milliseconds
+pub const KAFKA_MAX_RETRY_BACKOFF: u64 = 1000;
+
+// For shutdown, in seconds
+pub const KAFKA_FLUSH_TIMEOUT: u64 = 10;
+pub const WS_DRAIN_TIMEOUT: u64 = 5;
 
 // Error kinds
 pub const UNRECOVERABLE_ERROR_KINDS: [ErrorKind; 7] = [
@@ -1,31 +1,55 @@
+use std::mem::{offset_of, size_of};
+
 use crate::{
     constants::{BSE_BCAST_COMPLEX, BSE_BCAST_DEBT, BSE_BCAST_MBP},
-    global::STATISTICS,
+    global::{STATISTICS, TOKEN_FILTER},
     types::{
-        packet::Packet,
+        arbiter::Arbiter,
+        packet::{Feed, Packet},
+        packet_structures::bse::*,
         work::{Work, WorkType},
     },
-    utils::byte_utils::bytes_to_struct,
+    utils::byte_utils::try_bytes_to_struct,
     workers::get_bse_processing_fn,
 };
 
 use super::Distribute;
 
-pub struct BseDistributor {}
+pub struct BseDistributor {
+    arbiter: Arbiter,
+}
 
 impl BseDistributor {
     pub fn new() -> Self {
-        Self {}
+        println!("Bse messages carry no seq no, gap events are not published for bse");
+
+        Self {
+            arbiter: Arbiter::new(),
+        }
     }
 }
 
 impl Distribute for BseDistributor {
-    fn distribute(&mut self, packet: Packet) {
-        let mut message_code: i32 = bytes_to_struct(&packet.0);
+    fn distribute(&mut self, mut packet: Packet, feed: Feed) {
+        // Bse messages don't carry seq no, so copies from both feeds are matched by content
+        if !self.arbiter.accept_bytes(&packet.0[..packet.1], feed) {
+            STATISTICS.get().duplicate_packets_count.inc();
+            return;
+        }
+
+        let mut message_code: i32 = match try_bytes_to_struct(&packet.0[..packet.1], 0) {
+            Ok(message_code) => message_code,
+            Err(e) => {
+                println!("Malformed bse packet: {}", e);
+                STATISTICS.record_malformed();
+                return;
+            }
+        };
         // Twiddle
         message_code = message_code.to_be();
 
-        STATISTICS.get().other_packets_count += 1;
+        STATISTICS.get().other_packets_count.inc();
+        STATISTICS.record_message_code(message_code);
 
         // Create work
         let work_type = match message_code {
@@ -34,6 +58,13 @@ impl Distribute for BseDistributor {
             _ => WorkType::BseUncompressed,
         };
 
+        // Compressed records are filtered by worker, as tokens can only be read after decompression
+        if let WorkType::BseUncompressed = work_type {
+            if !filter_records(&mut packet, message_code) {
+                return;
+            }
+        }
+
         let processing_fn = get_bse_processing_fn(&work_type);
         let work = Work {
             work_type,
@@ -45,4 +76,93 @@ impl Distribute for BseDistributor {
 
         super::distribute_to_queue(packet, work);
     }
+
+    fn arbiter(&mut self) -> &mut Arbiter {
+        &mut self.arbiter
+    }
+}
+
+// Offsets of record count and records, and record size, of messages with per instrument records
+// Each record starts with its instrument code
+fn record_layout(message_code: i32) -> Option<(usize, usize, usize)> {
+    macro_rules! layout {
+        ($message:ty, $records:ident, $record:ty) => {
+            Some((
+                offset_of!($message, no_of_records),
+                offset_of!($message, $records),
+                size_of::<$record>(),
+            ))
+        };
+    }
+
+    match message_code {
+        2014 => layout!(BcastClosePrice, close_price_details, BcastClosePriceDetails),
+        2015 => layout!(
+            BcastOpenInterest,
+            close_price_details,
+            BcastOpenInterestDetails
+        ),
+        2016 => layout!(BcastVarPercentage, var_details, BcastVarDetails),
+        2017 => layout!(BcastAuctionMBP, mbp_details, BcastAuctionMBPDetails),
+        2027 => layout!(BcastOddLotMarketPicture, mbp_details, BcastOddLotMBPDetails),
+        2028 => layout!(
+            BcastImpliedVolatility,
+            implied_volatility,
+            BcastImpliedVolatilityDetails
+        ),
+        2034 => layout!(BcastLPPRange, lpp_range_details, BcastLPPRangeDetails),
+        2035 => layout!(
+            BcastCACxlQtyMessage,
+            ca_cxl_qty_details,
+            BcastCACxlQtyDetails
+        ),
+        _ => None,
+    }
+}
+
+// Removes records of unsubscribed instruments in place, false if none are left
+fn filter_records(packet: &mut Packet, message_code: i32) -> bool {
+    if TOKEN_FILTER.is_all() {
+        return true;
+    }
+
+    let Some((count_offset, records_offset, record_size)) = record_layout(message_code) else {
+        return true;
+    };
+
+    let Ok(count) = try_bytes_to_struct::<i16>(&packet.0[..packet.1], count_offset) else {
+        return true;
+    };
+    // Records cut by packet end are left to worker
+    let count = (i16::from_be(count).max(0) as usize)
+        .min(packet.1.saturating_sub(records_offset) / record_size);
+
+    let buf = &mut packet.0[..];
+    let mut kept = 0;
+    for i in 0..count {
+        let start = records_offset + i * record_size;
+        let instrument = i32::from_be_bytes(buf[start..start + 4].try_into().unwrap());
+
+        if !TOKEN_FILTER.contains(instrument as i64) {
+            STATISTICS.get().unsubscribed_packets_count.inc();
+            continue;
+        }
+
+        if kept != i {
+            buf.copy_within(
+                start..start + record_size,
+                records_offset + kept * record_size,
+            );
+        }
+        kept += 1;
+    }
+
+    if kept == count {
+        return true;
+    }
+
+    // Length is kept, as worker reads whole message struct
+    buf[count_offset..count_offset + 2].copy_from_slice(&(kept as i16).to_be_bytes());
+
+    kept > 0
 }
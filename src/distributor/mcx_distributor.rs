@@ -1,20 +1,24 @@
-use std::{fs, ptr::drop_in_place, sync::atomic::Ordering};
+use std::{fs, mem::size_of, sync::atomic::Ordering};
 
 use bytes::Bytes;
 use fastlib::{Decoder, ModelFactory};
 use serde::Deserialize;
 
 use crate::{
-    constants::BUF_SIZE,
-    global::{MCX_TOKEN_WISE_MAP, STATISTICS, TPOOL_QUEUE},
+    global::{MCX_TOKEN_WISE_MAP, STATISTICS, TOKEN_FILTER},
     settings,
     types::{
-        packet::Packet,
-        packet_structures::mcx::{DepthIncremental, DepthSnapshot, Message},
+        arbiter::Arbiter,
+        packet::{Feed, Packet},
+        packet_structures::{
+            depth_output::TagMarketPictureBroadcast,
+            mcx::{DepthIncremental, DepthSnapshot, Message},
+        },
         state::McxTokenState,
         work::{Work, WorkType},
     },
     utils::{atomic_utils::compare_and_swap_gte, byte_utils::struct_to_bytes_heap},
+    worker_pool::dispatch,
     workers::get_mcx_processing_fn,
 };
 
@@ -22,6 +26,7 @@ use super::Distribute;
 
 pub struct McxDistributor {
     decoder: Decoder,
+    arbiter: Arbiter,
 }
 
 // Required for Decoder, safe because is used by only single thread
@@ -39,12 +44,15 @@ impl McxDistributor {
 
         let decoder = Decoder::new_from_xml(&template).unwrap();
 
-        Self { decoder }
+        Self {
+            decoder,
+            arbiter: Arbiter::new(),
+        }
     }
 }
 
 impl Distribute for McxDistributor {
-    fn distribute(&mut self, packet: Packet) {
+    fn distribute(&mut self, packet: Packet, feed: Feed) {
         let mut raw = Bytes::from(packet.0[0..packet.1].to_owned());
 
         loop {
@@ -59,6 +67,7 @@ impl Distribute for McxDistributor {
 
             if err.is_err() {
                 println!("Error: {:?}", err);
+                STATISTICS.record_malformed();
                 break;
             }
 
@@ -67,6 +76,7 @@ impl Distribute for McxDistributor {
             // Get result or error
             let message = if let Err(error) = message {
                 println!("Error: {:?}", error);
+                STATISTICS.record_malformed();
                 break;
             } else {
                 message.unwrap()
@@ -76,50 +86,76 @@ impl Distribute for McxDistributor {
             if let Message::FastReset(_) = message {
                 self.decoder.reset();
                 continue;
-            } else if let Message::MDPacketHeader(_) = message {
+            } else if let Message::MDPacketHeader(header) = message {
+                // Drop whole packet if it was already received on other feed
+                let accepted = self.arbiter.accept_seq(
+                    header.SenderCompID as u64,
+                    header.packet_seq_num(),
+                    feed,
+                );
+
+                if !accepted {
+                    STATISTICS.get().duplicate_packets_count.inc();
+                    break;
+                }
+
                 continue;
             }
 
+            STATISTICS.record_message_code(message.template_id());
+
             match message {
-                Message::DepthSnapshot(depth_snapshot) => self.distribute_snapshot(depth_snapshot),
+                Message::DepthSnapshot(depth_snapshot) => {
+                    self.distribute_snapshot(depth_snapshot, packet.2)
+                }
                 Message::DepthIncremental(depth_incremental) => {
-                    self.distribute_incremental(depth_incremental)
+                    self.distribute_incremental(depth_incremental, packet.2)
                 }
-                _ => self.distribute_others(message),
+                _ => self.distribute_others(message, packet.2),
             }
         }
     }
+
+    fn arbiter(&mut self) -> &mut Arbiter {
+        &mut self.arbiter
+    }
 }
 
 impl McxDistributor {
-    pub fn distribute_snapshot(&self, depth_snapshot: DepthSnapshot) {
-        STATISTICS.get().depth_packets_count += 1;
+    // Receive time of datagram is carried by packet which is processed
+    pub fn distribute_snapshot(&self, depth_snapshot: DepthSnapshot, recv_ts: u64) {
+        STATISTICS.get().depth_packets_count.inc();
+
+        // Skip tokens which are not subscribed
+        if !TOKEN_FILTER.contains(depth_snapshot.SecurityID) {
+            STATISTICS.get().unsubscribed_packets_count.inc();
+            return;
+        }
 
         // Get token and mcx state
         let token = depth_snapshot.SecurityID as usize;
-        let mcx_state = MCX_TOKEN_WISE_MAP
-            .entry(token)
-            .or_insert(McxTokenState::new());
+        let mcx_state = MCX_TOKEN_WISE_MAP.get_or_insert_with(token, McxTokenState::new);
 
         // Do not process if packet's seq no is older than current
         let new_seq_no = depth_snapshot.MsgSeqNum.expect("MsgSeqNum must be present");
         let swapped = compare_and_swap_gte(&mcx_state.seq_no, new_seq_no);
 
         if swapped.is_err() {
+            STATISTICS.get().dropped_count.inc();
             return;
         }
 
         // Create work
         let work = Work {
-            work_type: WorkType::McxDepthSnapshot,
-            processing_fn: get_mcx_processing_fn(&WorkType::McxDepthSnapshot),
+            work_type: WorkType::McxDepthSnapshot(depth_snapshot.SecurityID),
+            processing_fn: get_mcx_processing_fn(&WorkType::McxDepthSnapshot(0)),
             atomic_ptr: None,
             mcx_state: Some(mcx_state.clone()),
             seq_no: new_seq_no as usize,
         };
 
         // Create packet
-        let mut packet = Packet([0; BUF_SIZE], BUF_SIZE);
+        let mut packet = Packet::uninit(size_of::<DepthSnapshot>());
         packet.1 = struct_to_bytes_heap(depth_snapshot, &mut packet.0);
 
         // Swap new packet in atomic ptr
@@ -129,16 +165,19 @@ impl McxDistributor {
         // Free old packet
         if !old_packet_ptr.is_null() {
             unsafe {
-                drop_in_place(old_packet_ptr);
+                drop(Box::from_raw(old_packet_ptr));
             }
+
+            STATISTICS.get().conflated_count.inc();
         }
 
         // Only add work if work queue is empty
         if mcx_state.packet_queue.len() == 0 {
-            // Create message packet
-            let mut empty_packet = Packet([0; BUF_SIZE], BUF_SIZE);
+            // Create message packet, worker writes market picture over it
+            let mut empty_packet = Packet::uninit(size_of::<TagMarketPictureBroadcast>());
             empty_packet.1 =
                 struct_to_bytes_heap(Message::DepthSnapshotEmpty(()), &mut empty_packet.0);
+            empty_packet.2 = recv_ts;
 
             mcx_state.packet_queue.push(empty_packet);
 
@@ -147,22 +186,29 @@ impl McxDistributor {
                 .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
                 .is_ok()
             {
-                TPOOL_QUEUE.push(work);
+                dispatch(work);
             }
         }
     }
 
-    pub fn distribute_incremental(&self, depth_incremental: DepthIncremental) {
-        STATISTICS.get().depth_packets_count += 1;
+    pub fn distribute_incremental(&self, depth_incremental: DepthIncremental, recv_ts: u64) {
+        STATISTICS.get().depth_packets_count.inc();
         let messages = depth_incremental.MDIncGrp;
 
         for message in messages {
+            // Skip tokens which are not subscribed
+            if !TOKEN_FILTER.contains(message.SecurityID) {
+                STATISTICS.get().unsubscribed_packets_count.inc();
+                continue;
+            }
+
             // Get token and mcx state
             let token = message.SecurityID as usize;
-            let mcx_state = MCX_TOKEN_WISE_MAP.get(&token);
+            let mcx_state = MCX_TOKEN_WISE_MAP.get(token);
 
             // Continue if snapshot not available for this token
             if mcx_state.is_none() {
+                STATISTICS.get().dropped_count.inc();
                 continue;
             }
 
@@ -171,20 +217,22 @@ impl McxDistributor {
             // Do not process if packet's seq no is older than current
             let current_seq_no = mcx_state.seq_no.load(Ordering::SeqCst);
             if depth_incremental.MsgSeqNum <= current_seq_no {
+                STATISTICS.get().dropped_count.inc();
                 continue;
             }
 
             let work = Work {
-                work_type: WorkType::McxDepthIncr,
-                processing_fn: get_mcx_processing_fn(&WorkType::McxDepthIncr),
+                work_type: WorkType::McxDepthIncr(message.SecurityID),
+                processing_fn: get_mcx_processing_fn(&WorkType::McxDepthIncr(0)),
                 atomic_ptr: None,
                 mcx_state: Some(mcx_state.clone()),
                 seq_no: depth_incremental.MsgSeqNum as usize,
             };
 
-            // Create message packet
-            let mut packet = Packet([0; BUF_SIZE], BUF_SIZE);
+            // Create message packet, worker writes market picture over it
+            let mut packet = Packet::uninit(size_of::<TagMarketPictureBroadcast>());
             packet.1 = struct_to_bytes_heap(Message::MDIncGrp(message), &mut packet.0);
+            packet.2 = recv_ts;
 
             mcx_state.packet_queue.push(packet);
 
@@ -193,12 +241,28 @@ impl McxDistributor {
                 .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
                 .is_ok()
             {
-                TPOOL_QUEUE.push(work);
+                dispatch(work);
             }
         }
     }
 
-    pub fn distribute_others(&self, message: Message) {
-        println!("Other messages {:?}", message);
+    pub fn distribute_others(&self, message: Message, recv_ts: u64) {
+        STATISTICS.get().other_packets_count.inc();
+
+        let work = Work {
+            work_type: WorkType::McxOther,
+            processing_fn: get_mcx_processing_fn(&WorkType::McxOther),
+            atomic_ptr: None,
+            mcx_state: None,
+            seq_no: 0,
+        };
+
+        // Create message packet
+        let mut packet = Packet::uninit(size_of::<Message>());
+        packet.1 = struct_to_bytes_heap(message, &mut packet.0);
+        packet.2 = recv_ts;
+
+        // Single queue keeps state changes in order
+        super::distribute_to_queue(packet, work);
     }
 }
@@ -1,4 +1,4 @@
-use std::ptr::drop_in_place;
+use std::ptr;
 use std::sync::{
     atomic::{AtomicPtr, Ordering},
     Arc,
@@ -7,8 +7,12 @@ use std::thread::{self, JoinHandle};
 
 use crate::types::state::NseTokenState;
 use crate::{
-    global::{NSE_TOKEN_WISE_MAP, PACKET_QUEUES, TPOOL_QUEUE, WORK_LOCKS},
-    types::{packet::Packet, work::Work},
+    global::{NSE_TOKEN_WISE_MAP, PACKET_QUEUES, WORK_LOCKS},
+    types::{
+        arbiter::Arbiter,
+        packet::{Feed, Packet},
+        work::Work,
+    },
 };
 
 use bse_distributor::BseDistributor;
@@ -17,7 +21,13 @@ use ncd_distributor::NcdDistributor;
 use neq_distributor::NeqDistributor;
 use nfo_distributor::NfoDistributor;
 
-use crate::{global::INPUT_QUEUE, settings, types::settings::Exchange};
+use crate::{
+    global::{INPUT_QUEUE, INPUT_STOPPED, INPUT_WAKEUP, STATISTICS},
+    settings,
+    types::{settings::Exchange, wait::Waiter},
+    utils::thread_utils::pin_to_core,
+    worker_pool::dispatch,
+};
 
 pub mod bse_distributor;
 pub mod mcx_distributor;
@@ -45,9 +55,27 @@ impl Distributor {
             Exchange::MCX => Box::new(McxDistributor::new()),
         };
 
-        thread::spawn(move || loop {
-            if let Some(packet) = INPUT_QUEUE.pop() {
-                distributor.distribute(packet);
+        let core = settings.distributor_core;
+        let mut waiter = Waiter::new(settings.wait_strategy.distributor);
+
+        thread::spawn(move || {
+            pin_to_core(core);
+            INPUT_WAKEUP.register();
+
+            loop {
+                if let Some((packet, feed)) = INPUT_QUEUE.pop() {
+                    waiter.reset();
+                    STATISTICS.get().feed_packets_count[feed as usize].inc();
+
+                    distributor.distribute(packet, feed);
+                } else if INPUT_STOPPED.load(Ordering::SeqCst) {
+                    // Input has exited and queue is drained
+                    distributor.arbiter().close_gaps();
+                    break;
+                } else {
+                    distributor.arbiter().expire_gaps();
+                    waiter.wait(Some(&INPUT_WAKEUP), || !INPUT_QUEUE.is_empty());
+                }
             }
         })
     }
@@ -61,48 +89,47 @@ pub fn distribute_to_queue(packet: Packet, work: Work) {
 
     packet_queue.push(packet);
 
-    // If no work of current type in threadpool
+    // If no work of current type is queued to workers
     if work_lock
         .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
         .is_ok()
     {
-        TPOOL_QUEUE.push(work);
+        dispatch(work);
     }
 }
 
 pub fn distribute_to_map(packet: Packet, mut work: Work) {
     let new_packet_ptr = Box::into_raw(Box::new(packet));
 
-    let nse_token_state = NSE_TOKEN_WISE_MAP.get(&work.work_type.get_id());
-
-    if let Some(nse_token_state) = nse_token_state {
-        work.atomic_ptr = Some(nse_token_state.ptr.clone());
-        // If value exists
-        // retreive old packet by swaping with new value
-        let old_packet_ptr = nse_token_state.ptr.swap(new_packet_ptr, Ordering::SeqCst);
-
-        // If old packet ptr was set to null
-        // create new work
-        if old_packet_ptr.is_null() {
-            TPOOL_QUEUE.push(work);
-        } else {
-            // If old packet was not null
-            // means it is still allocated in heap
-            // manually create box from it and drop
-            unsafe {
-                drop_in_place(old_packet_ptr);
-            }
-        }
-    } else {
-        let atomic_ptr = Arc::new(AtomicPtr::new(new_packet_ptr));
-        work.atomic_ptr = Some(atomic_ptr.clone());
+    // New token starts with null, so first packet creates work
+    let nse_token_state =
+        NSE_TOKEN_WISE_MAP.get_or_insert_with(work.work_type.get_id(), || NseTokenState {
+            ptr: Arc::new(AtomicPtr::new(ptr::null_mut())),
+        });
 
-        NSE_TOKEN_WISE_MAP.insert(work.work_type.get_id(), NseTokenState { ptr: atomic_ptr });
+    work.atomic_ptr = Some(nse_token_state.ptr.clone());
+    // retreive old packet by swaping with new value
+    let old_packet_ptr = nse_token_state.ptr.swap(new_packet_ptr, Ordering::SeqCst);
 
-        TPOOL_QUEUE.push(work);
+    // If old packet ptr was set to null
+    // create new work
+    if old_packet_ptr.is_null() {
+        dispatch(work);
+    } else {
+        // If old packet was not null
+        // means it is still allocated in heap
+        // manually create box from it and drop
+        unsafe {
+            drop(Box::from_raw(old_packet_ptr));
+        }
+
+        STATISTICS.get().conflated_count.inc();
     }
 }
 
 pub trait Distribute {
-    fn distribute(&mut self, packet: Packet);
+    fn distribute(&mut self, packet: Packet, feed: Feed);
+
+    // Arbiter of both feeds, for reporting gaps while input is idle
+    fn arbiter(&mut self) -> &mut Arbiter;
 }
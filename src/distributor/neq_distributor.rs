@@ -1,6 +1,8 @@
 use crate::{
+    global::{STATISTICS, TOKEN_FILTER},
     types::{
-        packet::Packet,
+        arbiter::Arbiter,
+        packet::{Feed, Packet},
         work::{Work, WorkType},
     },
     workers::get_neq_processing_fn,
@@ -8,21 +10,32 @@ use crate::{
 
 use super::Distribute;
 
-pub struct NeqDistributor {}
+pub struct NeqDistributor {
+    arbiter: Arbiter,
+}
 
 impl NeqDistributor {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            arbiter: Arbiter::new(),
+        }
     }
 }
 
 impl Distribute for NeqDistributor {
-    fn distribute(&mut self, packet: Packet) {
+    fn distribute(&mut self, packet: Packet, feed: Feed) {
         // If nse, extract packets received in single packet
-        let (packets, no_of_packets) = packet.get_nse_packets();
+        // Messages already received on other feed are dropped
+        let packets = match packet.get_nse_packets(&mut self.arbiter, feed) {
+            Ok(packets) => packets,
+            Err(e) => {
+                println!("Malformed nse packet: {}", e);
+                STATISTICS.record_malformed();
+                return;
+            }
+        };
 
-        for i in 0..no_of_packets {
-            let (packet, work_type) = packets[i];
+        for (packet, work_type) in packets {
             // Create work
             let processing_fn = get_neq_processing_fn(&work_type);
             let work = Work {
@@ -33,11 +46,21 @@ impl Distribute for NeqDistributor {
                 seq_no: 0,
             };
 
-            if let WorkType::TokenWise(_) = work_type {
+            if let WorkType::TokenWise(token) = work_type {
+                // Skip tokens which are not subscribed
+                if !TOKEN_FILTER.contains(token as i64) {
+                    STATISTICS.get().unsubscribed_packets_count.inc();
+                    continue;
+                }
+
                 super::distribute_to_map(packet, work);
             } else {
                 super::distribute_to_queue(packet, work);
             }
         }
     }
+
+    fn arbiter(&mut self) -> &mut Arbiter {
+        &mut self.arbiter
+    }
 }
@@ -6,27 +6,41 @@ use std::{
 use crate::{
     create_array,
     output::Output,
+    settings,
     statistics::Statistics,
     types::{
-        packet::Packet,
+        bounded_queue::{BoundedQueue, QueueKind},
+        packet::{Feed, Packet},
+        packet_pool::PacketPool,
         packet_structures::neq::BcastHeaders,
+        security_master::SecurityMaster,
         settings::{Exchange, Settings},
         state::{McxTokenState, NseTokenState},
-        unsafe_hashmap::UnsafeHashMap,
-        work::Work,
+        token_filter::TokenFilter,
+        token_map::TokenMap,
+        wait::Wakeup,
+        worker_queue::WorkerQueue,
     },
 };
-use crossbeam::queue::SegQueue;
 use lazy_static::lazy_static;
 
 // No of work types
 const TYPE_COUNT: usize = 258;
 
-pub static INPUT_QUEUE: SegQueue<Packet> = SegQueue::new();
-pub static TPOOL_QUEUE: SegQueue<Work> = SegQueue::new();
-pub static PACKET_QUEUES: [SegQueue<Packet>; TYPE_COUNT] = create_array!(SegQueue::new(); 258);
+pub static INPUT_QUEUE: BoundedQueue<(Packet, Feed)> = BoundedQueue::new(QueueKind::Input);
+// Wakes distributor after push to INPUT_QUEUE
+pub static INPUT_WAKEUP: Wakeup = Wakeup::new();
+pub static PACKET_QUEUES: [BoundedQueue<Packet>; TYPE_COUNT] =
+    create_array!(BoundedQueue::new(QueueKind::Packet); 258);
 pub static WORK_LOCKS: [AtomicBool; TYPE_COUNT] = create_array!(AtomicBool::new(false); 258);
 
+// Set on SIGTERM/SIGINT, or when input finishes
+pub static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+// Set once input thread has exited, so distributor can exit after draining INPUT_QUEUE
+pub static INPUT_STOPPED: AtomicBool = AtomicBool::new(false);
+// Set once distributor has exited, so workers can exit after draining their queues
+pub static DISTRIBUTOR_STOPPED: AtomicBool = AtomicBool::new(false);
+
 pub static mut EXCHANGE: Exchange = Exchange::NEQ;
 
 pub static SETTINGS: OnceLock<Settings> = OnceLock::new();
@@ -34,7 +48,14 @@ pub static NSE_HEADER_SIZE: usize = size_of::<BcastHeaders>();
 pub static STATISTICS: Statistics = Statistics::new();
 
 lazy_static! {
-    pub static ref NSE_TOKEN_WISE_MAP: UnsafeHashMap<usize, NseTokenState> = UnsafeHashMap::new();
-    pub static ref MCX_TOKEN_WISE_MAP: UnsafeHashMap<usize, McxTokenState> = UnsafeHashMap::new();
+    pub static ref PACKET_POOL: PacketPool = PacketPool::new();
+    pub static ref NSE_TOKEN_WISE_MAP: TokenMap<NseTokenState> =
+        TokenMap::new(&[Exchange::NEQ, Exchange::NFO, Exchange::NCD]);
+    pub static ref MCX_TOKEN_WISE_MAP: TokenMap<McxTokenState> = TokenMap::new(&[Exchange::MCX]);
     pub static ref OUTPUT: Output = Output::new();
+    pub static ref SECURITY_MASTER: SecurityMaster = SecurityMaster::new();
+    pub static ref TOKEN_FILTER: TokenFilter = TokenFilter::new();
+    // One queue per worker thread, as per thread_count
+    pub static ref WORKER_QUEUES: Vec<WorkerQueue> =
+        (0..settings::get().thread_count).map(|_| WorkerQueue::new()).collect();
 }
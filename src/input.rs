@@ -1,84 +1,85 @@
-use std::{mem::MaybeUninit, net::Ipv4Addr};
+use std::{
+    net::{Ipv4Addr, SocketAddrV4},
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant},
+};
 
 use socket2::Socket;
 
 use crate::{
     constants::{BUF_SIZE, UNRECOVERABLE_ERROR_KINDS},
-    global::{INPUT_QUEUE, STATISTICS},
+    global::{INPUT_QUEUE, INPUT_WAKEUP, SHUTDOWN, STATISTICS},
+    recorder::{JournalReader, Recorder},
     settings,
-    types::packet::Packet,
-    utils::{byte_utils::uninit_to_buf, udp_utils::build_socket},
+    types::{
+        packet::{Feed, Packet},
+        packet_pool::PacketBuf,
+        settings::ReplayPacing,
+    },
+    utils::{
+        pcap_utils::{parse_udp, PcapReader},
+        time_utils::get_epoch_us,
+        udp_utils::build_socket,
+    },
 };
 
-enum SocketType {
-    Primary,
-    Secondary,
-}
-
-pub struct UdpInput<'a> {
+pub struct UdpInput {
     primary: Socket,
     secondary: Socket,
-    current: Option<&'a Socket>,
-    current_id: SocketType,
-    auto_switch: bool,
     source_ip: Ipv4Addr,
+    recorder: Option<Recorder>,
 }
 
-impl<'a> UdpInput<'a> {
-    pub fn new() -> UdpInput<'a> {
+impl UdpInput {
+    pub fn new() -> UdpInput {
         let settings = settings::get();
 
-        // Set timeout 0 if autoswitch is false
-        let timeout = if settings.udp_auto_switch == true {
-            settings.udp_switch_timeout
-        } else {
-            0
-        } as u64;
-
+        // Sockets time out, so readers can check for shutdown while feed is idle
         UdpInput {
             primary: build_socket(
                 &settings.primary_mcast_ip,
                 &settings.udp_local_ip,
                 settings.primary_mcast_port as u16,
-                timeout,
+                1,
             ),
             secondary: build_socket(
                 &settings.secondary_mcast_ip,
                 &settings.udp_local_ip,
                 settings.secondary_mcast_port as u16,
-                timeout,
+                1,
             ),
-            current: None,
-            current_id: SocketType::Primary,
-            auto_switch: settings.udp_auto_switch,
             source_ip: settings.source_ip.parse().unwrap(),
+            recorder: settings.record_dir.as_ref().map(|_| Recorder::new()),
         }
     }
 
-    pub fn read(&'a mut self) {
-        // Set current to primary
-        self.current = Some(&self.primary);
+    // Reads both feeds simultaneously, distributor drops duplicates
+    pub fn read(&self) {
+        thread::scope(|scope| {
+            scope.spawn(|| self.read_feed(&self.secondary, Feed::Secondary));
 
-        loop {
-            // let mut buf = Packet([0; BUF_SIZE]);
-            let mut buf: [MaybeUninit<u8>; BUF_SIZE] =
-                unsafe { MaybeUninit::uninit().assume_init() };
-            let packet_size;
+            self.read_feed(&self.primary, Feed::Primary);
+        });
+    }
 
-            // Value can never be none
-            debug_assert!(self.current.is_some());
+    fn read_feed(&self, socket: &Socket, feed: Feed) {
+        while !SHUTDOWN.load(Ordering::SeqCst) {
+            // Received directly into pooled buffer
+            let mut buf = PacketBuf::uninit(BUF_SIZE);
+            let packet_size;
 
-            match self.current.unwrap().recv_from(&mut buf) {
+            match socket.recv_from(buf.as_uninit_mut()) {
                 Ok((len, addr)) => {
                     // Drop packet if source ip doesn't match
                     if *addr.as_socket_ipv4().unwrap().ip() != self.source_ip {
-                        STATISTICS.get().filtered_packets_count += 1;
+                        STATISTICS.get().filtered_packets_count.inc();
                         continue;
                     }
 
                     packet_size = len;
 
-                    STATISTICS.get().udp_packets_count += 1;
+                    STATISTICS.get().udp_packets_count.inc();
                 }
                 Err(e) => {
                     // Check for client side errors
@@ -86,29 +87,189 @@ impl<'a> UdpInput<'a> {
                         panic!("Unrecoverable io error in udp input: {}", e);
                     }
 
-                    // If autoswitch is false, then don't rotate
-                    if !self.auto_switch {
-                        continue;
-                    }
+                    continue;
+                }
+            }
 
-                    // Switch current id
-                    self.current_id = match self.current_id {
-                        SocketType::Primary => SocketType::Secondary,
-                        SocketType::Secondary => SocketType::Primary,
-                    };
+            let packet = Packet(buf, packet_size, get_epoch_us() as u64);
 
-                    // Based on current id select new
-                    self.current = match self.current_id {
-                        SocketType::Primary => Some(&self.primary),
-                        SocketType::Secondary => Some(&self.secondary),
-                    };
+            if let Some(recorder) = &self.recorder {
+                recorder.record(feed as u8, &packet);
+            }
 
-                    continue;
+            INPUT_QUEUE.push((packet, feed));
+            INPUT_WAKEUP.wake();
+        }
+    }
+}
+
+// Sleeps between replayed packets to keep their original spacing
+struct Pacer {
+    pacing: ReplayPacing,
+    // Capture time of first packet and wall time it was replayed at
+    start: Option<(u64, Instant)>,
+}
+
+impl Pacer {
+    fn new(pacing: ReplayPacing) -> Pacer {
+        Pacer {
+            pacing,
+            start: None,
+        }
+    }
+
+    fn wait(&mut self, ts_ns: u64) {
+        if self.pacing != ReplayPacing::Original {
+            return;
+        }
+
+        let (first_ts, started_at) = *self.start.get_or_insert((ts_ns, Instant::now()));
+
+        // Wait till packet's offset from first packet has elapsed
+        let offset = Duration::from_nanos(ts_ns.saturating_sub(first_ts));
+        let elapsed = started_at.elapsed();
+
+        if offset > elapsed {
+            thread::sleep(offset - elapsed);
+        }
+    }
+}
+
+pub struct PcapInput {
+    reader: PcapReader,
+    pacer: Pacer,
+    feeds: [SocketAddrV4; 2],
+    source_ip: Ipv4Addr,
+}
+
+impl PcapInput {
+    pub fn new() -> PcapInput {
+        let settings = settings::get();
+
+        let path = settings
+            .pcap_file
+            .as_ref()
+            .expect("pcap_file required for pcap input");
+        let reader = PcapReader::open(path).expect("Unable to open pcap file");
+
+        // Only datagrams sent to either feed are replayed
+        let feeds = [
+            SocketAddrV4::new(
+                settings.primary_mcast_ip.parse().unwrap(),
+                settings.primary_mcast_port as u16,
+            ),
+            SocketAddrV4::new(
+                settings.secondary_mcast_ip.parse().unwrap(),
+                settings.secondary_mcast_port as u16,
+            ),
+        ];
+
+        PcapInput {
+            reader,
+            pacer: Pacer::new(settings.replay_pacing),
+            feeds,
+            source_ip: settings.source_ip.parse().unwrap(),
+        }
+    }
+
+    pub fn read(&mut self) {
+        while !SHUTDOWN.load(Ordering::SeqCst) {
+            let record = match self.reader.next_record() {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(e) => {
+                    println!("Error reading pcap file: {}", e);
+                    break;
                 }
+            };
+
+            let Some(datagram) = parse_udp(record.link_type, &record.data) else {
+                continue;
+            };
+
+            let destination = SocketAddrV4::new(datagram.dst_ip, datagram.dst_port);
+            let feed = if destination == self.feeds[Feed::Primary as usize] {
+                Feed::Primary
+            } else if destination == self.feeds[Feed::Secondary as usize] {
+                Feed::Secondary
+            } else {
+                continue;
+            };
+
+            // Drop packet if source ip doesn't match
+            if datagram.src_ip != self.source_ip {
+                STATISTICS.get().filtered_packets_count.inc();
+                continue;
             }
 
-            let packet = Packet(uninit_to_buf(&buf), packet_size);
-            INPUT_QUEUE.push(packet);
+            self.pacer.wait(record.ts_ns);
+
+            STATISTICS.get().udp_packets_count.inc();
+
+            let packet_size = datagram.payload.len().min(BUF_SIZE);
+            // Same as received datagram, bse workers write over it
+            let mut packet = Packet::from_slice(&datagram.payload[..packet_size], BUF_SIZE);
+            packet.2 = record.ts_ns / 1000;
+
+            INPUT_QUEUE.push((packet, feed));
+            INPUT_WAKEUP.wake();
         }
+
+        println!("Pcap replay finished");
+    }
+}
+
+pub struct JournalInput {
+    reader: JournalReader,
+    pacer: Pacer,
+}
+
+impl JournalInput {
+    pub fn new() -> JournalInput {
+        let settings = settings::get();
+
+        let path = settings
+            .journal_file
+            .as_ref()
+            .expect("journal_file required for journal input");
+        let reader = JournalReader::open(path).expect("Unable to open journal file");
+
+        JournalInput {
+            reader,
+            pacer: Pacer::new(settings.replay_pacing),
+        }
+    }
+
+    pub fn read(&mut self) {
+        while !SHUTDOWN.load(Ordering::SeqCst) {
+            // Journal only has accepted datagrams, so no filtering is required
+            let record = match self.reader.next_record() {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(e) => {
+                    println!("Error reading journal file: {}", e);
+                    break;
+                }
+            };
+
+            self.pacer.wait(record.header.recv_ts);
+
+            STATISTICS.get().udp_packets_count.inc();
+
+            let packet_size = record.data.len().min(BUF_SIZE);
+            let mut packet = Packet::from_slice(&record.data[..packet_size], BUF_SIZE);
+            packet.2 = record.header.recv_ts / 1000;
+
+            let feed = if record.header.socket == Feed::Secondary as u8 {
+                Feed::Secondary
+            } else {
+                Feed::Primary
+            };
+
+            INPUT_QUEUE.push((packet, feed));
+            INPUT_WAKEUP.wake();
+        }
+
+        println!("Journal replay finished");
     }
 }
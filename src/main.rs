@@ -1,11 +1,20 @@
-use std::thread;
+use std::{
+    process,
+    sync::atomic::Ordering,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
 
 use distributor::Distributor;
-use global::OUTPUT;
-use input::UdpInput;
+use global::{
+    DISTRIBUTOR_STOPPED, INPUT_STOPPED, MCX_TOKEN_WISE_MAP, NSE_TOKEN_WISE_MAP, OUTPUT,
+    PACKET_POOL, SECURITY_MASTER, SHUTDOWN,
+};
+use input::{JournalInput, PcapInput, UdpInput};
 use output::Output;
 use statistics::Statistics;
-use threadpool::ThreadPoolMaster;
+use utils::thread_utils::pin_to_core;
+use worker_pool::WorkerPool;
 
 mod constants;
 mod distributor;
@@ -13,29 +22,107 @@ mod global;
 mod input;
 mod macros;
 mod output;
+mod recorder;
 mod settings;
 mod statistics;
-mod threadpool;
 mod types;
 mod utils;
+mod worker_pool;
 mod workers;
 
 fn main() {
     settings::init();
     // Because behind lazy static, we need to init it at start
+    PACKET_POOL.touch();
+    SECURITY_MASTER.touch();
+    NSE_TOKEN_WISE_MAP.touch();
+    MCX_TOKEN_WISE_MAP.touch();
     OUTPUT.touch();
 
+    // Handler only sets flag, main thread does the shutdown
+    // Second signal exits without draining
+    ctrlc::set_handler(|| {
+        if SHUTDOWN.swap(true, Ordering::SeqCst) {
+            println!("Exiting without draining queues");
+            process::exit(1);
+        }
+
+        println!("Shutting down");
+    })
+    .expect("Unable to set signal handler");
+
     let distributor = Distributor::new();
-    let tpool_master = ThreadPoolMaster::new(settings::get().thread_count);
+    let worker_pool = WorkerPool::new();
 
-    let input_thread = thread::spawn(|| UdpInput::new().read());
+    let input_core = settings::get().input_core;
+    let input_thread = if settings::get().pcap_file.is_some() {
+        thread::spawn(move || {
+            pin_to_core(input_core);
+            PcapInput::new().read()
+        })
+    } else if settings::get().journal_file.is_some() {
+        thread::spawn(move || {
+            pin_to_core(input_core);
+            JournalInput::new().read()
+        })
+    } else {
+        thread::spawn(move || {
+            pin_to_core(input_core);
+            UdpInput::new().read()
+        })
+    };
     let distributor_thread = distributor.start_distributor();
-    let tpool_master_thread = tpool_master.start_tpool();
+    let worker_threads = worker_pool.start_workers();
+
+    // Serves metrics, if metrics_addr is set
+    thread::spawn(Statistics::run);
+
+    // Run till signal, or till replay has finished
+    while !SHUTDOWN.load(Ordering::SeqCst) && !input_thread.is_finished() {
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    process::exit(shutdown(input_thread, distributor_thread, worker_threads));
+}
+
+// Stops each stage after the one feeding it has exited, so queued packets are processed
+// Returns exit status, 0 if everything was drained and delivered
+fn shutdown(
+    input_thread: JoinHandle<()>,
+    distributor_thread: JoinHandle<()>,
+    worker_threads: Vec<JoinHandle<()>>,
+) -> i32 {
+    let mut status = 0;
+
+    SHUTDOWN.store(true, Ordering::SeqCst);
+
+    if input_thread.join().is_err() {
+        println!("Input thread panicked");
+        status = 1;
+    }
+
+    INPUT_STOPPED.store(true, Ordering::SeqCst);
+
+    if distributor_thread.join().is_err() {
+        println!("Distributor thread panicked");
+        status = 1;
+    }
+
+    DISTRIBUTOR_STOPPED.store(true, Ordering::SeqCst);
+
+    for worker_thread in worker_threads {
+        if worker_thread.join().is_err() {
+            println!("Worker thread panicked");
+            status = 1;
+        }
+    }
+
+    if !OUTPUT.close() {
+        println!("Some output was not delivered");
+        status = 1;
+    }
 
-    // Runs in main thread in loop
-    Statistics::run();
+    println!("Shutdown complete");
 
-    input_thread.join().unwrap();
-    distributor_thread.join().unwrap();
-    tpool_master_thread.join().unwrap();
+    status
 }
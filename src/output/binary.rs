@@ -0,0 +1,41 @@
+use std::mem::{offset_of, size_of};
+
+use crate::{
+    constants::{ENRICHED_PICTURE_CODE, MAX_MARKET_DEPTH_IDX},
+    types::{
+        packet::Packet,
+        packet_structures::{
+            depth_output::{TagMarketDepthInfo, TagMarketPictureBroadcast},
+            enriched_output::TagEnrichedMarketPicture,
+        },
+    },
+    utils::byte_utils::bytes_to_struct_ptr,
+};
+
+// Output struct in little endian, as written to udp
+// Market pictures are cut after last populated depth level, prices are in exchange units
+pub fn compact(packet: &Packet, depth_codes: &[i32]) -> Vec<u8> {
+    let message_code = i32::from_le_bytes(packet.0[0..4].try_into().unwrap());
+
+    let picture_offset = if message_code == ENRICHED_PICTURE_CODE {
+        offset_of!(TagEnrichedMarketPicture, market_picture)
+    } else if depth_codes.contains(&message_code) {
+        0
+    } else {
+        return packet.0[..packet.1].to_vec();
+    };
+
+    let Ok(picture) = bytes_to_struct_ptr::<TagMarketPictureBroadcast>(&packet.0[picture_offset..])
+    else {
+        return packet.0[..packet.1].to_vec();
+    };
+
+    // Buy levels are followed by sell levels
+    let levels = (picture.buy_depth_count + picture.sell_depth_count)
+        .clamp(0, MAX_MARKET_DEPTH_IDX as i32) as usize;
+    let len = picture_offset
+        + offset_of!(TagMarketPictureBroadcast, market_depth_info)
+        + levels * size_of::<TagMarketDepthInfo>();
+
+    packet.0[..len].to_vec()
+}
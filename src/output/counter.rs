@@ -21,7 +21,7 @@ impl Counter {
 }
 
 impl OutputTrait for Counter {
-    fn write(&mut self, _data: &Packet) {
+    fn write(&mut self, _data: &Packet) -> bool {
         self.i += 1;
 
         if self.i % self.step == 0 {
@@ -29,5 +29,7 @@ impl OutputTrait for Counter {
 
             self.time = Instant::now();
         }
+
+        true
     }
 }
@@ -0,0 +1,71 @@
+use std::mem::{offset_of, size_of};
+
+use crate::{
+    constants::ENRICHED_PICTURE_CODE,
+    global::SECURITY_MASTER,
+    types::{
+        packet::Packet,
+        packet_structures::{
+            depth_output::{TagMarketPictureBroadcast, TagMessageHeader},
+            enriched_output::{TagEnrichedMarketPicture, TagInstrument},
+        },
+        security_master::Instrument,
+    },
+    utils::byte_utils::{bytes_to_struct, struct_to_bytes},
+};
+
+// Wraps market picture with its instrument details
+// Returns None if token is not in security master
+pub fn enrich(packet: &Packet) -> Option<Packet> {
+    let token: i64 =
+        bytes_to_struct(&packet.0[offset_of!(TagMarketPictureBroadcast, token)..]).ok()?;
+    let instrument = SECURITY_MASTER.get(token)?;
+
+    let picture_offset = offset_of!(TagEnrichedMarketPicture, market_picture);
+
+    if picture_offset + packet.1 > size_of::<TagEnrichedMarketPicture>() {
+        return None;
+    }
+
+    let mut msg_header: TagMessageHeader = bytes_to_struct(&packet.0).ok()?;
+    msg_header.message_code = ENRICHED_PICTURE_CODE;
+    msg_header.message_length = (picture_offset + packet.1) as i16;
+
+    // Encoders read whole struct
+    let mut enriched = Packet::new(size_of::<TagEnrichedMarketPicture>());
+    enriched.1 = picture_offset + packet.1;
+    enriched.2 = packet.2;
+
+    struct_to_bytes(&msg_header, &mut enriched.0);
+    struct_to_bytes(
+        &to_tag_instrument(&instrument),
+        &mut enriched.0[size_of::<TagMessageHeader>()..],
+    );
+    // Depth of picture is already trimmed, so it is copied as is
+    enriched.0[picture_offset..picture_offset + packet.1].copy_from_slice(&packet.0[..packet.1]);
+
+    Some(enriched)
+}
+
+pub fn to_tag_instrument(instrument: &Instrument) -> TagInstrument {
+    TagInstrument {
+        symbol: to_fixed(&instrument.symbol),
+        series: to_fixed(&instrument.series),
+        option_type: to_fixed(&instrument.option_type),
+        expiry: instrument.expiry,
+        strike_price: instrument.strike_price,
+        tick_size: instrument.tick_size,
+        lot_size: instrument.lot_size,
+        price_multiplier: instrument.price_multiplier,
+    }
+}
+
+// Null padded, longer strings are cut
+pub fn to_fixed<const N: usize>(value: &str) -> [u8; N] {
+    let mut fixed = [0; N];
+    let len = value.len().min(N);
+
+    fixed[..len].copy_from_slice(&value.as_bytes()[..len]);
+
+    fixed
+}
@@ -0,0 +1,126 @@
+use serde_json::Value;
+
+use crate::{
+    constants::{ENRICHED_PICTURE_CODE, GAP_EVENT_CODE, MASTER_CHANGE_CODE},
+    settings,
+    types::{
+        packet::Packet,
+        packet_structures::{
+            depth_output::TagMarketPictureBroadcast, enriched_output::TagEnrichedMarketPicture,
+            gap_event::TagGapEvent, master_change::TagMasterChange,
+        },
+        settings::Exchange,
+    },
+    utils::{
+        byte_utils::bytes_to_struct_ptr,
+        price_utils::{to_decimal, token_price_multiplier},
+    },
+};
+
+use super::depth_message_codes;
+
+// Fields of market picture which are in exchange price units
+const PICTURE_PRICE_FIELDS: [&str; 7] = [
+    "open_price",
+    "close_price",
+    "high_price",
+    "low_price",
+    "ltp",
+    "atp",
+    "indicative_close_price",
+];
+const INSTRUMENT_PRICE_FIELDS: [&str; 2] = ["strike_price", "tick_size"];
+
+// Writes market pictures, gap events and master changes as json
+pub struct JsonEncoder {
+    exchange: Exchange,
+    depth_codes: Vec<i32>,
+    // Prices are divided by price multiplier of token, instead of exchange units
+    decimal_prices: bool,
+}
+
+impl JsonEncoder {
+    pub fn new() -> Self {
+        let settings = settings::get();
+
+        Self {
+            exchange: settings.exchange,
+            depth_codes: depth_message_codes(settings.exchange),
+            decimal_prices: settings.decimal_prices,
+        }
+    }
+
+    // Returns None for other messages
+    pub fn encode(&self, packet: &Packet) -> Option<String> {
+        let message_code = i32::from_le_bytes(packet.0[0..4].try_into().unwrap());
+
+        let value = if message_code == ENRICHED_PICTURE_CODE {
+            let enriched = bytes_to_struct_ptr::<TagEnrichedMarketPicture>(&packet.0).ok()?;
+            let mut value = serde_json::to_value(enriched).unwrap();
+
+            if self.decimal_prices {
+                let multiplier = enriched.instrument.price_multiplier;
+
+                scale_picture(&mut value["market_picture"], multiplier);
+                scale_fields(
+                    &mut value["instrument"],
+                    &INSTRUMENT_PRICE_FIELDS,
+                    multiplier,
+                );
+            }
+
+            value
+        } else if self.depth_codes.contains(&message_code) {
+            let picture = bytes_to_struct_ptr::<TagMarketPictureBroadcast>(&packet.0).ok()?;
+            let mut value = serde_json::to_value(picture).unwrap();
+
+            if self.decimal_prices {
+                let multiplier = token_price_multiplier(picture.token, self.exchange);
+
+                scale_picture(&mut value, multiplier);
+            }
+
+            value
+        } else if message_code == MASTER_CHANGE_CODE {
+            let change = bytes_to_struct_ptr::<TagMasterChange>(&packet.0).ok()?;
+            let mut value = serde_json::to_value(change).unwrap();
+
+            // Instrument is zero for other masters
+            let multiplier = change.instrument.price_multiplier;
+
+            if self.decimal_prices && multiplier != 0 {
+                scale_fields(
+                    &mut value["instrument"],
+                    &INSTRUMENT_PRICE_FIELDS,
+                    multiplier,
+                );
+            }
+
+            value
+        } else if message_code == GAP_EVENT_CODE {
+            serde_json::to_value(bytes_to_struct_ptr::<TagGapEvent>(&packet.0).ok()?).unwrap()
+        } else {
+            return None;
+        };
+
+        Some(value.to_string())
+    }
+}
+
+fn scale_picture(picture: &mut Value, multiplier: i64) {
+    scale_fields(picture, &PICTURE_PRICE_FIELDS, multiplier);
+
+    if let Some(depth) = picture["market_depth_info"].as_array_mut() {
+        for level in depth {
+            scale_fields(level, &["price"], multiplier);
+        }
+    }
+}
+
+fn scale_fields(value: &mut Value, fields: &[&str], multiplier: i64) {
+    for field in fields {
+        if let Some(units) = value[field].as_i64() {
+            value[field] = Value::from(to_decimal(units, multiplier));
+        }
+    }
+}
@@ -1,18 +1,100 @@
-use std::{collections::HashMap, fs};
+use std::{
+    collections::HashMap,
+    fs,
+    mem::offset_of,
+    sync::{atomic::Ordering, Arc},
+    thread,
+    time::Duration,
+};
 
 use rdkafka::{
-    producer::{BaseRecord, DefaultProducerContext, NoCustomPartitioner, ThreadedProducer},
-    ClientConfig,
+    error::{KafkaError, RDKafkaErrorCode},
+    message::{Header, OwnedHeaders},
+    producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer},
+    ClientConfig, ClientContext,
+};
+
+use crate::{
+    constants::{
+        ENRICHED_PICTURE_CODE, KAFKA_FLUSH_TIMEOUT, KAFKA_MAX_RETRY_BACKOFF, MASTER_CHANGE_CODE,
+    },
+    global::STATISTICS,
+    settings,
+    types::{
+        message_class::{MessageClass, MessageClassifier},
+        packet::Packet,
+        packet_structures::{
+            depth_output::TagMarketPictureBroadcast, enriched_output::TagEnrichedMarketPicture,
+            master_change::TagMasterChange,
+        },
+        settings::{KafkaQueueFullConfig, KafkaQueueFullPolicy},
+    },
+    utils::byte_utils::bytes_to_struct,
+};
+
+use super::{
+    json::JsonEncoder,
+    kafka_spill::{InFlight, Spill},
+    OutputTrait,
 };
 
-use crate::{settings, types::packet::Packet};
+// Delivery opaque of records which weren't spilled
+const NOT_SPILLED: usize = 0;
+
+// Counts delivery reports and tracks broker health, called from producer thread
+pub struct KafkaContext {
+    // Told about delivery of spilled records, so they aren't sent again after restart
+    spill: Option<Arc<InFlight>>,
+}
+
+impl ClientContext for KafkaContext {
+    fn error(&self, error: KafkaError, reason: &str) {
+        if error.rdkafka_error_code() == Some(RDKafkaErrorCode::AllBrokersDown) {
+            STATISTICS
+                .get()
+                .kafka_brokers_down
+                .store(true, Ordering::Relaxed);
+        }
+
+        println!("Kafka error: {}: {}", error, reason);
+    }
+}
 
-use super::OutputTrait;
+impl ProducerContext for KafkaContext {
+    // Id of spilled record, else NOT_SPILLED
+    type DeliveryOpaque = usize;
+
+    fn delivery(&self, delivery_result: &DeliveryResult<'_>, id: Self::DeliveryOpaque) {
+        let stats = STATISTICS.get();
+
+        // Failed records aren't sent again, same as records which weren't spilled
+        if let (Some(spill), true) = (&self.spill, id != NOT_SPILLED) {
+            spill.delivered(id);
+        }
+
+        match delivery_result {
+            Ok(_) => {
+                stats.kafka_acked_count.inc();
+                stats.kafka_brokers_down.store(false, Ordering::Relaxed);
+            }
+            Err(_) => stats.kafka_failed_count.inc(),
+        }
+    }
+}
 
 pub struct KafkaOutput {
-    producer: ThreadedProducer<DefaultProducerContext, NoCustomPartitioner>,
-    topic_name: String,
-    partition_no: i32,
+    producer: ThreadedProducer<KafkaContext>,
+    queue_full: KafkaQueueFullConfig,
+    spill: Option<Spill>,
+    // Topic of each message class, others go to default topic
+    topics: HashMap<MessageClass, String>,
+    default_topic: String,
+    // Records are partitioned by hash of key if not set
+    partition_no: Option<i32>,
+    classifier: MessageClassifier,
+    exchange: String,
+    // Market pictures are written as json, if enabled
+    encoder: Option<JsonEncoder>,
 }
 
 impl KafkaOutput {
@@ -30,29 +112,280 @@ impl KafkaOutput {
             config = config.set(key, val);
         }
 
+        let queue_full = settings.kafka_queue_full.clone();
+
+        // Records left from last run are sent by output thread, before new ones
+        let spill = if queue_full.policy == KafkaQueueFullPolicy::Spill {
+            let path = queue_full
+                .spill_file
+                .as_ref()
+                .expect("Please provide `kafka_queue_full.spill_file` in config");
+
+            Some(Spill::open(path))
+        } else {
+            None
+        };
+
         // Build producer from config
-        let producer: ThreadedProducer<DefaultProducerContext, NoCustomPartitioner> = config
+        let context = KafkaContext {
+            spill: spill.as_ref().map(Spill::in_flight),
+        };
+        let producer: ThreadedProducer<KafkaContext> = config
             .set("bootstrap.servers", &settings.kafka_brokers)
-            .create()
+            .create_with_context(context)
             .expect("Producer creation failed");
 
+        let encoder = if settings.json_output {
+            Some(JsonEncoder::new())
+        } else {
+            None
+        };
+
         KafkaOutput {
             producer,
-            partition_no: settings.kafka_partition_no as i32,
-            topic_name: settings.kafka_topic_name.clone(),
+            queue_full,
+            spill,
+            encoder,
+            topics: settings.kafka_topics.clone(),
+            default_topic: settings.kafka_topic_name.clone(),
+            partition_no: settings
+                .kafka_partition_no
+                .map(|partition_no| partition_no as i32),
+            classifier: MessageClassifier::new(settings.exchange),
+            exchange: format!("{:?}", settings.exchange),
+        }
+    }
+
+    // Token of market pictures and securities, so records of an instrument stay in order
+    fn key(&self, packet: &Packet, message_code: i32, class: MessageClass) -> Option<i64> {
+        match (class, message_code) {
+            (MessageClass::Depth, ENRICHED_PICTURE_CODE) => bytes_to_struct(
+                &packet.0[offset_of!(TagEnrichedMarketPicture, market_picture)
+                    + offset_of!(TagMarketPictureBroadcast, token)..],
+            )
+            .ok(),
+            (MessageClass::Depth, _) => {
+                bytes_to_struct(&packet.0[offset_of!(TagMarketPictureBroadcast, token)..]).ok()
+            }
+            (_, MASTER_CHANGE_CODE) => {
+                let change: TagMasterChange = bytes_to_struct(&packet.0).ok()?;
+
+                // Security and spread
+                matches!(change.master, 1 | 4).then_some(change.id)
+            }
+            _ => None,
         }
     }
+
+    // Sends spilled records in order, till producer queue is full
+    // Doesn't wait for room, rest is sent on next write or poll
+    fn drain_spill(&mut self) {
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+
+        while let Some(record) = spill.peek() {
+            let mut payload = BaseRecord::with_opaque_to(record.topic, record.id)
+                .payload(record.payload)
+                .headers(to_owned_headers(&record.headers));
+            if let Some(key) = record.key {
+                payload = payload.key(key);
+            }
+            if let Some(partition) = record.partition {
+                payload = payload.partition(partition);
+            }
+
+            match self.producer.send(payload) {
+                Ok(()) => spill.pop(),
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), _)) => {
+                    STATISTICS.get().kafka_queue_full_count.inc();
+                    break;
+                }
+                // Kept in spill, so later records don't overtake it
+                Err((e, _)) => {
+                    println!("Error sending spilled kafka record: {}", e);
+                    break;
+                }
+            }
+        }
+
+        if let Err(e) = spill.commit() {
+            println!("Error saving kafka spill file position: {}", e);
+        }
+    }
+
+    fn headers(&self, packet: &Packet, message_code: i32) -> Vec<(&'static str, String)> {
+        vec![
+            ("exchange", self.exchange.clone()),
+            ("trans_code", message_code.to_string()),
+            // Epoch microseconds when datagram was received, or event was generated
+            ("receive_ts", packet.2.to_string()),
+        ]
+    }
+}
+
+pub fn to_owned_headers<K: AsRef<str>, V: AsRef<str>>(headers: &[(K, V)]) -> OwnedHeaders {
+    headers.iter().fold(
+        OwnedHeaders::new_with_capacity(headers.len()),
+        |owned, (key, value)| {
+            owned.insert(Header {
+                key: key.as_ref(),
+                value: Some(value.as_ref()),
+            })
+        },
+    )
 }
 
 impl OutputTrait for KafkaOutput {
-    fn write(&mut self, data: &Packet) {
-        let slice = &data.0[..data.1];
+    fn write(&mut self, data: &Packet) -> bool {
+        self.drain_spill();
+
+        let json = self
+            .encoder
+            .as_ref()
+            .and_then(|encoder| encoder.encode(data));
+        let slice = match &json {
+            Some(json) => json.as_bytes(),
+            None => &data.0[..data.1],
+        };
+
+        let message_code = self.classifier.message_code(data);
+        let class = self.classifier.class(message_code);
+
+        let topic = self.topics.get(&class).unwrap_or(&self.default_topic);
+        let key = self
+            .key(data, message_code, class)
+            .map(|token| token.to_string());
+
+        let headers = self.headers(data, message_code);
+
+        // Spilled records must be sent first, so records of a key stay in order
+        if let Some(spill_file) = self.spill.as_mut().filter(|spill| !spill.is_empty()) {
+            return spill(
+                spill_file,
+                topic,
+                key.as_deref(),
+                self.partition_no,
+                &headers,
+                slice,
+            );
+        }
+
+        let mut payload = BaseRecord::with_opaque_to(topic, NOT_SPILLED)
+            .payload(slice)
+            .headers(to_owned_headers(&headers));
+
+        // Default partitioner hashes key, records without key are spread randomly
+        if let Some(key) = &key {
+            payload = payload.key(key);
+        }
+        if let Some(partition_no) = self.partition_no {
+            payload = payload.partition(partition_no);
+        }
+
+        let stats = STATISTICS.get();
+        let mut backoff = self.queue_full.retry_backoff_ms;
+        let mut retries = 0;
+
+        let sent = loop {
+            match self.producer.send(payload) {
+                Ok(()) => break true,
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), returned)) => {
+                    stats.kafka_queue_full_count.inc();
+
+                    match (self.queue_full.policy, &mut self.spill) {
+                        (KafkaQueueFullPolicy::Retry, _)
+                            if retries < self.queue_full.max_retries =>
+                        {
+                            // Producer thread keeps delivering meanwhile
+                            thread::sleep(Duration::from_millis(backoff));
+                            backoff = (backoff * 2).min(KAFKA_MAX_RETRY_BACKOFF);
+                            retries += 1;
+
+                            payload = returned;
+                        }
+                        (KafkaQueueFullPolicy::Spill, Some(spill_file)) => {
+                            break spill(
+                                spill_file,
+                                topic,
+                                key.as_deref(),
+                                self.partition_no,
+                                &headers,
+                                slice,
+                            );
+                        }
+                        _ => {
+                            stats.kafka_dropped_count.inc();
+                            stats.dropped_count.inc();
+                            break false;
+                        }
+                    }
+                }
+                Err((e, _)) => {
+                    println!("Error writing kafka output: {}", e);
+                    break false;
+                }
+            }
+        };
 
-        let payload = BaseRecord::to(&self.topic_name)
-            .partition(self.partition_no)
-            .key(&())
-            .payload(slice);
+        stats
+            .kafka_in_flight
+            .store(self.producer.in_flight_count() as u64, Ordering::Relaxed);
 
-        self.producer.send(payload).unwrap();
+        sent
     }
+
+    fn poll(&mut self) {
+        self.drain_spill();
+    }
+
+    fn close(&mut self) -> bool {
+        self.drain_spill();
+
+        // Wait for queued messages to be delivered
+        let flushed = self
+            .producer
+            .flush(Duration::from_secs(KAFKA_FLUSH_TIMEOUT));
+
+        // Unsent and undelivered records stay in file, for next start
+        if let Some(spill) = &mut self.spill {
+            if let Err(e) = spill.commit().and_then(|_| spill.flush()) {
+                println!("Error flushing kafka spill file: {}", e);
+            }
+
+            if !spill.is_empty() {
+                println!("Kafka records left in spill file");
+            }
+        }
+
+        if let Err(e) = flushed {
+            println!("Error flushing kafka output: {}", e);
+            return false;
+        }
+
+        true
+    }
+}
+
+// Returns false if record couldn't be written to spill file
+fn spill(
+    spill: &mut Spill,
+    topic: &str,
+    key: Option<&str>,
+    partition: Option<i32>,
+    headers: &[(&str, String)],
+    payload: &[u8],
+) -> bool {
+    let stats = STATISTICS.get();
+
+    if let Err(e) = spill.write(topic, key, partition, headers, payload) {
+        println!("Error writing kafka spill file: {}", e);
+        stats.kafka_dropped_count.inc();
+        stats.dropped_count.inc();
+        return false;
+    }
+
+    stats.kafka_spilled_count.inc();
+
+    true
 }
@@ -0,0 +1,399 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    os::unix::fs::FileExt,
+    sync::{Arc, Mutex},
+};
+
+use crate::utils::byte_utils::try_bytes_to_struct;
+
+// Key length of records without key
+const NO_KEY: u32 = u32::MAX;
+// Partition of records partitioned by key
+const NO_PARTITION: i32 = -1;
+
+// Spilled records given to producer, by end offset, till their delivery is reported
+// Shared with delivery callback, which runs on producer thread
+#[derive(Default)]
+pub struct InFlight(Mutex<BTreeMap<u64, u64>>);
+
+impl InFlight {
+    fn sent(&self, start: u64, end: u64) {
+        self.0.lock().unwrap().insert(end, start);
+    }
+
+    // Called with id of record, for delivered and failed records
+    pub fn delivered(&self, id: usize) {
+        self.0.lock().unwrap().remove(&(id as u64));
+    }
+
+    // Start offset of first record not yet delivered
+    fn first(&self) -> Option<u64> {
+        self.0.lock().unwrap().values().next().copied()
+    }
+}
+
+// Kafka records which didn't fit in producer queue, sent in order once there is room
+// File is a queue, records are appended at end and sent from front
+// Each record and each of its fields is prefixed by its u32 length in little endian:
+// topic, key, partition (i32, no prefix), header count (no prefix), header names and values, payload
+// Offset till which records are delivered is kept in <path>.pos, so a restart doesn't send them again
+pub struct Spill {
+    file: File,
+    pos_file: File,
+    // Offset of next record to send
+    read_pos: u64,
+    len: u64,
+    // Offset saved in pos file
+    delivered: u64,
+    // Record at read_pos, kept till it is sent
+    next: Option<Vec<u8>>,
+    in_flight: Arc<InFlight>,
+}
+
+impl Spill {
+    // Records left by previous run are sent first
+    pub fn open(path: &str) -> Self {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .expect("Unable to open kafka spill file");
+
+        let len = file
+            .metadata()
+            .expect("Unable to read kafka spill file")
+            .len();
+
+        let pos_path = format!("{}.pos", path);
+        let pos_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&pos_path)
+            .expect("Unable to open kafka spill pos file");
+
+        // Pos beyond file is left from a run which emptied file, before pos was saved
+        let delivered = fs::read(&pos_path)
+            .ok()
+            .and_then(|pos| try_bytes_to_struct::<u64>(&pos, 0).ok())
+            .filter(|&pos| pos <= len)
+            .unwrap_or(0);
+
+        if len > delivered {
+            println!(
+                "Sending {} bytes of kafka records left in spill file",
+                len - delivered
+            );
+        }
+
+        Self {
+            file,
+            pos_file,
+            read_pos: delivered,
+            len,
+            delivered,
+            next: None,
+            in_flight: Arc::new(InFlight::default()),
+        }
+    }
+
+    pub fn in_flight(&self) -> Arc<InFlight> {
+        self.in_flight.clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read_pos == self.len
+    }
+
+    pub fn write(
+        &mut self,
+        topic: &str,
+        key: Option<&str>,
+        partition: Option<i32>,
+        headers: &[(&str, String)],
+        payload: &[u8],
+    ) -> io::Result<()> {
+        // Length of record is filled at end
+        let mut record = vec![0; 4];
+
+        put(&mut record, topic.as_bytes());
+        match key {
+            Some(key) => put(&mut record, key.as_bytes()),
+            None => record.extend_from_slice(&NO_KEY.to_le_bytes()),
+        }
+        record.extend_from_slice(&partition.unwrap_or(NO_PARTITION).to_le_bytes());
+        record.extend_from_slice(&(headers.len() as u32).to_le_bytes());
+        for (name, value) in headers {
+            put(&mut record, name.as_bytes());
+            put(&mut record, value.as_bytes());
+        }
+        put(&mut record, payload);
+
+        let record_len = (record.len() - 4) as u32;
+        record[..4].copy_from_slice(&record_len.to_le_bytes());
+
+        // Whole record in one write, so a crash can only cut the last one
+        self.file.write_all(&record)?;
+        self.len += record.len() as u64;
+
+        Ok(())
+    }
+
+    // Next record to send, None if spill is empty
+    // A record cut by crash ends the file, it is discarded
+    pub fn peek(&mut self) -> Option<SpilledRecord<'_>> {
+        if self.is_empty() {
+            return None;
+        }
+
+        if self.next.is_none() {
+            match self.read_next() {
+                Ok(next) => self.next = Some(next),
+                Err(e) => {
+                    println!("Discarding kafka spill file from {}: {}", self.read_pos, e);
+
+                    self.len = self.read_pos;
+                    if let Err(e) = self.file.set_len(self.len) {
+                        println!("Error truncating kafka spill file: {}", e);
+                    }
+
+                    return None;
+                }
+            }
+        }
+
+        let next = self.next.as_ref().unwrap();
+        let id = (self.read_pos + 4 + next.len() as u64) as usize;
+
+        SpillReader { data: next, pos: 0 }.record(id)
+    }
+
+    // Called once record from peek is in producer queue
+    pub fn pop(&mut self) {
+        if let Some(next) = self.next.take() {
+            let start = self.read_pos;
+            self.read_pos += 4 + next.len() as u64;
+
+            self.in_flight.sent(start, self.read_pos);
+        }
+    }
+
+    // Saves offset till which records are delivered, and empties file once all are
+    // Records not yet delivered are sent again if process dies
+    pub fn commit(&mut self) -> io::Result<()> {
+        let delivered = self.in_flight.first().unwrap_or(self.read_pos);
+
+        if delivered == self.delivered {
+            return Ok(());
+        }
+
+        if delivered == self.len {
+            self.file.set_len(0)?;
+            self.read_pos = 0;
+            self.len = 0;
+        }
+
+        let delivered = delivered.min(self.len);
+        self.pos_file.write_all_at(&delivered.to_le_bytes(), 0)?;
+        self.delivered = delivered;
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.pos_file.sync_data()
+    }
+
+    fn read_next(&self) -> io::Result<Vec<u8>> {
+        let mut len = [0; 4];
+        self.file.read_exact_at(&mut len, self.read_pos)?;
+
+        let mut next = vec![0; u32::from_le_bytes(len) as usize];
+        self.file.read_exact_at(&mut next, self.read_pos + 4)?;
+
+        let mut reader = SpillReader {
+            data: &next,
+            pos: 0,
+        };
+        if reader.record(0).is_none() || reader.pos != next.len() {
+            return Err(io::Error::new(ErrorKind::InvalidData, "malformed record"));
+        }
+
+        Ok(next)
+    }
+}
+
+fn put(record: &mut Vec<u8>, field: &[u8]) {
+    record.extend_from_slice(&(field.len() as u32).to_le_bytes());
+    record.extend_from_slice(field);
+}
+
+pub struct SpilledRecord<'a> {
+    // Passed to InFlight::delivered once record is delivered
+    pub id: usize,
+    pub topic: &'a str,
+    pub key: Option<&'a str>,
+    pub partition: Option<i32>,
+    pub headers: Vec<(&'a str, &'a str)>,
+    pub payload: &'a [u8],
+}
+
+struct SpillReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SpillReader<'a> {
+    fn len(&mut self) -> Option<u32> {
+        let len: u32 = try_bytes_to_struct(self.data, self.pos).ok()?;
+        self.pos += 4;
+
+        Some(len)
+    }
+
+    fn bytes(&mut self, len: u32) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + len as usize)?;
+        self.pos += len as usize;
+
+        Some(bytes)
+    }
+
+    fn field(&mut self) -> Option<&'a [u8]> {
+        let len = self.len()?;
+
+        self.bytes(len)
+    }
+
+    fn text(&mut self) -> Option<&'a str> {
+        std::str::from_utf8(self.field()?).ok()
+    }
+
+    fn record(&mut self, id: usize) -> Option<SpilledRecord<'a>> {
+        let topic = self.text()?;
+
+        let key = match self.len()? {
+            NO_KEY => None,
+            len => Some(std::str::from_utf8(self.bytes(len)?).ok()?),
+        };
+
+        let partition = match self.len()? as i32 {
+            NO_PARTITION => None,
+            partition => Some(partition),
+        };
+
+        let header_count = self.len()?;
+        let mut headers = vec![];
+        for _ in 0..header_count {
+            headers.push((self.text()?, self.text()?));
+        }
+
+        let payload = self.field()?;
+
+        Some(SpilledRecord {
+            id,
+            topic,
+            key,
+            partition,
+            headers,
+            payload,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spill_path(name: &str) -> String {
+        let path =
+            std::env::temp_dir().join(format!("kafka_spill_{}_{}", name, std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(format!("{}.pos", path));
+
+        path
+    }
+
+    fn write(spill: &mut Spill, payload: &[u8]) {
+        let headers = [("trans_code", "7208".to_string())];
+
+        spill
+            .write("depth", Some("42"), None, &headers, payload)
+            .unwrap();
+    }
+
+    // Sends next record, returns its id and payload
+    fn send(spill: &mut Spill) -> (usize, Vec<u8>) {
+        let record = spill.peek().unwrap();
+        let sent = (record.id, record.payload.to_vec());
+
+        spill.pop();
+
+        sent
+    }
+
+    #[test]
+    fn delivered_records_not_sent_after_reopen() {
+        let path = spill_path("reopen");
+
+        let mut spill = Spill::open(&path);
+        write(&mut spill, b"first");
+        write(&mut spill, b"second");
+        write(&mut spill, b"third");
+
+        let (first, _) = send(&mut spill);
+        let (second, _) = send(&mut spill);
+
+        // Second is delivered before first, so nothing is committed
+        spill.in_flight().delivered(second);
+        spill.commit().unwrap();
+        drop(spill);
+
+        let mut spill = Spill::open(&path);
+        assert_eq!(send(&mut spill).1, b"first");
+        drop(spill);
+
+        let mut spill = Spill::open(&path);
+        send(&mut spill);
+        let (second, _) = send(&mut spill);
+        spill.in_flight().delivered(first);
+        spill.in_flight().delivered(second);
+        spill.commit().unwrap();
+        drop(spill);
+
+        let mut spill = Spill::open(&path);
+        let record = spill.peek().unwrap();
+        assert_eq!(record.payload, b"third");
+        assert_eq!(record.key, Some("42"));
+        assert_eq!(record.headers, vec![("trans_code", "7208")]);
+    }
+
+    #[test]
+    fn file_emptied_once_all_delivered() {
+        let path = spill_path("empty");
+
+        let mut spill = Spill::open(&path);
+        write(&mut spill, b"first");
+
+        let (first, _) = send(&mut spill);
+        spill.commit().unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), spill.len);
+
+        spill.in_flight().delivered(first);
+        spill.commit().unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+        assert!(spill.is_empty());
+
+        write(&mut spill, b"second");
+        drop(spill);
+
+        let mut spill = Spill::open(&path);
+        assert_eq!(send(&mut spill).1, b"second");
+    }
+}
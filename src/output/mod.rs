@@ -1,12 +1,20 @@
+pub mod binary;
 pub mod counter;
+pub mod enrich;
+pub mod json;
 pub mod kafka_output;
+pub mod kafka_spill;
 pub mod std_out;
 pub mod udp_output;
 pub mod ws;
+pub mod ws_client;
 
 use std::{
-    cell::UnsafeCell,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
 };
 
 use counter::Counter;
@@ -16,87 +24,241 @@ use udp_output::UdpOutput;
 use ws::Ws;
 
 use crate::{
+    constants::{
+        BCAST_MBO_MBP, BCAST_ONLY_MBP, BCAST_ONLY_MBP_EQ, BSE_BCAST_MBP, OUTPUT_NAMES,
+        SNAPSHOT_TEMPLATE_ID,
+    },
+    global::STATISTICS,
     settings,
-    types::{packet::Packet, settings::OutputTargets},
+    types::{
+        bounded_queue::{BoundedQueue, QueueKind},
+        packet::Packet,
+        settings::{Exchange, OutputTargets, WaitStrategy},
+        wait::{Waiter, Wakeup},
+    },
 };
 
+// Fans out packets to enabled outputs
+// Each output has its own queue and thread, so a slow output doesn't hold up others
 pub struct Output {
-    kafka: Option<UnsafeCell<KafkaOutput>>,
-    ws: Option<UnsafeCell<Ws>>,
-    udp: UnsafeCell<UdpOutput>,
-    stdout: UnsafeCell<StdOut>,
-    counter: UnsafeCell<Counter>,
-    lock: AtomicBool,
-    output_targets: OutputTargets,
+    channels: Vec<OutputChannel>,
+    // Message codes enriched with instrument details, empty if not enabled
+    enriched_codes: Vec<i32>,
 }
 
-unsafe impl Send for Output {}
-unsafe impl Sync for Output {}
-
 trait OutputTrait {
-    fn write(&mut self, data: &Packet);
+    // Returns false if packet couldn't be written
+    fn write(&mut self, data: &Packet) -> bool;
+
+    // Called when queue is empty, for outputs with pending work of their own
+    fn poll(&mut self) {}
+
+    // Delivers pending data before exit, returns false if some was lost
+    fn close(&mut self) -> bool {
+        true
+    }
 }
 
+// Index of each output in statistics, as per OUTPUT_NAMES
+const UDP_IDX: usize = 0;
+const KAFKA_IDX: usize = 1;
+const STDOUT_IDX: usize = 2;
+const COUNTER_IDX: usize = 3;
+const WS_IDX: usize = 4;
+
 impl Output {
     pub fn new() -> Self {
         let settings = settings::get();
+        let targets = &settings.output_targets;
+        let wait_strategy = settings.wait_strategy.outputs;
 
-        let kafka = if settings.output_targets.contains(OutputTargets::KAFKA) {
-            Some(UnsafeCell::new(KafkaOutput::new()))
-        } else {
-            None
-        };
-        let udp = UnsafeCell::new(UdpOutput::new());
-        let stdout = UnsafeCell::new(StdOut::new());
-        let counter = UnsafeCell::new(Counter::new(settings.steps));
+        let mut channels = vec![];
+
+        if targets.contains(OutputTargets::UDP) {
+            channels.push(OutputChannel::new(UDP_IDX, UdpOutput::new(), wait_strategy));
+        }
+
+        if targets.contains(OutputTargets::KAFKA) {
+            channels.push(OutputChannel::new(
+                KAFKA_IDX,
+                KafkaOutput::new(),
+                wait_strategy,
+            ));
+        }
+
+        if targets.contains(OutputTargets::STDOUT) {
+            channels.push(OutputChannel::new(STDOUT_IDX, StdOut::new(), wait_strategy));
+        }
+
+        if targets.contains(OutputTargets::COUNTER) {
+            channels.push(OutputChannel::new(
+                COUNTER_IDX,
+                Counter::new(settings.steps),
+                wait_strategy,
+            ));
+        }
 
-        let ws = if settings.output_targets.contains(OutputTargets::WS) {
-            Some(UnsafeCell::new(Ws::new()))
+        if targets.contains(OutputTargets::WS) {
+            channels.push(OutputChannel::new(WS_IDX, Ws::new(), wait_strategy));
+        }
+
+        let enriched_codes = if settings.enriched_output {
+            depth_message_codes(settings.exchange)
         } else {
-            None
+            vec![]
         };
-        let output_targets = settings::get().output_targets.clone();
 
         Self {
-            kafka,
-            udp,
-            stdout,
-            counter,
-            ws,
-            output_targets,
-            lock: AtomicBool::new(false),
+            channels,
+            enriched_codes,
         }
     }
 
+    // Packets of a token are written by one worker, so each output gets them in order
     pub fn write(&self, packet: &Packet) {
-        unsafe {
-            // Acquire lock
-            while self.lock.swap(true, Ordering::Relaxed) == true {}
+        // Depth is written with instrument details, if token is in security master
+        let enriched = if self.enriched_codes.is_empty() {
+            None
+        } else {
+            let message_code = i32::from_le_bytes(packet.0[0..4].try_into().unwrap());
 
-            if self.output_targets.contains(OutputTargets::UDP) {
-                (*self.udp.get()).write(packet);
+            if self.enriched_codes.contains(&message_code) {
+                enrich::enrich(packet)
+            } else {
+                None
             }
+        };
+        let packet = enriched.as_ref().unwrap_or(packet);
 
-            if self.output_targets.contains(OutputTargets::KAFKA) {
-                (*self.kafka.as_ref().unwrap().get()).write(packet);
-            }
+        // Only buffer reference is cloned
+        for channel in &self.channels {
+            channel.push(packet.clone());
+        }
+    }
 
-            if self.output_targets.contains(OutputTargets::STDOUT) {
-                (*self.stdout.get()).write(packet);
-            }
+    // Called once on shutdown, after all workers have exited
+    // Each output delivers its queue and is closed
+    pub fn close(&self) -> bool {
+        let mut closed = true;
 
-            if self.output_targets.contains(OutputTargets::COUNTER) {
-                (*self.counter.get()).write(packet);
-            }
+        for channel in &self.channels {
+            closed &= channel.close();
+        }
 
-            if self.output_targets.contains(OutputTargets::WS) {
-                (*self.ws.as_ref().unwrap().get()).write(packet);
-            }
+        closed
+    }
 
-            // release lock
-            self.lock.store(false, Ordering::Relaxed);
-        }
+    // Packets waiting in queue of each output, by output index
+    pub fn queue_depths(&self) -> Vec<(usize, usize)> {
+        self.channels
+            .iter()
+            .map(|channel| (channel.idx, channel.queue.len()))
+            .collect()
     }
 
     pub fn touch(&self) {}
 }
+
+struct OutputChannel {
+    idx: usize,
+    queue: Arc<BoundedQueue<Packet>>,
+    wakeup: Arc<Wakeup>,
+    stop: Arc<AtomicBool>,
+    // Returns result of close, taken on close
+    thread: Mutex<Option<JoinHandle<bool>>>,
+}
+
+impl OutputChannel {
+    fn new(
+        idx: usize,
+        mut output: impl OutputTrait + Send + 'static,
+        wait_strategy: WaitStrategy,
+    ) -> Self {
+        let queue = Arc::new(BoundedQueue::new(QueueKind::Output));
+        let wakeup = Arc::new(Wakeup::new());
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let queue = queue.clone();
+            let wakeup = wakeup.clone();
+            let stop = stop.clone();
+
+            thread::Builder::new()
+                .name(format!("output-{}", OUTPUT_NAMES[idx]))
+                .spawn(move || {
+                    wakeup.register();
+
+                    let mut waiter = Waiter::new(wait_strategy);
+
+                    loop {
+                        if let Some(packet) = queue.pop() {
+                            waiter.reset();
+                            Self::write_to(idx, &mut output, &packet);
+                        } else if stop.load(Ordering::SeqCst) {
+                            // Nothing is pushed after stop, so queue is drained
+                            break;
+                        } else {
+                            output.poll();
+                            waiter.wait(Some(&wakeup), || !queue.is_empty());
+                        }
+                    }
+
+                    output.close()
+                })
+                .expect("Unable to spawn output thread")
+        };
+
+        Self {
+            idx,
+            queue,
+            wakeup,
+            stop,
+            thread: Mutex::new(Some(thread)),
+        }
+    }
+
+    fn push(&self, packet: Packet) {
+        self.queue.push(packet);
+        self.wakeup.wake();
+    }
+
+    fn write_to(idx: usize, output: &mut impl OutputTrait, packet: &Packet) {
+        if output.write(packet) {
+            STATISTICS.get().output_write_counts[idx].inc();
+        } else {
+            STATISTICS.get().output_error_counts[idx].inc();
+        }
+    }
+
+    fn close(&self) -> bool {
+        let Some(thread) = self.thread.lock().unwrap().take() else {
+            return true;
+        };
+
+        self.stop.store(true, Ordering::SeqCst);
+        self.wakeup.wake();
+
+        match thread.join() {
+            Ok(closed) => closed,
+            Err(_) => {
+                println!("Output thread {} panicked", OUTPUT_NAMES[self.idx]);
+                false
+            }
+        }
+    }
+}
+
+// Message codes of market pictures written by workers
+pub fn depth_message_codes(exchange: Exchange) -> Vec<i32> {
+    match exchange {
+        Exchange::BSE => vec![BSE_BCAST_MBP],
+        Exchange::NEQ | Exchange::NFO | Exchange::NCD => {
+            vec![
+                BCAST_ONLY_MBP as i32,
+                BCAST_ONLY_MBP_EQ as i32,
+                BCAST_MBO_MBP as i32,
+            ]
+        }
+        Exchange::MCX => vec![SNAPSHOT_TEMPLATE_ID],
+    }
+}
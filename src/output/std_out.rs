@@ -1,19 +1,39 @@
-use crate::types::packet::Packet;
+use crate::{settings, types::packet::Packet};
 
-use super::OutputTrait;
+use super::{json::JsonEncoder, OutputTrait};
 
-pub struct StdOut {}
+pub struct StdOut {
+    // Market pictures are printed as json, if enabled
+    encoder: Option<JsonEncoder>,
+}
 
 impl StdOut {
     pub fn new() -> StdOut {
-        StdOut {}
+        let encoder = if settings::get().json_output {
+            Some(JsonEncoder::new())
+        } else {
+            None
+        };
+
+        StdOut { encoder }
     }
 }
 
 impl OutputTrait for StdOut {
-    fn write(&mut self, data: &Packet) {
+    fn write(&mut self, data: &Packet) -> bool {
+        if let Some(json) = self
+            .encoder
+            .as_ref()
+            .and_then(|encoder| encoder.encode(data))
+        {
+            println!("{}", json);
+            return true;
+        }
+
         let slice = &data.0[..data.1];
 
         println!("{}", String::from_utf8_lossy(slice));
+
+        true
     }
 }
@@ -35,9 +35,14 @@ impl UdpOutput {
 }
 
 impl OutputTrait for UdpOutput {
-    fn write(&mut self, data: &Packet) {
+    fn write(&mut self, data: &Packet) -> bool {
         let slice = &data.0[..data.1];
 
-        self.socket.write(slice).unwrap();
+        if let Err(e) = self.socket.write(slice) {
+            println!("Error writing udp output: {}", e);
+            return false;
+        }
+
+        true
     }
 }
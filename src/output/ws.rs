@@ -1,54 +1,67 @@
 use std::{
+    collections::HashMap,
+    mem::offset_of,
     net::TcpListener,
     sync::{Arc, Mutex},
     thread,
+    time::{Duration, Instant},
 };
 
-use crossbeam::queue::SegQueue;
-use tungstenite::{accept, Message};
+use tungstenite::accept;
 
 use crate::{
-    constants::{
-        BCAST_MBO_MBP, BCAST_ONLY_MBP, BCAST_ONLY_MBP_EQ, BSE_BCAST_MBP, SNAPSHOT_TEMPLATE_ID,
-    },
+    constants::{ENRICHED_PICTURE_CODE, GAP_EVENT_CODE, MASTER_CHANGE_CODE, WS_DRAIN_TIMEOUT},
     settings,
     types::{
-        packet::Packet, packet_structures::depth_output::TagMarketPictureBroadcast,
-        settings::Exchange,
+        bounded_queue::{BoundedQueue, QueueKind},
+        message_class::MessageClassifier,
+        packet::Packet,
+        packet_structures::{
+            depth_output::TagMarketPictureBroadcast, enriched_output::TagEnrichedMarketPicture,
+        },
+        wait::{Waiter, Wakeup},
     },
-    utils::byte_utils::bytes_to_struct_ptr,
+    utils::byte_utils::bytes_to_struct,
 };
 
-use super::OutputTrait;
+use super::{
+    depth_message_codes,
+    ws_client::{ClientHandle, Pictures, Update},
+    OutputTrait,
+};
 
 pub struct Ws {
     message_code: Vec<i32>,
-    queue: Arc<SegQueue<Packet>>,
+    queue: Arc<BoundedQueue<Packet>>,
+    wakeup: Arc<Wakeup>,
+    clients: Arc<Mutex<Vec<Arc<ClientHandle>>>>,
 }
 
 impl Ws {
     pub fn new() -> Ws {
         let settings = settings::get();
 
-        let message_code = match settings.exchange {
-            Exchange::BSE => vec![BSE_BCAST_MBP as i32],
-            Exchange::NEQ | Exchange::NFO | Exchange::NCD => {
-                vec![
-                    BCAST_ONLY_MBP as i32,
-                    BCAST_ONLY_MBP_EQ as i32,
-                    BCAST_MBO_MBP as i32,
-                ]
-            }
-            Exchange::MCX => vec![SNAPSHOT_TEMPLATE_ID as i32],
-        };
-        let mq: Arc<SegQueue<Packet>> = Arc::new(SegQueue::new());
-        let clients = Arc::new(Mutex::new(vec![]));
+        let depth_codes = depth_message_codes(settings.exchange);
+        let mut message_code = depth_codes.clone();
+
+        if settings.enriched_output {
+            message_code.push(ENRICHED_PICTURE_CODE);
+        }
+        message_code.push(GAP_EVENT_CODE);
+        message_code.push(MASTER_CHANGE_CODE);
+
+        let mq: Arc<BoundedQueue<Packet>> =
+            Arc::new(BoundedQueue::with_key(QueueKind::Ws, token_of));
+        let wakeup = Arc::new(Wakeup::new());
+        let clients: Arc<Mutex<Vec<Arc<ClientHandle>>>> = Arc::new(Mutex::new(vec![]));
+        let pictures: Arc<Pictures> = Arc::new(Mutex::new(HashMap::new()));
 
         let ws_url = settings.ws.as_ref().expect("Please provide `ws` in config");
 
         // Thread for handling new connections
         {
             let clients = clients.clone();
+            let pictures = pictures.clone();
             thread::spawn(move || {
                 let tcp_stream = TcpListener::bind(ws_url).unwrap();
 
@@ -56,8 +69,15 @@ impl Ws {
                     match stream {
                         Ok(stream) => match accept(stream) {
                             Ok(ws) => {
+                                // Client thread also reads control messages, so it must not block
+                                if ws.get_ref().set_nonblocking(true).is_err() {
+                                    println!("Error nonblocking");
+                                    continue;
+                                }
+
                                 println!("Connected");
-                                clients.lock().unwrap().push(ws)
+                                let client = ClientHandle::spawn(ws, pictures.clone());
+                                clients.lock().unwrap().push(client)
                             }
                             _ => {
                                 println!("Error accept");
@@ -75,19 +95,41 @@ impl Ws {
         {
             let clients = clients.clone();
             let mq = mq.clone();
+            let wakeup = wakeup.clone();
+            let classifier = MessageClassifier::new(settings.exchange);
+            let mut waiter = Waiter::new(settings.wait_strategy.ws);
+
+            thread::spawn(move || {
+                wakeup.register();
+
+                loop {
+                    let Some(packet) = mq.pop() else {
+                        waiter.wait(Some(&wakeup), || !mq.is_empty());
+                        continue;
+                    };
+
+                    waiter.reset();
+
+                    let message_code = i32::from_le_bytes(packet.0[0..4].try_into().unwrap());
+                    let class = classifier.class(message_code);
+                    let token = token_of(&packet).map(|token| token as i64);
 
-            thread::spawn(move || loop {
-                if let Some(packet) = mq.pop() {
-                    let json = serde_json::to_string(bytes_to_struct_ptr::<
-                        TagMarketPictureBroadcast,
-                    >(&packet.0))
-                    .unwrap();
+                    // Clients encode it as per their format, once for all of them
+                    let update = Arc::new(Update::new(&packet, token, &depth_codes));
 
-                    clients.lock().unwrap().retain_mut(|client| {
-                        let err = client.send(Message::text(&json));
+                    let mut pictures = pictures.lock().unwrap();
 
-                        err.is_ok()
-                    });
+                    if let Some(token) = token {
+                        pictures.insert(token, update.clone());
+                    }
+
+                    // Each client has own queue and thread, so slow ones don't hold up others
+                    let mut clients = clients.lock().unwrap();
+                    clients.retain(|client| !client.is_finished());
+
+                    for client in clients.iter() {
+                        client.send(class, &update);
+                    }
                 }
             });
         }
@@ -95,20 +137,63 @@ impl Ws {
         Ws {
             message_code,
             queue: mq,
+            wakeup,
+            clients,
         }
     }
 }
 
+// Market pictures can be conflated by token, events are not
+fn token_of(packet: &Packet) -> Option<u64> {
+    let message_code = i32::from_le_bytes(packet.0[0..4].try_into().unwrap());
+
+    let offset = match message_code {
+        ENRICHED_PICTURE_CODE => offset_of!(TagEnrichedMarketPicture, market_picture),
+        GAP_EVENT_CODE | MASTER_CHANGE_CODE => return None,
+        _ => 0,
+    };
+    let token: i64 =
+        bytes_to_struct(&packet.0[offset + offset_of!(TagMarketPictureBroadcast, token)..]).ok()?;
+
+    Some(token as u64)
+}
+
 impl OutputTrait for Ws {
-    fn write(&mut self, data: &Packet) {
+    fn write(&mut self, data: &Packet) -> bool {
         // get message code
         let message_code = i32::from_le_bytes(data.0[0..4].try_into().unwrap());
 
         if !self.message_code.contains(&message_code) {
             // println!("Invalid message code: {}", message_code);
-            return;
+            return true;
+        }
+
+        self.queue.push(data.clone());
+        self.wakeup.wake();
+
+        true
+    }
+
+    fn close(&mut self) -> bool {
+        let started_at = Instant::now();
+
+        // Let sender thread deliver queued packets
+        while !self.queue.is_empty() {
+            if started_at.elapsed() > Duration::from_secs(WS_DRAIN_TIMEOUT) {
+                println!("Dropping {} queued ws packets", self.queue.len());
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let drained = self.queue.is_empty();
+
+        // Clients are removed, so sender thread won't write after close
+        for client in self.clients.lock().unwrap().drain(..) {
+            client.close();
         }
 
-        self.queue.push(*data);
+        drained
     }
 }
@@ -0,0 +1,463 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::ErrorKind,
+    mem::size_of,
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use tungstenite::{Error, Message, WebSocket};
+
+use crate::{
+    constants::WS_CLIENT_POLL_INTERVAL,
+    global::{SECURITY_MASTER, STATISTICS},
+    settings,
+    types::{
+        bounded_queue::{BoundedQueue, QueueKind},
+        message_class::MessageClass,
+        packet::Packet,
+        packet_structures::enriched_output::TagEnrichedMarketPicture,
+        wait::{Waiter, Wakeup},
+    },
+};
+
+use super::{binary, json::JsonEncoder};
+
+// Shared by all clients it is sent to, each format is encoded at most once
+pub struct Update {
+    pub token: Option<i64>,
+    // Compact binary frame, empty for replies
+    bytes: Vec<u8>,
+    json: OnceLock<Option<String>>,
+}
+
+impl Update {
+    pub fn new(packet: &Packet, token: Option<i64>, depth_codes: &[i32]) -> Self {
+        Self {
+            token,
+            bytes: binary::compact(packet, depth_codes),
+            json: OnceLock::new(),
+        }
+    }
+
+    // Sent as text in both formats
+    fn reply(text: String) -> Self {
+        Self {
+            token: None,
+            bytes: vec![],
+            json: OnceLock::from(Some(text)),
+        }
+    }
+
+    // Json is encoded by first client which needs it, from compact frame
+    fn message(&self, format: Format, encoder: &JsonEncoder) -> Option<Message> {
+        if format == Format::Binary && !self.bytes.is_empty() {
+            return Some(Message::binary(self.bytes.clone()));
+        }
+
+        self.json
+            .get_or_init(|| {
+                // Encoder reads whole struct, largest is enriched picture
+                let mut packet =
+                    Packet::from_slice(&self.bytes, size_of::<TagEnrichedMarketPicture>());
+                packet.zero_tail(size_of::<TagEnrichedMarketPicture>());
+
+                encoder.encode(&packet)
+            })
+            .clone()
+            .map(Message::text)
+    }
+}
+
+// Latest picture of each token, sent on subscribe
+pub type Pictures = Mutex<HashMap<i64, Arc<Update>>>;
+
+// Lagging clients conflate by token, events are dropped
+fn token_of(update: &Arc<Update>) -> Option<u64> {
+    update.token.map(|token| token as u64)
+}
+
+// Shared between ws sender thread and thread of the client
+pub struct ClientHandle {
+    queue: BoundedQueue<Arc<Update>>,
+    subscription: Mutex<Subscription>,
+    wakeup: Wakeup,
+    stop: AtomicBool,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ClientHandle {
+    // Starts thread which writes to and reads from the socket, socket must be nonblocking
+    pub fn spawn(ws: WebSocket<TcpStream>, pictures: Arc<Pictures>) -> Arc<ClientHandle> {
+        let handle = Arc::new(ClientHandle {
+            queue: BoundedQueue::with_key(QueueKind::WsClient, token_of),
+            subscription: Mutex::new(Subscription::new()),
+            wakeup: Wakeup::new(),
+            stop: AtomicBool::new(false),
+            thread: Mutex::new(None),
+        });
+
+        let client = Client {
+            ws,
+            handle: handle.clone(),
+            pictures,
+            format: Format::Json,
+            encoder: JsonEncoder::new(),
+        };
+        let thread = thread::Builder::new()
+            .name("ws-client".to_string())
+            .spawn(move || client.run())
+            .unwrap();
+
+        *handle.thread.lock().unwrap() = Some(thread);
+
+        STATISTICS.get().ws_connects_count.inc();
+
+        handle
+    }
+
+    // Called with pictures locked, so snapshot on subscribe can't overtake an update
+    pub fn send(&self, class: MessageClass, update: &Arc<Update>) {
+        if !self.subscription.lock().unwrap().wants(class, update.token) {
+            return;
+        }
+
+        self.queue.push(update.clone());
+        self.wakeup.wake();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|thread| thread.is_finished())
+    }
+
+    // Delivers queued updates which fit in socket, then closes it
+    pub fn close(&self) {
+        self.stop.store(true, Ordering::Release);
+        self.wakeup.wake();
+
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Client {
+    ws: WebSocket<TcpStream>,
+    handle: Arc<ClientHandle>,
+    pictures: Arc<Pictures>,
+    format: Format,
+    encoder: JsonEncoder,
+}
+
+impl Client {
+    fn run(mut self) {
+        let settings = settings::get();
+        let config = &settings.ws_client;
+
+        let ping_interval = Duration::from_secs(config.ping_interval);
+        let pong_timeout = Duration::from_secs(config.pong_timeout);
+        let lag_timeout = Duration::from_secs(config.lag_timeout);
+
+        let mut waiter = Waiter::new(settings.wait_strategy.ws_clients);
+        self.handle.wakeup.register();
+
+        let mut pinged_at = Instant::now();
+        let mut received_at = Instant::now();
+        // Socket is full since
+        let mut blocked_at: Option<Instant> = None;
+
+        loop {
+            if self.handle.stop.load(Ordering::Acquire) {
+                let _ = self.write_queued();
+                break;
+            }
+
+            match self.read_requests() {
+                Some(true) => received_at = Instant::now(),
+                Some(false) => {}
+                None => break,
+            }
+
+            if received_at.elapsed() > pong_timeout {
+                println!("Disconnecting ws client, no pong");
+                STATISTICS.get().ws_evictions_count.inc();
+                break;
+            }
+
+            // Buffered if socket is full, client replies with pong
+            if pinged_at.elapsed() > ping_interval {
+                if would_block(self.ws.write(Message::Ping(vec![]))).is_none() {
+                    break;
+                }
+                pinged_at = Instant::now();
+            }
+
+            match self.write_queued() {
+                Some(Progress::Written) => {
+                    blocked_at = None;
+                    waiter.reset();
+                }
+                Some(Progress::Idle) => {
+                    blocked_at = None;
+                    let queue = &self.handle.queue;
+                    waiter.wait(Some(&self.handle.wakeup), || !queue.is_empty());
+                }
+                // Rest of the updates wait in queue, where they are conflated
+                Some(Progress::Blocked) => {
+                    let since = *blocked_at.get_or_insert_with(Instant::now);
+
+                    if since.elapsed() > lag_timeout {
+                        println!("Disconnecting ws client, lagging");
+                        STATISTICS.get().ws_evictions_count.inc();
+                        break;
+                    }
+
+                    thread::sleep(Duration::from_millis(WS_CLIENT_POLL_INTERVAL));
+                }
+                None => break,
+            }
+        }
+
+        // Best effort, socket may be full or gone
+        if self.ws.close(None).is_ok() {
+            let _ = self.ws.flush();
+        }
+
+        println!("Disconnected");
+        STATISTICS.get().ws_disconnects_count.inc();
+    }
+
+    // None if client is disconnected
+    fn write_queued(&mut self) -> Option<Progress> {
+        // Previous writes are still buffered
+        if !would_block(self.ws.flush())? {
+            return Some(Progress::Blocked);
+        }
+
+        let mut progress = Progress::Idle;
+
+        while let Some(update) = self.handle.queue.pop() {
+            let Some(message) = update.message(self.format, &self.encoder) else {
+                continue;
+            };
+
+            progress = Progress::Written;
+
+            if !would_block(self.ws.write(message))? {
+                return Some(Progress::Blocked);
+            }
+        }
+
+        if !would_block(self.ws.flush())? {
+            return Some(Progress::Blocked);
+        }
+
+        Some(progress)
+    }
+
+    // Returns true if anything was received, including pongs, None if client is disconnected
+    fn read_requests(&mut self) -> Option<bool> {
+        let mut received = false;
+
+        loop {
+            let text = match self.ws.read() {
+                Ok(Message::Text(text)) => text,
+                Ok(_) => {
+                    received = true;
+                    continue;
+                }
+                Err(Error::Io(err)) if err.kind() == ErrorKind::WouldBlock => {
+                    return Some(received)
+                }
+                Err(_) => return None,
+            };
+
+            received = true;
+
+            let reply = match serde_json::from_str::<Request>(&text) {
+                Ok(request) => self.apply(request),
+                Err(err) => Err(err.to_string()),
+            };
+
+            // Through queue, so it stays in order with updates
+            if let Err(err) = reply {
+                let error = serde_json::json!({ "error": err }).to_string();
+
+                self.handle.queue.push(Arc::new(Update::reply(error)));
+            }
+        }
+    }
+
+    fn apply(&mut self, request: Request) -> Result<(), String> {
+        // Applies to updates written after this, including queued ones
+        if let Action::SetFormat = request.action {
+            self.format = request.format.ok_or("Missing format")?;
+            return Ok(());
+        }
+
+        let mut tokens = request.tokens;
+
+        for symbol in &request.symbols {
+            let symbol_tokens = SECURITY_MASTER.tokens_for_symbol(symbol);
+
+            if symbol_tokens.is_empty() {
+                return Err(format!("Unknown symbol: {}", symbol));
+            }
+
+            tokens.extend(symbol_tokens);
+        }
+
+        // Sender thread doesn't push updates while pictures are locked
+        let pictures = self.pictures.lock().unwrap();
+        let mut subscription = self.handle.subscription.lock().unwrap();
+
+        if !subscription.explicit {
+            subscription.clear();
+            subscription.explicit = true;
+        }
+
+        if let Action::Unsubscribe = request.action {
+            for class in &request.classes {
+                subscription.classes.remove(class);
+            }
+
+            for token in &tokens {
+                subscription.tokens.remove(token);
+            }
+
+            if request.classes.is_empty() && tokens.is_empty() {
+                subscription.clear();
+            }
+
+            return Ok(());
+        }
+
+        let mut classes = request.classes;
+
+        if classes.is_empty() {
+            classes.push(MessageClass::Depth);
+        }
+
+        if tokens.is_empty() {
+            subscription.all_tokens = true;
+        } else {
+            subscription.tokens.extend(&tokens);
+        }
+        subscription.classes.extend(&classes);
+
+        if !classes.contains(&MessageClass::Depth) {
+            return Ok(());
+        }
+
+        // Send latest pictures, so client doesn't wait for next update
+        let snapshot: Vec<&Arc<Update>> = if tokens.is_empty() {
+            pictures.values().collect()
+        } else {
+            tokens
+                .iter()
+                .filter_map(|token| pictures.get(token))
+                .collect()
+        };
+
+        for update in snapshot {
+            self.handle.queue.push(update.clone());
+        }
+
+        Ok(())
+    }
+}
+
+enum Progress {
+    // Nothing was queued
+    Idle,
+    Written,
+    // Socket is full, rest is buffered in websocket and queue
+    Blocked,
+}
+
+// Some(false) if socket is full, unwritten data stays buffered in websocket
+fn would_block(result: Result<(), Error>) -> Option<bool> {
+    match result {
+        Ok(_) => Some(true),
+        Err(Error::Io(err)) if err.kind() == ErrorKind::WouldBlock => Some(false),
+        Err(_) => None,
+    }
+}
+
+// Control message from client, eg
+// {"action": "subscribe", "tokens": [35001], "symbols": ["NIFTY"], "classes": ["depth", "gap"]}
+// {"action": "set_format", "format": "binary"}
+#[derive(Deserialize)]
+struct Request {
+    action: Action,
+    format: Option<Format>,
+    #[serde(default)]
+    tokens: Vec<i64>,
+    #[serde(default)]
+    symbols: Vec<String>,
+    #[serde(default)]
+    classes: Vec<MessageClass>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    Subscribe,
+    Unsubscribe,
+    SetFormat,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Format {
+    Json,
+    // Output struct cut after last populated depth level, see binary::compact
+    Binary,
+}
+
+// Tokens are only checked for market pictures, events are sent to everyone subscribed to the class
+struct Subscription {
+    all_tokens: bool,
+    tokens: HashSet<i64>,
+    classes: HashSet<MessageClass>,
+    // New clients get all market pictures until they subscribe
+    explicit: bool,
+}
+
+impl Subscription {
+    fn new() -> Self {
+        Self {
+            all_tokens: true,
+            tokens: HashSet::new(),
+            classes: HashSet::from([MessageClass::Depth]),
+            explicit: false,
+        }
+    }
+
+    fn wants(&self, class: MessageClass, token: Option<i64>) -> bool {
+        if !self.classes.contains(&class) {
+            return false;
+        }
+
+        match token {
+            Some(token) => self.all_tokens || self.tokens.contains(&token),
+            None => true,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.all_tokens = false;
+        self.tokens.clear();
+        self.classes.clear();
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    mem::size_of,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    constants::{JOURNAL_MAGIC, NS_PER_DAY},
+    global::STATISTICS,
+    settings,
+    types::{
+        bounded_queue::{BoundedQueue, QueueKind},
+        packet::Packet,
+    },
+    utils::{
+        byte_utils::{bytes_to_struct, struct_to_bytes},
+        time_utils::epoch_days_to_date,
+    },
+};
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct JournalRecordHeader {
+    // Receive time in nanoseconds since epoch
+    pub recv_ts: u64,
+    // 0 for primary, 1 for secondary
+    pub socket: u8,
+    pub len: u32,
+}
+
+pub struct JournalRecord {
+    pub header: JournalRecordHeader,
+    pub data: Vec<u8>,
+}
+
+// Writes raw datagrams to rotating journal files from a background thread
+// Packets are queued as received, their buffers are shared with rest of pipeline
+pub struct Recorder {
+    queue: Arc<BoundedQueue<(u8, Packet)>>,
+    stop: Arc<AtomicBool>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        let settings = settings::get();
+
+        let dir = PathBuf::from(
+            settings
+                .record_dir
+                .as_ref()
+                .expect("record_dir required for recorder"),
+        );
+        // Size in mb after which a new file is started, 0 means only daily rotation
+        let max_file_size = settings.record_max_file_mb.unwrap_or(0) as u64 * 1024 * 1024;
+
+        fs::create_dir_all(&dir).expect("Unable to create record_dir");
+
+        let queue = Arc::new(BoundedQueue::new(QueueKind::Recorder));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // Thread for writing journal
+        let writer = {
+            let queue = queue.clone();
+            let stop = stop.clone();
+
+            thread::spawn(move || {
+                let mut journal = JournalWriter::new(dir, max_file_size);
+
+                loop {
+                    if let Some((socket, packet)) = queue.pop() {
+                        if let Err(e) = journal.write(socket, &packet) {
+                            println!("Error writing journal: {}", e);
+                            STATISTICS.get().journal_dropped_count.inc();
+                        }
+                    } else {
+                        // Flush while idle, so journal is complete on disk
+                        if let Err(e) = journal.flush() {
+                            println!("Error flushing journal: {}", e);
+                        }
+
+                        // Queue is drained and flushed, so nothing is lost
+                        if stop.load(Ordering::SeqCst) {
+                            break;
+                        }
+
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                }
+            })
+        };
+
+        Recorder {
+            queue,
+            stop,
+            writer: Some(writer),
+        }
+    }
+
+    // Waits for writer if it is behind by queues.recorder capacity, unless a drop policy is set
+    pub fn record(&self, socket: u8, packet: &Packet) {
+        if !self.queue.push((socket, packet.clone())) {
+            STATISTICS.get().journal_dropped_count.inc();
+        }
+    }
+}
+
+impl Drop for Recorder {
+    // Waits for queued records to be written
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(writer) = self.writer.take() {
+            if writer.join().is_err() {
+                println!("Journal writer panicked");
+            }
+        }
+    }
+}
+
+struct JournalWriter {
+    dir: PathBuf,
+    max_file_size: u64,
+    file: Option<BufWriter<File>>,
+    file_size: u64,
+    // Day since epoch and index of current file in that day
+    day: u64,
+    index: usize,
+}
+
+impl JournalWriter {
+    fn new(dir: PathBuf, max_file_size: u64) -> JournalWriter {
+        JournalWriter {
+            dir,
+            max_file_size,
+            file: None,
+            file_size: 0,
+            day: 0,
+            index: 0,
+        }
+    }
+
+    fn write(&mut self, socket: u8, packet: &Packet) -> io::Result<()> {
+        let data = &packet.0[..packet.1];
+        let header = JournalRecordHeader {
+            // Receive time of packet, so replay stamps packets same as live
+            recv_ts: packet.2 * 1000,
+            socket,
+            len: data.len() as u32,
+        };
+
+        let day = header.recv_ts / NS_PER_DAY;
+        let record_size = (size_of::<JournalRecordHeader>() + data.len()) as u64;
+
+        // Rotate on new day or when file is full
+        if self.file.is_none()
+            || day != self.day
+            || (self.max_file_size > 0 && self.file_size + record_size > self.max_file_size)
+        {
+            self.rotate(day)?;
+        }
+
+        let mut header_bytes = [0u8; size_of::<JournalRecordHeader>()];
+        struct_to_bytes(&header, &mut header_bytes);
+
+        let file = self.file.as_mut().unwrap();
+        file.write_all(&header_bytes)?;
+        file.write_all(data)?;
+
+        self.file_size += record_size;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn rotate(&mut self, day: u64) -> io::Result<()> {
+        self.flush()?;
+
+        if day != self.day {
+            self.day = day;
+            self.index = 0;
+        }
+
+        let (year, month, date) = epoch_days_to_date(day as i64);
+
+        // Find next unused file name, so restarts don't overwrite
+        let path = loop {
+            let path = self.dir.join(format!(
+                "journal_{year:04}{month:02}{date:02}_{:03}.bin",
+                self.index
+            ));
+            self.index += 1;
+
+            if !path.exists() {
+                break path;
+            }
+        };
+
+        let mut file = BufWriter::new(File::create(&path)?);
+        file.write_all(JOURNAL_MAGIC)?;
+
+        self.file = Some(file);
+        self.file_size = JOURNAL_MAGIC.len() as u64;
+
+        Ok(())
+    }
+}
+
+// Reads records back from a journal file
+pub struct JournalReader {
+    reader: BufReader<File>,
+}
+
+impl JournalReader {
+    pub fn open(path: &str) -> io::Result<JournalReader> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; JOURNAL_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+
+        if magic != *JOURNAL_MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Not a journal file"));
+        }
+
+        Ok(JournalReader { reader })
+    }
+
+    // Returns None at end of file
+    pub fn next_record(&mut self) -> io::Result<Option<JournalRecord>> {
+        let mut header = [0u8; size_of::<JournalRecordHeader>()];
+
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let header: JournalRecordHeader = bytes_to_struct(&header)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+
+        let mut data = vec![0u8; header.len as usize];
+        self.reader.read_exact(&mut data)?;
+
+        Ok(Some(JournalRecord { header, data }))
+    }
+}
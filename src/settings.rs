@@ -2,7 +2,7 @@ use std::{env, fs};
 
 use crate::{
     global::{EXCHANGE, SETTINGS},
-    types::settings::Settings,
+    types::settings::{Exchange, OverflowPolicy, Settings},
 };
 
 pub fn init() {
@@ -17,6 +17,40 @@ pub fn init() {
     let settings =
         serde_json::from_str::<Settings>(&settings).expect("Unable to parse settings file");
 
+    // Only ws queue items have a token to conflate on
+    let queues = &settings.queues;
+    if [
+        queues.input,
+        queues.packet,
+        queues.mcx,
+        queues.output,
+        queues.recorder,
+    ]
+    .iter()
+    .any(|queue| queue.policy == OverflowPolicy::Conflate)
+    {
+        panic!("Conflate policy is only supported for ws and ws_client queues");
+    }
+
+    // Mcx messages own heap data and dropping an incremental corrupts the book till next snapshot
+    let mut mcx_queues = vec![queues.mcx];
+    if settings.exchange == Exchange::MCX {
+        mcx_queues.push(queues.packet);
+    }
+    if mcx_queues.iter().any(|queue| {
+        matches!(
+            queue.policy,
+            OverflowPolicy::DropOldest | OverflowPolicy::DropNewest
+        )
+    }) {
+        panic!("Drop policies are not supported for mcx queue, or packet queue of mcx");
+    }
+
+    // One slow client would stall all others
+    if queues.ws_client.policy == OverflowPolicy::Block {
+        panic!("Block policy is not supported for ws_client queue");
+    }
+
     // Initialize settings
     SETTINGS.get_or_init(|| settings);
 
@@ -1,78 +1,558 @@
-use std::{cell::UnsafeCell, io};
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+    time::Duration,
+};
 
-use crate::global::STATISTICS;
+use crate::{
+    constants::{
+        FEED_NAMES, MESSAGE_CODE_COUNT, OUTPUT_COUNT, OUTPUT_NAMES, QUEUE_COUNT, QUEUE_NAMES,
+    },
+    create_array,
+    global::{INPUT_QUEUE, OUTPUT, PACKET_QUEUES, STATISTICS, WORKER_QUEUES},
+    settings,
+    types::settings::Exchange,
+};
+
+const METRICS_PREFIX: &str = "broadcast_gate";
+
+// Counter updated by many threads, only totals are read so relaxed ordering is enough
+// Each counter is on own cache line, so threads updating different counters don't contend
+#[repr(align(64))]
+pub struct StatCounter(AtomicU64);
+
+impl StatCounter {
+    pub const fn new() -> StatCounter {
+        StatCounter(AtomicU64::new(0))
+    }
+
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
 
 pub struct StatisticsData {
-    pub udp_packets_count: u64,
-    pub other_packets_count: u64,
-    pub depth_packets_count: u64,
-    pub filtered_packets_count: u64,
+    // Input stage
+    pub udp_packets_count: StatCounter,
+    pub filtered_packets_count: StatCounter,
+    // Distributor stage
+    pub duplicate_packets_count: StatCounter,
+    pub decompressed_count: StatCounter,
+    pub decompress_failed_count: StatCounter,
+    pub parsed_count: StatCounter,
+    pub unsubscribed_packets_count: StatCounter,
+    pub conflated_count: StatCounter,
+    pub other_packets_count: StatCounter,
+    pub depth_packets_count: StatCounter,
+    pub malformed_packets_count: StatCounter,
+    // Messages discarded at any stage, other than filtered, duplicate or conflated
+    pub dropped_count: StatCounter,
+    pub gaps_count: StatCounter,
+    pub gap_recovered_count: StatCounter,
+    pub gap_lost_count: StatCounter,
+    // Security master entries changed by broadcasts
+    pub master_changes_count: StatCounter,
+    // Datagrams missing from journal, as recorder queue was full or write failed
+    pub journal_dropped_count: StatCounter,
+    // Packet buffers allocated because pool was empty
+    pub packet_allocations_count: StatCounter,
+    // Websocket clients
+    pub ws_connects_count: StatCounter,
+    pub ws_disconnects_count: StatCounter,
+    // Disconnected for lagging or not answering pings
+    pub ws_evictions_count: StatCounter,
+    // Kafka records, as per delivery reports
+    pub kafka_acked_count: StatCounter,
+    pub kafka_failed_count: StatCounter,
+    // Sends rejected as librdkafka queue was full, and what happened to them
+    pub kafka_queue_full_count: StatCounter,
+    pub kafka_dropped_count: StatCounter,
+    pub kafka_spilled_count: StatCounter,
+    pub kafka_in_flight: AtomicU64,
+    // Set on all brokers down error, cleared on next delivered record
+    pub kafka_brokers_down: AtomicBool,
+    // Count of messages received for each message code, indexed by code
+    // Not padded like StatCounter, a table of 64k cache lines would take 4 MB
+    pub message_codes: [AtomicU64; MESSAGE_CODE_COUNT + 1],
+    // Count of messages received for each unknown message code, indexed by code
+    pub unknown_codes: [AtomicU64; MESSAGE_CODE_COUNT + 1],
+    // Indexed by feed
+    pub feed_packets_count: [StatCounter; 2],
+    pub feed_accepted_count: [StatCounter; 2],
+    // Feed which delivered last message first
+    pub active_feed: AtomicU8,
+    // Indexed by output, in order of OUTPUT_NAMES
+    pub output_write_counts: [StatCounter; OUTPUT_COUNT],
+    pub output_error_counts: [StatCounter; OUTPUT_COUNT],
+    // Pushes to a full queue, indexed by queue in order of QUEUE_NAMES
+    pub queue_overflow_counts: [StatCounter; QUEUE_COUNT],
 }
 
 pub struct Statistics {
-    inner: UnsafeCell<StatisticsData>,
+    inner: StatisticsData,
 }
 
-unsafe impl Send for Statistics {}
-unsafe impl Sync for Statistics {}
-
 impl Statistics {
     pub const fn new() -> Self {
         Self {
-            inner: UnsafeCell::new(StatisticsData {
-                udp_packets_count: 0,
-                other_packets_count: 0,
-                depth_packets_count: 0,
-                filtered_packets_count: 0,
-            }),
+            inner: StatisticsData {
+                udp_packets_count: StatCounter::new(),
+                filtered_packets_count: StatCounter::new(),
+                duplicate_packets_count: StatCounter::new(),
+                decompressed_count: StatCounter::new(),
+                decompress_failed_count: StatCounter::new(),
+                parsed_count: StatCounter::new(),
+                unsubscribed_packets_count: StatCounter::new(),
+                conflated_count: StatCounter::new(),
+                other_packets_count: StatCounter::new(),
+                depth_packets_count: StatCounter::new(),
+                malformed_packets_count: StatCounter::new(),
+                dropped_count: StatCounter::new(),
+                gaps_count: StatCounter::new(),
+                gap_recovered_count: StatCounter::new(),
+                gap_lost_count: StatCounter::new(),
+                master_changes_count: StatCounter::new(),
+                packet_allocations_count: StatCounter::new(),
+                ws_connects_count: StatCounter::new(),
+                ws_disconnects_count: StatCounter::new(),
+                ws_evictions_count: StatCounter::new(),
+                kafka_acked_count: StatCounter::new(),
+                kafka_failed_count: StatCounter::new(),
+                kafka_queue_full_count: StatCounter::new(),
+                kafka_dropped_count: StatCounter::new(),
+                journal_dropped_count: StatCounter::new(),
+                kafka_spilled_count: StatCounter::new(),
+                kafka_in_flight: AtomicU64::new(0),
+                kafka_brokers_down: AtomicBool::new(false),
+                message_codes: create_array!(AtomicU64::new(0); MESSAGE_CODE_COUNT + 1),
+                unknown_codes: create_array!(AtomicU64::new(0); MESSAGE_CODE_COUNT + 1),
+                feed_packets_count: create_array!(StatCounter::new(); 2),
+                feed_accepted_count: create_array!(StatCounter::new(); 2),
+                active_feed: AtomicU8::new(0),
+                output_write_counts: create_array!(StatCounter::new(); OUTPUT_COUNT),
+                output_error_counts: create_array!(StatCounter::new(); OUTPUT_COUNT),
+                queue_overflow_counts: create_array!(StatCounter::new(); QUEUE_COUNT),
+            },
         }
     }
 
-    pub fn get(&self) -> &mut StatisticsData {
-        unsafe { &mut *self.inner.get() }
+    pub fn get(&self) -> &StatisticsData {
+        &self.inner
+    }
+
+    // Message which failed validation or decompression
+    pub fn record_malformed(&self) {
+        self.inner.malformed_packets_count.inc();
+        self.inner.dropped_count.inc();
+    }
+
+    // True for first message with this code, so it is logged only once
+    pub fn record_unknown_code(&self, message_code: i32) -> bool {
+        self.inner.unknown_codes[code_idx(message_code)].fetch_add(1, Ordering::Relaxed) == 0
+    }
+
+    // Called once for each message parsed
+    pub fn record_message_code(&self, message_code: i32) {
+        self.inner.parsed_count.inc();
+        self.inner.message_codes[code_idx(message_code)].fetch_add(1, Ordering::Relaxed);
     }
 
+    // Serves counters in prometheus text format on /metrics
     pub fn run() {
-        let mut command = String::new();
-
-        loop {
-            Self::list_options();
-            io::stdin().read_line(&mut command).unwrap();
-
-            match command.trim() {
-                "1" => println!(
-                    "Depth Packets Count : {}",
-                    STATISTICS.get().depth_packets_count
-                ),
-                "2" => println!(
-                    "Other Packets Processed : {}",
-                    STATISTICS.get().other_packets_count
-                ),
-                "3" => println!("Total UDP Packets : {}", STATISTICS.get().udp_packets_count),
-                "4" => println!(
-                    "Filtered UDP Packets : {}",
-                    STATISTICS.get().filtered_packets_count
-                ),
-                _ => println!("Unknown command"),
+        let Some(metrics_addr) = &settings::get().metrics_addr else {
+            return;
+        };
+
+        let listener = TcpListener::bind(metrics_addr).expect("Unable to bind metrics_addr");
+
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = Self::serve(stream) {
+                        println!("Error serving metrics: {}", e);
+                    }
+                }
+                Err(e) => println!("Error accepting metrics connection: {}", e),
             }
+        }
+    }
+
+    fn serve(mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+        // Only request line is required
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+
+        let path = request_line.split_whitespace().nth(1).unwrap_or("");
+
+        let response = if path == "/metrics" {
+            let body = Self::render();
+
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        } else {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        };
+
+        stream.write_all(response.as_bytes())
+    }
+
+    fn render() -> String {
+        let stats = STATISTICS.get();
+        let mut metrics = Metrics(String::new());
+
+        metrics.counter(
+            "udp_packets_total",
+            "Datagrams received",
+            stats.udp_packets_count.get(),
+        );
+        metrics.counter(
+            "filtered_packets_total",
+            "Datagrams dropped for source ip",
+            stats.filtered_packets_count.get(),
+        );
+        metrics.counter(
+            "duplicate_packets_total",
+            "Messages dropped as already received on other feed",
+            stats.duplicate_packets_count.get(),
+        );
+        metrics.counter(
+            "decompressed_messages_total",
+            "Compressed messages decompressed",
+            stats.decompressed_count.get(),
+        );
+        metrics.counter(
+            "decompress_failures_total",
+            "Compressed messages which failed to decompress",
+            stats.decompress_failed_count.get(),
+        );
+        metrics.counter(
+            "parsed_messages_total",
+            "Messages parsed",
+            stats.parsed_count.get(),
+        );
+        metrics.counter(
+            "depth_packets_total",
+            "Depth messages processed",
+            stats.depth_packets_count.get(),
+        );
+        metrics.counter(
+            "other_packets_total",
+            "Non depth messages processed",
+            stats.other_packets_count.get(),
+        );
+        metrics.counter(
+            "unsubscribed_packets_total",
+            "Messages dropped for unsubscribed tokens",
+            stats.unsubscribed_packets_count.get(),
+        );
+        metrics.counter(
+            "malformed_packets_total",
+            "Packets dropped for failing validation",
+            stats.malformed_packets_count.get(),
+        );
+        metrics.counter(
+            "conflated_messages_total",
+            "Depth messages replaced by newer message for same token before processing",
+            stats.conflated_count.get(),
+        );
+        metrics.counter(
+            "dropped_messages_total",
+            "Messages discarded as malformed, stale or undecodable",
+            stats.dropped_count.get(),
+        );
+        // Bse messages carry no seq no, so its gaps can't be detected
+        if settings::get().exchange != Exchange::BSE {
+            metrics.counter(
+                "sequence_gaps_total",
+                "Sequence gaps detected, nse and mcx only",
+                stats.gaps_count.get(),
+            );
+            metrics.counter(
+                "gap_recovered_messages_total",
+                "Gap messages recovered from other feed",
+                stats.gap_recovered_count.get(),
+            );
+            metrics.counter(
+                "gap_lost_messages_total",
+                "Gap messages never received",
+                stats.gap_lost_count.get(),
+            );
+        }
+        metrics.counter(
+            "master_changes_total",
+            "Security master entries changed by broadcasts",
+            stats.master_changes_count.get(),
+        );
+        metrics.counter(
+            "packet_allocations_total",
+            "Packet buffers allocated because pool was empty",
+            stats.packet_allocations_count.get(),
+        );
+        metrics.counter(
+            "ws_connections_total",
+            "Websocket clients connected",
+            stats.ws_connects_count.get(),
+        );
+        metrics.counter(
+            "ws_evictions_total",
+            "Websocket clients disconnected for lagging or not answering pings",
+            stats.ws_evictions_count.get(),
+        );
+        metrics.counter(
+            "kafka_acked_total",
+            "Kafka records acknowledged by broker",
+            stats.kafka_acked_count.get(),
+        );
+        metrics.counter(
+            "kafka_failed_total",
+            "Kafka records which failed delivery",
+            stats.kafka_failed_count.get(),
+        );
+        metrics.counter(
+            "kafka_queue_full_total",
+            "Kafka sends rejected as producer queue was full",
+            stats.kafka_queue_full_count.get(),
+        );
+        metrics.counter(
+            "kafka_dropped_total",
+            "Kafka records dropped as producer queue was full",
+            stats.kafka_dropped_count.get(),
+        );
+        metrics.counter(
+            "journal_dropped_total",
+            "Datagrams not written to journal",
+            stats.journal_dropped_count.get(),
+        );
+        metrics.counter(
+            "kafka_spilled_total",
+            "Kafka records written to spill file as producer queue was full",
+            stats.kafka_spilled_count.get(),
+        );
+        metrics.gauge(
+            "kafka_in_flight",
+            "Kafka records waiting for delivery",
+            stats.kafka_in_flight.load(Ordering::Relaxed),
+        );
+        metrics.gauge(
+            "kafka_up",
+            "0 if all kafka brokers are down",
+            !stats.kafka_brokers_down.load(Ordering::Relaxed) as u64,
+        );
+        metrics.gauge(
+            "ws_clients",
+            "Websocket clients connected now",
+            stats
+                .ws_connects_count
+                .get()
+                .saturating_sub(stats.ws_disconnects_count.get()),
+        );
+
+        metrics.header(
+            "messages_total",
+            "Messages received by message code",
+            "counter",
+        );
+        for (code, count) in code_counts(&stats.message_codes) {
+            metrics.sample("messages_total", &format!("code=\"{}\"", code), count);
+        }
 
-            io::stdin().read_line(&mut command).unwrap();
-            command.clear();
+        metrics.header(
+            "unknown_messages_total",
+            "Messages with unknown message code",
+            "counter",
+        );
+        for (code, count) in code_counts(&stats.unknown_codes) {
+            metrics.sample(
+                "unknown_messages_total",
+                &format!("code=\"{}\"", code),
+                count,
+            );
         }
+
+        metrics.header(
+            "feed_packets_total",
+            "Datagrams received by feed",
+            "counter",
+        );
+        for (feed, name) in FEED_NAMES.iter().enumerate() {
+            metrics.sample(
+                "feed_packets_total",
+                &format!("feed=\"{}\"", name),
+                stats.feed_packets_count[feed].get(),
+            );
+        }
+
+        metrics.header(
+            "feed_first_copies_total",
+            "Messages delivered first by feed",
+            "counter",
+        );
+        for (feed, name) in FEED_NAMES.iter().enumerate() {
+            metrics.sample(
+                "feed_first_copies_total",
+                &format!("feed=\"{}\"", name),
+                stats.feed_accepted_count[feed].get(),
+            );
+        }
+
+        metrics.header(
+            "active_feed",
+            "1 for feed which delivered last message first",
+            "gauge",
+        );
+        for (feed, name) in FEED_NAMES.iter().enumerate() {
+            metrics.sample(
+                "active_feed",
+                &format!("feed=\"{}\"", name),
+                (stats.active_feed.load(Ordering::Relaxed) as usize == feed) as u64,
+            );
+        }
+
+        metrics.header("queue_depth", "Items waiting in queue", "gauge");
+        metrics.sample("queue_depth", "queue=\"input\"", INPUT_QUEUE.len() as u64);
+        metrics.sample(
+            "queue_depth",
+            "queue=\"worker\"",
+            WORKER_QUEUES.iter().map(|queue| queue.len() as u64).sum(),
+        );
+        metrics.sample(
+            "queue_depth",
+            "queue=\"packet\"",
+            PACKET_QUEUES.iter().map(|queue| queue.len() as u64).sum(),
+        );
+
+        // Only non empty packet queues, to keep series count low
+        metrics.header("packet_queue_depth", "Packets waiting by work id", "gauge");
+        for (id, queue) in PACKET_QUEUES.iter().enumerate() {
+            if !queue.is_empty() {
+                metrics.sample(
+                    "packet_queue_depth",
+                    &format!("queue=\"{}\"", id),
+                    queue.len() as u64,
+                );
+            }
+        }
+
+        metrics.header(
+            "queue_overflows_total",
+            "Pushes to a full queue, handled as per overflow policy",
+            "counter",
+        );
+        for (queue, name) in QUEUE_NAMES.iter().enumerate() {
+            metrics.sample(
+                "queue_overflows_total",
+                &format!("queue=\"{}\"", name),
+                stats.queue_overflow_counts[queue].get(),
+            );
+        }
+
+        metrics.header("worker_queue_depth", "Work waiting by worker", "gauge");
+        for (worker, queue) in WORKER_QUEUES.iter().enumerate() {
+            metrics.sample(
+                "worker_queue_depth",
+                &format!("worker=\"{}\"", worker),
+                queue.len() as u64,
+            );
+        }
+
+        metrics.header("output_queue_depth", "Packets waiting by output", "gauge");
+        for (output, len) in OUTPUT.queue_depths() {
+            metrics.sample(
+                "output_queue_depth",
+                &format!("output=\"{}\"", OUTPUT_NAMES[output]),
+                len as u64,
+            );
+        }
+
+        metrics.header(
+            "output_writes_total",
+            "Packets written by output",
+            "counter",
+        );
+        for (output, name) in OUTPUT_NAMES.iter().enumerate() {
+            metrics.sample(
+                "output_writes_total",
+                &format!("output=\"{}\"", name),
+                stats.output_write_counts[output].get(),
+            );
+        }
+
+        metrics.header("output_errors_total", "Failed writes by output", "counter");
+        for (output, name) in OUTPUT_NAMES.iter().enumerate() {
+            metrics.sample(
+                "output_errors_total",
+                &format!("output=\"{}\"", name),
+                stats.output_error_counts[output].get(),
+            );
+        }
+
+        metrics.0
+    }
+}
+
+// Counter of message code, codes outside table share last counter
+fn code_idx(message_code: i32) -> usize {
+    if (0..MESSAGE_CODE_COUNT as i32).contains(&message_code) {
+        message_code as usize
+    } else {
+        MESSAGE_CODE_COUNT
+    }
+}
+
+// Codes which were received, with their counts
+fn code_counts(counters: &[AtomicU64]) -> Vec<(String, u64)> {
+    counters
+        .iter()
+        .map(|counter| counter.load(Ordering::Relaxed))
+        .enumerate()
+        .filter(|&(_, count)| count > 0)
+        .map(|(code, count)| {
+            let code = if code == MESSAGE_CODE_COUNT {
+                "other".to_string()
+            } else {
+                code.to_string()
+            };
+
+            (code, count)
+        })
+        .collect()
+}
+
+// Builder for prometheus text format
+struct Metrics(String);
+
+impl Metrics {
+    fn header(&mut self, name: &str, help: &str, metric_type: &str) {
+        let _ = writeln!(self.0, "# HELP {METRICS_PREFIX}_{name} {help}");
+        let _ = writeln!(self.0, "# TYPE {METRICS_PREFIX}_{name} {metric_type}");
     }
 
-    pub fn list_options() {
-        // Clears terminal
-        print!("\x1B[2J\x1B[1;1H");
+    fn sample(&mut self, name: &str, labels: &str, value: u64) {
+        let _ = writeln!(self.0, "{METRICS_PREFIX}_{name}{{{labels}}} {value}");
+    }
 
-        let options = r#"
-Select an option
-1. Depth Packets Count
-2. Other Packets Processed
-3. Total UDP Packets
-4. Filtered UDP Packets
-        "#;
+    fn counter(&mut self, name: &str, help: &str, value: u64) {
+        self.header(name, help, "counter");
+        let _ = writeln!(self.0, "{METRICS_PREFIX}_{name} {value}");
+    }
 
-        println!("{options}");
+    fn gauge(&mut self, name: &str, help: &str, value: u64) {
+        self.header(name, help, "gauge");
+        let _ = writeln!(self.0, "{METRICS_PREFIX}_{name} {value}");
     }
 }
@@ -0,0 +1,438 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    hash::{Hash, Hasher},
+    mem::size_of,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    constants::{ALPHA_CHAR_LEN, GAP_EVENT_CODE, TIMESTAMP_LEN},
+    global::{OUTPUT, STATISTICS},
+    utils::{byte_utils::struct_to_bytes, time_utils::get_epoch_us},
+};
+
+use super::{
+    packet::{Feed, Packet},
+    packet_structures::{depth_output::TagMessageHeader, gap_event::TagGapEvent},
+};
+
+// No of sequence numbers remembered behind the highest seen
+const SEQ_WINDOW: u64 = 1 << 16;
+// No of unmatched messages remembered per feed
+const CONTENT_WINDOW: usize = 4096;
+// Time to wait for other feed to fill a gap, before messages are counted lost
+const GAP_TIMEOUT: Duration = Duration::from_secs(1);
+// Lost gaps are logged at most once in this interval, all are counted in statistics
+const GAP_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+// Merges primary and secondary feeds, first copy of each message wins
+pub struct Arbiter {
+    streams: HashMap<u64, SeqWindow>,
+    unmatched: [Unmatched; 2],
+}
+
+impl Arbiter {
+    pub fn new() -> Self {
+        Self {
+            streams: HashMap::new(),
+            unmatched: [Unmatched::new(), Unmatched::new()],
+        }
+    }
+
+    // Returns true if message with this seq no is seen first time on stream
+    pub fn accept_seq(&mut self, stream: u64, seq_no: u64, feed: Feed) -> bool {
+        let accepted = self
+            .streams
+            .entry(stream)
+            .or_insert_with(|| SeqWindow::new(stream))
+            .accept(seq_no, feed);
+
+        if accepted {
+            Self::record_accepted(feed);
+        }
+
+        accepted
+    }
+
+    // For messages without sequence no
+    // Message is duplicate only if same bytes were received on other feed
+    pub fn accept_bytes(&mut self, buf: &[u8], feed: Feed) -> bool {
+        let mut hasher = DefaultHasher::new();
+        buf.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let other = match feed {
+            Feed::Primary => Feed::Secondary,
+            Feed::Secondary => Feed::Primary,
+        };
+
+        if self.unmatched[other as usize].take(hash) {
+            return false;
+        }
+
+        self.unmatched[feed as usize].push(hash);
+        Self::record_accepted(feed);
+
+        true
+    }
+
+    // Called when no messages are arriving, so gaps before a quiet period are still reported
+    pub fn expire_gaps(&mut self) {
+        for window in self.streams.values_mut() {
+            window.expire_gaps();
+        }
+    }
+
+    // Called at exit, gaps still waiting are reported with what was recovered so far
+    pub fn close_gaps(&mut self) {
+        for window in self.streams.values_mut() {
+            window.close_all_gaps();
+        }
+    }
+
+    fn record_accepted(feed: Feed) {
+        let stats = STATISTICS.get();
+
+        stats.feed_accepted_count[feed as usize].inc();
+        stats.active_feed.store(feed as u8, Ordering::Relaxed);
+    }
+}
+
+struct SeqWindow {
+    stream: u64,
+    highest: u64,
+    // Ring of bits, one for each seq no in window
+    seen: Vec<u64>,
+    started: bool,
+    // Gaps still waiting to be filled, oldest first
+    gaps: VecDeque<Gap>,
+    logged_at: Option<Instant>,
+    // Lost gaps not logged since last log
+    unlogged: u64,
+}
+
+struct Gap {
+    start: u64,
+    size: u64,
+    feed: Feed,
+    recovered: u64,
+    reordered: u64,
+    detected_at: Instant,
+}
+
+impl SeqWindow {
+    fn new(stream: u64) -> Self {
+        Self {
+            stream,
+            highest: 0,
+            seen: vec![0; (SEQ_WINDOW / 64) as usize],
+            started: false,
+            gaps: VecDeque::new(),
+            logged_at: None,
+            unlogged: 0,
+        }
+    }
+
+    fn accept(&mut self, seq_no: u64, feed: Feed) -> bool {
+        self.expire_gaps();
+
+        if !self.started || self.highest - seq_no.min(self.highest) >= SEQ_WINDOW {
+            // First message, or far behind window because exchange reset sequence
+            self.close_all_gaps();
+            self.seen.fill(0);
+            self.started = true;
+        } else if seq_no <= self.highest {
+            // Late message, accept only if other feed hasn't delivered it
+            if self.is_set(seq_no) {
+                return false;
+            }
+
+            self.set(seq_no);
+            self.fill_gap(seq_no, feed);
+            return true;
+        } else {
+            if seq_no - self.highest >= SEQ_WINDOW {
+                self.seen.fill(0);
+            } else {
+                // Forget seq nos which are moving out of window
+                for skipped in self.highest + 1..seq_no {
+                    self.clear(skipped);
+                }
+            }
+
+            if seq_no > self.highest + 1 {
+                STATISTICS.get().gaps_count.inc();
+
+                self.gaps.push_back(Gap {
+                    start: self.highest + 1,
+                    size: seq_no - self.highest - 1,
+                    feed,
+                    recovered: 0,
+                    reordered: 0,
+                    detected_at: Instant::now(),
+                });
+            }
+        }
+
+        self.highest = seq_no;
+        self.set(seq_no);
+
+        true
+    }
+
+    fn fill_gap(&mut self, seq_no: u64, feed: Feed) {
+        let Some(idx) = self
+            .gaps
+            .iter()
+            .position(|gap| seq_no >= gap.start && seq_no < gap.start + gap.size)
+        else {
+            return;
+        };
+
+        let gap = &mut self.gaps[idx];
+
+        if gap.feed == feed {
+            gap.reordered += 1;
+        } else {
+            gap.recovered += 1;
+            STATISTICS.get().gap_recovered_count.inc();
+        }
+
+        if gap.recovered + gap.reordered == gap.size {
+            let gap = self.gaps.remove(idx).unwrap();
+            self.report_gap(&gap);
+        }
+    }
+
+    // Gaps not filled in time, or moving out of window are closed as lost
+    fn expire_gaps(&mut self) {
+        while let Some(gap) = self.gaps.front() {
+            let out_of_window = self.highest >= gap.start + SEQ_WINDOW;
+
+            if !out_of_window && gap.detected_at.elapsed() < GAP_TIMEOUT {
+                break;
+            }
+
+            let gap = self.gaps.pop_front().unwrap();
+            self.report_gap(&gap);
+        }
+    }
+
+    fn close_all_gaps(&mut self) {
+        while let Some(gap) = self.gaps.pop_front() {
+            self.report_gap(&gap);
+        }
+    }
+
+    fn report_gap(&mut self, gap: &Gap) {
+        let lost = gap.size - gap.recovered - gap.reordered;
+
+        STATISTICS.get().gap_lost_count.add(lost);
+
+        if lost > 0 {
+            self.log_lost(gap, lost);
+        }
+
+        let msg_header = TagMessageHeader {
+            message_code: GAP_EVENT_CODE,
+            transaction_type: 0,
+            log_time: 0,
+            alpha_char: [0; ALPHA_CHAR_LEN],
+            trader_id: 0,
+            error_code: 0,
+            timestamp: get_epoch_us() as u64,
+            timestamp1: [0; TIMESTAMP_LEN],
+            timestamp2: [0; TIMESTAMP_LEN],
+            message_length: 0,
+        };
+
+        let gap_event = TagGapEvent {
+            msg_header,
+            stream: self.stream,
+            start_seq_no: gap.start,
+            size: gap.size,
+            recovered: gap.recovered,
+            reordered: gap.reordered,
+            lost,
+            feed: gap.feed as i16,
+        };
+
+        let mut packet = Packet::new(size_of::<TagGapEvent>());
+        packet.1 = struct_to_bytes(&gap_event, &mut packet.0);
+        packet.2 = gap_event.msg_header.timestamp;
+
+        OUTPUT.write(&packet);
+    }
+
+    fn log_lost(&mut self, gap: &Gap, lost: u64) {
+        if self
+            .logged_at
+            .is_some_and(|logged_at| logged_at.elapsed() < GAP_LOG_INTERVAL)
+        {
+            self.unlogged += 1;
+            return;
+        }
+
+        println!(
+            "Lost {} of {} messages from seq no {} on stream {}",
+            lost, gap.size, gap.start, self.stream
+        );
+        if self.unlogged > 0 {
+            println!("Lost {} more gaps since last log", self.unlogged);
+        }
+
+        self.logged_at = Some(Instant::now());
+        self.unlogged = 0;
+    }
+
+    fn index(seq_no: u64) -> (usize, u64) {
+        let bit = seq_no % SEQ_WINDOW;
+
+        ((bit / 64) as usize, 1 << (bit % 64))
+    }
+
+    fn is_set(&self, seq_no: u64) -> bool {
+        let (word, mask) = Self::index(seq_no);
+
+        self.seen[word] & mask != 0
+    }
+
+    fn set(&mut self, seq_no: u64) {
+        let (word, mask) = Self::index(seq_no);
+
+        self.seen[word] |= mask;
+    }
+
+    fn clear(&mut self, seq_no: u64) {
+        let (word, mask) = Self::index(seq_no);
+
+        self.seen[word] &= !mask;
+    }
+}
+
+// Hashes of messages received on one feed, not yet seen on other
+struct Unmatched {
+    // Hash and generation of each message, oldest first, including ones matched since
+    order: VecDeque<(u64, u64)>,
+    // Generations of unmatched messages of each hash, oldest first
+    pending: HashMap<u64, VecDeque<u64>>,
+    next_generation: u64,
+}
+
+impl Unmatched {
+    fn new() -> Self {
+        Self {
+            order: VecDeque::with_capacity(CONTENT_WINDOW),
+            pending: HashMap::new(),
+            next_generation: 0,
+        }
+    }
+
+    fn push(&mut self, hash: u64) {
+        // Evict oldest, other feed has probably lost it
+        if self.order.len() == CONTENT_WINDOW {
+            if let Some((oldest, generation)) = self.order.pop_front() {
+                self.evict(oldest, generation);
+            }
+        }
+
+        let generation = self.next_generation;
+        self.next_generation += 1;
+
+        self.order.push_back((hash, generation));
+        self.pending.entry(hash).or_default().push_back(generation);
+    }
+
+    // Matches oldest unmatched message with this hash
+    fn take(&mut self, hash: u64) -> bool {
+        let Some(generations) = self.pending.get_mut(&hash) else {
+            return false;
+        };
+
+        generations.pop_front();
+
+        if generations.is_empty() {
+            self.pending.remove(&hash);
+        }
+
+        true
+    }
+
+    // Removes message only if it is still unmatched, a newer copy with same hash stays
+    fn evict(&mut self, hash: u64, generation: u64) {
+        let Some(generations) = self.pending.get_mut(&hash) else {
+            return;
+        };
+
+        // Oldest are matched first, so an unmatched evicted message is at front
+        if generations.front() == Some(&generation) {
+            generations.pop_front();
+
+            if generations.is_empty() {
+                self.pending.remove(&hash);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_from_other_feed_is_duplicate() {
+        let mut arbiter = Arbiter::new();
+
+        assert!(arbiter.accept_bytes(b"a", Feed::Primary));
+        assert!(!arbiter.accept_bytes(b"a", Feed::Secondary));
+
+        // Repeated payload is a new message once both copies were seen
+        assert!(arbiter.accept_bytes(b"a", Feed::Secondary));
+        assert!(!arbiter.accept_bytes(b"a", Feed::Primary));
+    }
+
+    #[test]
+    fn repeated_payload_matched_once_per_copy() {
+        let mut arbiter = Arbiter::new();
+
+        assert!(arbiter.accept_bytes(b"a", Feed::Primary));
+        assert!(arbiter.accept_bytes(b"a", Feed::Primary));
+        assert!(!arbiter.accept_bytes(b"a", Feed::Secondary));
+        assert!(!arbiter.accept_bytes(b"a", Feed::Secondary));
+        assert!(arbiter.accept_bytes(b"a", Feed::Secondary));
+    }
+
+    #[test]
+    fn eviction_of_matched_message_keeps_newer_copy() {
+        let mut arbiter = Arbiter::new();
+
+        // Matched, but its entry stays in window order
+        assert!(arbiter.accept_bytes(b"a", Feed::Primary));
+        assert!(!arbiter.accept_bytes(b"a", Feed::Secondary));
+
+        // Newer identical message, not yet seen on secondary
+        assert!(arbiter.accept_bytes(b"a", Feed::Primary));
+
+        // Evicts first message of primary
+        for i in 0..CONTENT_WINDOW - 1 {
+            assert!(arbiter.accept_bytes(&i.to_le_bytes(), Feed::Primary));
+        }
+
+        // Secondary copy of newer message is still a duplicate
+        assert!(!arbiter.accept_bytes(b"a", Feed::Secondary));
+    }
+
+    #[test]
+    fn evicted_message_accepted_again() {
+        let mut arbiter = Arbiter::new();
+
+        assert!(arbiter.accept_bytes(b"a", Feed::Primary));
+
+        for i in 0..CONTENT_WINDOW {
+            assert!(arbiter.accept_bytes(&i.to_le_bytes(), Feed::Primary));
+        }
+
+        assert!(arbiter.accept_bytes(b"a", Feed::Secondary));
+    }
+}
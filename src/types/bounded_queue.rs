@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+    thread,
+};
+
+use crossbeam::queue::SegQueue;
+
+use crate::{
+    global::STATISTICS,
+    settings,
+    types::settings::{OverflowPolicy, QueueConfig},
+};
+
+// Index of each queue in statistics, as per QUEUE_NAMES
+#[derive(Clone, Copy)]
+pub enum QueueKind {
+    Input = 0,
+    Packet = 1,
+    Mcx = 2,
+    Ws = 3,
+    // Queue of each output
+    Output = 4,
+    // Queue of each websocket client
+    WsClient = 5,
+    // Datagrams waiting to be journaled
+    Recorder = 6,
+}
+
+// Key of item for conflation, None if item can't be conflated
+pub type KeyFn<T> = fn(&T) -> Option<u64>;
+
+// Queue with capacity and overflow policy from settings
+pub struct BoundedQueue<T> {
+    queue: SegQueue<T>,
+    kind: QueueKind,
+    key: Option<KeyFn<T>>,
+    // Read from settings on first push, so queue can be a static
+    config: OnceLock<QueueConfig>,
+    // Latest item of each key which didn't fit in queue
+    // Popped only after queue is drained, so items of a key stay in order
+    conflated: Mutex<BTreeMap<u64, T>>,
+    overflowed: AtomicBool,
+}
+
+impl<T> BoundedQueue<T> {
+    pub const fn new(kind: QueueKind) -> Self {
+        Self {
+            queue: SegQueue::new(),
+            kind,
+            key: None,
+            config: OnceLock::new(),
+            conflated: Mutex::new(BTreeMap::new()),
+            overflowed: AtomicBool::new(false),
+        }
+    }
+
+    // Required for conflate policy
+    pub const fn with_key(kind: QueueKind, key: KeyFn<T>) -> Self {
+        let mut queue = Self::new(kind);
+        queue.key = Some(key);
+
+        queue
+    }
+
+    fn config(&self) -> &QueueConfig {
+        self.config
+            .get_or_init(|| settings::get().queues.get(self.kind))
+    }
+
+    // Returns false if this or an older item was dropped
+    pub fn push(&self, item: T) -> bool {
+        // Newer items must not overtake conflated ones
+        if self.overflowed.load(Ordering::Acquire) {
+            return self.push_conflated(item);
+        }
+
+        let config = self.config();
+
+        if self.queue.len() < config.capacity {
+            self.queue.push(item);
+            return true;
+        }
+
+        STATISTICS.get().queue_overflow_counts[self.kind as usize].inc();
+
+        match config.policy {
+            OverflowPolicy::Block => {
+                while self.queue.len() >= config.capacity {
+                    thread::yield_now();
+                }
+
+                self.queue.push(item);
+                true
+            }
+            OverflowPolicy::DropOldest => {
+                if self.queue.pop().is_some() {
+                    STATISTICS.get().dropped_count.inc();
+                }
+
+                self.queue.push(item);
+                false
+            }
+            OverflowPolicy::DropNewest => {
+                STATISTICS.get().dropped_count.inc();
+                false
+            }
+            OverflowPolicy::Conflate => self.push_conflated(item),
+        }
+    }
+
+    fn push_conflated(&self, item: T) -> bool {
+        let Some(key) = self.key.and_then(|key| key(&item)) else {
+            STATISTICS.get().dropped_count.inc();
+            return false;
+        };
+
+        let mut conflated = self.conflated.lock().unwrap();
+
+        let replaced = conflated.insert(key, item).is_some();
+        self.overflowed.store(true, Ordering::Release);
+
+        if replaced {
+            STATISTICS.get().conflated_count.inc();
+        }
+
+        !replaced
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        if let Some(item) = self.queue.pop() {
+            return Some(item);
+        }
+
+        if !self.overflowed.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let mut conflated = self.conflated.lock().unwrap();
+        let item = conflated.pop_first().map(|(_, item)| item);
+
+        // Back to queue once all conflated items are delivered
+        if conflated.is_empty() {
+            self.overflowed.store(false, Ordering::Release);
+        }
+
+        item
+    }
+
+    // Conflated items are not counted
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty() && !self.overflowed.load(Ordering::Acquire)
+    }
+}
+
+impl<T> fmt::Debug for BoundedQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BoundedQueue")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,119 @@
+use std::{collections::HashMap, mem::offset_of};
+
+use serde::Deserialize;
+
+use crate::{
+    constants::{
+        ENRICHED_PICTURE_CODE, GAP_EVENT_CODE, INDEX_STATS_TEMPLATE_ID,
+        INSTRUMENT_STATE_TEMPLATE_ID, MASS_INSTRUMENT_STATE_TEMPLATE_ID, MASTER_CHANGE_CODE,
+        PRODUCT_STATE_TEMPLATE_ID,
+    },
+    output::depth_message_codes,
+};
+
+use super::{packet::Packet, packet_structures::neq::BcastHeaders, settings::Exchange};
+
+// Kind of message, used by clients to pick what they receive
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageClass {
+    Depth,
+    Indices,
+    // Market open, close and security status changes
+    Status,
+    MasterChange,
+    News,
+    Gap,
+    Other,
+}
+
+// Codes and classes of output messages of an exchange
+pub struct MessageClassifier {
+    exchange: Exchange,
+    classes: HashMap<i32, MessageClass>,
+}
+
+impl MessageClassifier {
+    pub fn new(exchange: Exchange) -> Self {
+        let mut classes = HashMap::new();
+
+        for code in depth_message_codes(exchange) {
+            classes.insert(code, MessageClass::Depth);
+        }
+        classes.insert(ENRICHED_PICTURE_CODE, MessageClass::Depth);
+        classes.insert(MASTER_CHANGE_CODE, MessageClass::MasterChange);
+        classes.insert(GAP_EVENT_CODE, MessageClass::Gap);
+
+        let (indices, status, news): (&[i32], &[i32], &[i32]) = match exchange {
+            Exchange::NEQ => (
+                &[7207, 7216, 8207],
+                &[6511, 6521, 6531, 6541, 6571, 6581, 7206, 18130],
+                &[5294, 6501],
+            ),
+            Exchange::NFO => (
+                &[7203],
+                &[6511, 6521, 6531, 6541, 6571, 7206, 7320],
+                &[5294, 6501],
+            ),
+            Exchange::NCD => (
+                &[7203],
+                &[6511, 6521, 6522, 6531, 6541, 6571, 7206, 7320],
+                &[5294, 6501],
+            ),
+            Exchange::BSE => (&[2011, 2012], &[2002, 2003], &[2004]),
+            Exchange::MCX => (
+                &[INDEX_STATS_TEMPLATE_ID],
+                &[
+                    INSTRUMENT_STATE_TEMPLATE_ID,
+                    MASS_INSTRUMENT_STATE_TEMPLATE_ID,
+                    PRODUCT_STATE_TEMPLATE_ID,
+                ],
+                &[],
+            ),
+        };
+
+        for (codes, class) in [
+            (indices, MessageClass::Indices),
+            (status, MessageClass::Status),
+            (news, MessageClass::News),
+        ] {
+            for code in codes {
+                classes.insert(*code, class);
+            }
+        }
+
+        Self { exchange, classes }
+    }
+
+    // Converted messages start with code, other nse messages are written as twiddled exchange struct
+    pub fn message_code(&self, packet: &Packet) -> i32 {
+        let message_code = i32::from_le_bytes(packet.0[0..4].try_into().unwrap());
+
+        match self.exchange {
+            Exchange::NEQ | Exchange::NFO | Exchange::NCD if !self.is_converted(message_code) => {
+                i16::from_le_bytes(
+                    packet.0[offset_of!(BcastHeaders, trans_code)..][..2]
+                        .try_into()
+                        .unwrap(),
+                ) as i32
+            }
+            _ => message_code,
+        }
+    }
+
+    pub fn class(&self, message_code: i32) -> MessageClass {
+        self.classes
+            .get(&message_code)
+            .copied()
+            .unwrap_or(MessageClass::Other)
+    }
+
+    // Message has TagMessageHeader
+    fn is_converted(&self, message_code: i32) -> bool {
+        self.exchange == Exchange::MCX
+            || matches!(
+                self.class(message_code),
+                MessageClass::Depth | MessageClass::MasterChange | MessageClass::Gap
+            )
+    }
+}
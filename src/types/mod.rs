@@ -1,6 +1,14 @@
+pub mod arbiter;
+pub mod bounded_queue;
+pub mod message_class;
 pub mod packet;
+pub mod packet_pool;
 pub mod packet_structures;
+pub mod security_master;
 pub mod settings;
 pub mod state;
-pub mod unsafe_hashmap;
+pub mod token_filter;
+pub mod token_map;
+pub mod wait;
 pub mod work;
+pub mod worker_queue;
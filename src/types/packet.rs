@@ -5,70 +5,145 @@ use crate::{
         BCAST_MBO_MBP, BCAST_ONLY_MBP, BCAST_ONLY_MBP_EQ, BUF_SIZE, MAX_SUB_PACKETS, SKIP_BYTES,
     },
     global::STATISTICS,
-    utils::byte_utils::{bytes_to_struct, bytes_to_struct_mut, create_empty},
+    utils::byte_utils::{
+        bytes_to_struct, bytes_to_struct_mut, try_bytes_to_struct, try_slice, ParseError,
+    },
     workers::nse_worker::get_token,
 };
 
 use super::{
-    packet_structures::{neq::BcastHeaders, nfo, CompressionData, PackData},
+    arbiter::Arbiter,
+    packet_pool::PacketBuf,
+    packet_structures::{
+        depth_output::TagMarketPictureBroadcast,
+        ncd::NcdBroadcastTransactionMapping,
+        neq::{BcastHeaders, NeqBroadcastTransactionMapping},
+        nfo::{self, NfoBroadcastTransactionMapping},
+        PackData,
+    },
     work::WorkType,
 };
 
-#[derive(Debug, Clone, Copy)]
-pub struct Packet(pub [u8; BUF_SIZE], pub usize);
+// Workers write market picture over message, so nse packets can hold it
+const NSE_PACKET_SIZE: usize = size_of::<TagMarketPictureBroadcast>();
+// Exchange structs are read whole from message, which can be shorter
+const NSE_STRUCT_SIZE: usize = SKIP_BYTES
+    + max(
+        size_of::<NeqBroadcastTransactionMapping>(),
+        max(
+            size_of::<NfoBroadcastTransactionMapping>(),
+            size_of::<NcdBroadcastTransactionMapping>(),
+        ),
+    );
+
+const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+// Multicast feed a packet was received on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feed {
+    Primary = 0,
+    Secondary = 1,
+}
+
+// Buffer, length of data in it and epoch microseconds when its datagram was received
+// Cloning doesn't copy buffer
+#[derive(Debug, Clone)]
+pub struct Packet(pub PacketBuf, pub usize, pub u64);
 
 impl Packet {
-    pub fn get_nse_packets(&self) -> ([(Packet, WorkType); MAX_SUB_PACKETS], usize) {
-        let mut packets: [(Packet, WorkType); MAX_SUB_PACKETS] = create_empty();
-        let mut packet_idx = 0;
+    // Zeroed up to len, as structs written to it are read back whole
+    pub fn new(len: usize) -> Self {
+        let mut packet = Self::uninit(len);
+        packet.0[..len].fill(0);
 
-        let mut packet: PackData = bytes_to_struct(&self.0);
-        packet.twiddle();
+        packet
+    }
 
-        let mut offset = 0;
+    // For buffers which are written whole before they are read
+    pub fn uninit(len: usize) -> Self {
+        Packet(PacketBuf::uninit(len), len, 0)
+    }
 
-        for _ in 0..packet.no_of_packets {
-            let mut compression_data: CompressionData =
-                bytes_to_struct(&packet.pack_data[offset..]);
-            compression_data.twiddle();
+    // Buffer can hold capacity bytes, bytes after slice are left from previous use
+    pub fn from_slice(bytes: &[u8], capacity: usize) -> Self {
+        let mut packet = Self::uninit(capacity.max(bytes.len()));
+        packet.0[..bytes.len()].copy_from_slice(bytes);
+        packet.1 = bytes.len();
 
-            // Increment for compression length field, which is u16, 2 bytes
-            offset += size_of::<u16>();
+        packet
+    }
 
-            // Packet is not compressed
-            if compression_data.compression_len == 0 {
-                let packet = Packet(compression_data.broadcast_data, BUF_SIZE);
+    // Zeroes bytes after data up to len, so a struct read of len bytes sees no previous contents
+    pub fn zero_tail(&mut self, len: usize) {
+        if len > self.1 {
+            let start = self.1;
+            self.0[start..len].fill(0);
+        }
+    }
 
-                // Extract message length to increase offset
-                let start = SKIP_BYTES + offset_of!(BcastHeaders, message_length);
-                let end = start + size_of::<i16>();
+    pub fn get_nse_packets(
+        &self,
+        arbiter: &mut Arbiter,
+        feed: Feed,
+    ) -> Result<Vec<(Packet, WorkType)>, ParseError> {
+        let mut packets: Vec<(Packet, WorkType)> = Vec::with_capacity(MAX_SUB_PACKETS);
 
-                let mut message_length: i16 = bytes_to_struct(&packet.0[start..end]);
-                message_length = message_length.to_be();
+        let messages = self.get_nse_messages()?;
 
-                // Increment offset by each message
-                offset += message_length as usize + SKIP_BYTES + size_of::<u16>();
+        for (offset, compression_len, len) in messages {
+            let message = &self.0[offset..offset + len];
+
+            // Packet is not compressed
+            if compression_len == 0 {
+                if !Self::accept_nse_message(message, arbiter, feed) {
+                    continue;
+                }
+
+                let mut packet = Packet::from_slice(message, NSE_PACKET_SIZE);
+                packet.zero_tail(NSE_STRUCT_SIZE);
+                packet.2 = self.2;
+
+                STATISTICS.record_message_code(BcastHeaders::get_trans_code(&packet.0) as i32);
 
                 let work_type = WorkType::NseUncompressed;
 
-                packets[packet_idx] = (packet, work_type);
-                packet_idx += 1;
-                STATISTICS.get().other_packets_count += 1;
+                packets.push((packet, work_type));
+                STATISTICS.get().other_packets_count.inc();
             } else {
                 // Packet is compressed
 
-                offset += compression_data.compression_len as usize;
+                let mut compressed_packet = message;
+                // Decompressed directly into pooled buffer
+                let mut packet = Packet::uninit(NSE_PACKET_SIZE);
+
+                // Skip only this message if it can't be decompressed
+                let Ok(decompressed_len) =
+                    mylzo::decompress(&mut compressed_packet, &mut packet.0[..])
+                else {
+                    println!("Malformed nse packet: {}", ParseError::Decompress);
+                    STATISTICS.get().decompress_failed_count.inc();
+                    STATISTICS.record_malformed();
+                    continue;
+                };
 
-                let mut compressed_packet =
-                    &compression_data.broadcast_data[..compression_data.compression_len as usize];
-                let mut decompressed_packet = [0u8; BUF_SIZE];
+                STATISTICS.get().decompressed_count.inc();
 
-                mylzo::decompress(&mut compressed_packet, &mut decompressed_packet)
-                    .expect("Error decompressing packet");
+                if !Self::accept_nse_message(&packet.0[..decompressed_len], arbiter, feed) {
+                    continue;
+                }
 
-                let mut packet = Packet(decompressed_packet, BUF_SIZE);
+                packet.1 = decompressed_len;
+                packet.zero_tail(NSE_STRUCT_SIZE);
+                packet.2 = self.2;
 
                 let trans_code = BcastHeaders::get_trans_code(&packet.0);
+                STATISTICS.record_message_code(trans_code as i32);
 
                 // Fetch worktype for compressed packet
                 let work_type = if trans_code == BCAST_ONLY_MBP
@@ -97,18 +172,18 @@ impl Packet {
                     let end = start + size_of::<i16>();
 
                     // Get no of packets
-                    let mut no_of_records: i16 = bytes_to_struct(&packet.0[start..end]);
+                    let mut no_of_records: i16 = bytes_to_struct(&packet.0[start..end])?;
                     no_of_records = no_of_records.to_be();
 
                     // Set no of records to 0, for original packet
                     // No need to twiddle 0
-                    *bytes_to_struct_mut::<i16>(&mut packet.0[start..end]) = 0;
+                    *bytes_to_struct_mut::<i16>(&mut packet.0[start..end])? = 0;
 
                     // If more than one record
                     // Push first record into packet by setting no of records as 0
                     // increment it and re add same packet
                     if no_of_records > 1 {
-                        // Clone packet
+                        // Clone packet, buffer is copied on first write as records differ
                         // Set no of records to zero and push
                         let mut packet = packet.clone();
 
@@ -117,24 +192,274 @@ impl Packet {
                         let work_type = WorkType::TokenWise(token);
 
                         // Mutable ref to slice
-                        let no_of_records = bytes_to_struct_mut::<i16>(&mut packet.0[start..end]);
+                        let no_of_records = bytes_to_struct_mut::<i16>(&mut packet.0[start..end])?;
                         *no_of_records = 1;
                         // Twiddle, because this will be twiddeled again while processing
                         *no_of_records = no_of_records.to_be();
 
                         // Add packet and increase packet idx
-                        packets[packet_idx] = (packet, work_type);
-                        packet_idx += 1;
-                        STATISTICS.get().depth_packets_count += 1;
+                        packets.push((packet, work_type));
+                        STATISTICS.get().depth_packets_count.inc();
                     }
                 }
 
-                packets[packet_idx] = (packet, work_type);
-                packet_idx += 1;
-                STATISTICS.get().depth_packets_count += 1;
+                packets.push((packet, work_type));
+                STATISTICS.get().depth_packets_count.inc();
+            }
+        }
+
+        Ok(packets)
+    }
+
+    // Returns offset, compression length and length of each message in received bytes
+    // Whole packet is checked first, so a corrupt packet is dropped before any message is used
+    fn get_nse_messages(&self) -> Result<Vec<(usize, usize, usize)>, ParseError> {
+        let buf = &self.0[..self.1];
+
+        let no_of_packets: u16 = try_bytes_to_struct(buf, offset_of!(PackData, no_of_packets))?;
+        let no_of_packets = no_of_packets.to_be() as usize;
+
+        if no_of_packets > MAX_SUB_PACKETS {
+            return Err(ParseError::InvalidCount {
+                field: "no_of_packets",
+                count: no_of_packets as i64,
+                max: MAX_SUB_PACKETS,
+            });
+        }
+
+        let mut messages = Vec::with_capacity(no_of_packets);
+        let mut offset = offset_of!(PackData, pack_data);
+
+        for _ in 0..no_of_packets {
+            let compression_len: u16 = try_bytes_to_struct(buf, offset)?;
+            let compression_len = compression_len.to_be() as usize;
+
+            // Increment for compression length field, which is u16, 2 bytes
+            offset += size_of::<u16>();
+
+            if compression_len == 0 {
+                // Extract message length to increase offset
+                let start = offset + SKIP_BYTES + offset_of!(BcastHeaders, message_length);
+
+                let message_length: i16 = try_bytes_to_struct(buf, start)?;
+                let message_length = message_length.to_be();
+
+                if message_length < 0 {
+                    return Err(ParseError::InvalidCount {
+                        field: "message_length",
+                        count: message_length as i64,
+                        max: BUF_SIZE,
+                    });
+                }
+
+                let len = message_length as usize + SKIP_BYTES;
+
+                // Message must at least hold its headers, which are read before parsing
+                if len < SKIP_BYTES + size_of::<BcastHeaders>() {
+                    return Err(ParseError::Truncated {
+                        offset,
+                        needed: SKIP_BYTES + size_of::<BcastHeaders>(),
+                        len,
+                    });
+                }
+
+                try_slice(buf, offset, len)?;
+
+                messages.push((offset, 0, len));
+
+                // Increment offset by each message
+                offset += len + size_of::<u16>();
+            } else {
+                try_slice(buf, offset, compression_len)?;
+
+                messages.push((offset, compression_len, compression_len));
+
+                offset += compression_len;
+            }
+        }
+
+        Ok(messages)
+    }
+
+    // Drops message if it was already received on other feed, or is too short for headers
+    fn accept_nse_message(message: &[u8], arbiter: &mut Arbiter, feed: Feed) -> bool {
+        let seq_no =
+            try_slice(message, 0, SKIP_BYTES + size_of::<BcastHeaders>()).and_then(|headers| {
+                try_bytes_to_struct::<i32>(
+                    headers,
+                    SKIP_BYTES + offset_of!(BcastHeaders, bc_seq_no),
+                )
+            });
+        let seq_no = match seq_no {
+            Ok(seq_no) => seq_no.to_be(),
+            Err(e) => {
+                println!("Malformed nse packet: {}", e);
+                STATISTICS.record_malformed();
+                return false;
+            }
+        };
+
+        // Messages without seq no are matched by content
+        let accepted = if seq_no > 0 {
+            arbiter.accept_seq(0, seq_no as u64, feed)
+        } else {
+            arbiter.accept_bytes(message, feed)
+        };
+
+        if !accepted {
+            STATISTICS.get().duplicate_packets_count.inc();
+        }
+
+        accepted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADERS_LEN: usize = SKIP_BYTES + size_of::<BcastHeaders>();
+
+    // Uncompressed message with headers only, as per given message length
+    fn message(message_length: i16, seq_no: i32) -> Vec<u8> {
+        let mut message = vec![0; HEADERS_LEN];
+
+        let start = SKIP_BYTES + offset_of!(BcastHeaders, message_length);
+        message[start..start + 2].copy_from_slice(&message_length.to_be_bytes());
+
+        let start = SKIP_BYTES + offset_of!(BcastHeaders, bc_seq_no);
+        message[start..start + 4].copy_from_slice(&seq_no.to_be_bytes());
+
+        message
+    }
+
+    // Pack data with given no of packets, followed by compression length and bytes of each message
+    fn datagram(no_of_packets: u16, messages: &[(u16, Vec<u8>)]) -> Packet {
+        let mut bytes = vec![0, 0];
+        bytes.extend_from_slice(&no_of_packets.to_be_bytes());
+
+        for (compression_len, message) in messages {
+            bytes.extend_from_slice(&compression_len.to_be_bytes());
+            bytes.extend_from_slice(message);
+
+            if *compression_len == 0 {
+                bytes.extend_from_slice(&[0, 0]);
             }
         }
 
-        (packets, packet_idx)
+        Packet::from_slice(&bytes, bytes.len())
+    }
+
+    fn valid_message(seq_no: i32) -> (u16, Vec<u8>) {
+        (0, message(size_of::<BcastHeaders>() as i16, seq_no))
+    }
+
+    #[test]
+    fn truncated_pack_header() {
+        let packet = Packet::from_slice(&[0, 0, 0], 3);
+
+        assert!(matches!(
+            packet.get_nse_messages(),
+            Err(ParseError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn too_many_packets() {
+        let messages: Vec<_> = (1..=13).map(valid_message).collect();
+        let packet = datagram(13, &messages);
+
+        assert!(matches!(
+            packet.get_nse_messages(),
+            Err(ParseError::InvalidCount {
+                field: "no_of_packets",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn max_packets_are_accepted() {
+        let messages: Vec<_> = (1..=MAX_SUB_PACKETS as i32).map(valid_message).collect();
+        let packet = datagram(MAX_SUB_PACKETS as u16, &messages);
+
+        let packets = packet
+            .get_nse_packets(&mut Arbiter::new(), Feed::Primary)
+            .unwrap();
+
+        assert_eq!(packets.len(), MAX_SUB_PACKETS);
+    }
+
+    #[test]
+    fn message_shorter_than_headers() {
+        for message_length in [0, 17, size_of::<BcastHeaders>() as i16 - 1] {
+            let packet = datagram(1, &[(0, message(message_length, 1))]);
+
+            assert!(matches!(
+                packet.get_nse_messages(),
+                Err(ParseError::Truncated { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn negative_message_length() {
+        let packet = datagram(1, &[(0, message(-1, 1))]);
+
+        assert!(matches!(
+            packet.get_nse_messages(),
+            Err(ParseError::InvalidCount {
+                field: "message_length",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn message_past_end_of_packet() {
+        let mut message = message(size_of::<BcastHeaders>() as i16 + 100, 1);
+        message.truncate(HEADERS_LEN);
+        let packet = datagram(1, &[(0, message)]);
+
+        assert!(matches!(
+            packet.get_nse_messages(),
+            Err(ParseError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn missing_second_message() {
+        let packet = datagram(2, &[valid_message(1)]);
+
+        assert!(matches!(
+            packet.get_nse_messages(),
+            Err(ParseError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn oversized_compression_len() {
+        let packet = datagram(1, &[(600, vec![0; 10])]);
+
+        assert!(matches!(
+            packet.get_nse_messages(),
+            Err(ParseError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn short_message_is_dropped() {
+        let mut arbiter = Arbiter::new();
+
+        assert!(!Packet::accept_nse_message(
+            &[0; HEADERS_LEN - 1],
+            &mut arbiter,
+            Feed::Primary
+        ));
+        assert!(Packet::accept_nse_message(
+            &message(size_of::<BcastHeaders>() as i16, 1),
+            &mut arbiter,
+            Feed::Primary
+        ));
     }
 }
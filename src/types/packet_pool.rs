@@ -0,0 +1,119 @@
+use std::{
+    fmt,
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut},
+    slice,
+    sync::Arc,
+};
+
+use crossbeam::queue::ArrayQueue;
+
+use crate::{
+    constants::{PACKET_POOL_SIZE, PACKET_SIZE_CLASSES},
+    global::{PACKET_POOL, STATISTICS},
+};
+
+// Free buffers of each size class, reused so packets don't allocate on hot path
+pub struct PacketPool {
+    free: [ArrayQueue<Arc<[u8]>>; PACKET_SIZE_CLASSES.len()],
+}
+
+impl PacketPool {
+    pub fn new() -> Self {
+        Self {
+            free: [(); PACKET_SIZE_CLASSES.len()].map(|_| ArrayQueue::new(PACKET_POOL_SIZE)),
+        }
+    }
+
+    // Smallest buffer which holds len bytes, contents are left from previous use
+    fn get(&self, len: usize) -> Arc<[u8]> {
+        let class = PACKET_SIZE_CLASSES
+            .iter()
+            .position(|size| len <= *size)
+            .unwrap_or_else(|| panic!("Packet of {} bytes is larger than any buffer", len));
+
+        self.free[class].pop().unwrap_or_else(|| {
+            STATISTICS.get().packet_allocations_count.inc();
+
+            Arc::from(vec![0; PACKET_SIZE_CLASSES[class]])
+        })
+    }
+
+    // Buffer is freed if pool is full
+    fn put(&self, buffer: Arc<[u8]>) {
+        if let Some(class) = PACKET_SIZE_CLASSES
+            .iter()
+            .position(|size| buffer.len() == *size)
+        {
+            let _ = self.free[class].push(buffer);
+        }
+    }
+
+    pub fn touch(&self) {}
+}
+
+// Reference counted buffer from pool, cloning only increments count
+// Writing to a shared buffer copies it first, so clones never see each other's writes
+// Buffer can be larger than asked for, as per its size class
+pub struct PacketBuf(ManuallyDrop<Arc<[u8]>>);
+
+impl PacketBuf {
+    // Holds at least len bytes, contents are left from previous use
+    pub fn uninit(len: usize) -> Self {
+        Self(ManuallyDrop::new(PACKET_POOL.get(len)))
+    }
+
+    // For receiving into, socket doesn't read the bytes
+    pub fn as_uninit_mut(&mut self) -> &mut [MaybeUninit<u8>] {
+        let buf: &mut [u8] = self;
+
+        unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut MaybeUninit<u8>, buf.len()) }
+    }
+}
+
+impl Deref for PacketBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0[..]
+    }
+}
+
+impl DerefMut for PacketBuf {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if Arc::get_mut(&mut self.0).is_none() {
+            // Shared, copy before writing
+            let mut copy = Self::uninit(self.len());
+            copy.copy_from_slice(self);
+
+            *self = copy;
+        }
+
+        Arc::get_mut(&mut self.0).unwrap()
+    }
+}
+
+impl Clone for PacketBuf {
+    fn clone(&self) -> Self {
+        Self(ManuallyDrop::new(Arc::clone(&self.0)))
+    }
+}
+
+impl Drop for PacketBuf {
+    fn drop(&mut self) {
+        let mut buffer = unsafe { ManuallyDrop::take(&mut self.0) };
+
+        // Last reference returns buffer to pool
+        if Arc::get_mut(&mut buffer).is_some() {
+            PACKET_POOL.put(buffer);
+        }
+    }
+}
+
+impl fmt::Debug for PacketBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PacketBuf")
+            .field("refs", &Arc::strong_count(&self.0))
+            .finish_non_exhaustive()
+    }
+}
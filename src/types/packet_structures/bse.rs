@@ -1,6 +1,6 @@
 use crate::{
     constants::*,
-    utils::byte_utils::{bytes_to_struct, struct_to_bytes},
+    utils::byte_utils::{bytes_to_struct, struct_to_bytes, ParseError},
 };
 use twiddler::Twiddle;
 
@@ -27,29 +27,72 @@ pub enum BseBroadcastTransactionMapping {
     BcastCallAuctionCxlQtyMsg(BcastCACxlQtyMessage),
 }
 
-pub fn build_bse_struct(transaction_id: i16, buf: &[u8]) -> BseBroadcastTransactionMapping {
-    match transaction_id {
-        2001 => BseBroadcastTransactionMapping::BcastTimeMessage(bytes_to_struct(&buf)),
-        2002 => BseBroadcastTransactionMapping::BcastSessionChange(bytes_to_struct(&buf)),
-        2003 => BseBroadcastTransactionMapping::BcastAuctoinSessionChange(bytes_to_struct(&buf)),
-        2004 => BseBroadcastTransactionMapping::BcastNewsHeadline(bytes_to_struct(&buf)),
-        2011 => BseBroadcastTransactionMapping::BcastIndex1(bytes_to_struct(&buf)),
-        2012 => BseBroadcastTransactionMapping::BcastIndex2(bytes_to_struct(&buf)),
-        2014 => BseBroadcastTransactionMapping::BcastClosePrice(bytes_to_struct(&buf)),
-        2015 => BseBroadcastTransactionMapping::BcastOpenInterestMsg(bytes_to_struct(&buf)),
-        2016 => BseBroadcastTransactionMapping::BcastVarPercentage(bytes_to_struct(&buf)),
-        2017 => BseBroadcastTransactionMapping::BcastAuctionMbp(bytes_to_struct(&buf)),
-        2020 => BseBroadcastTransactionMapping::BcastMbp(bytes_to_struct(&buf)),
-        2021 => BseBroadcastTransactionMapping::BcastMbpComplexInst(bytes_to_struct(&buf)),
-        2022 => BseBroadcastTransactionMapping::BcastRbiRefRate(bytes_to_struct(&buf)),
-        2027 => BseBroadcastTransactionMapping::BcastOddLotMbp(bytes_to_struct(&buf)),
-        2028 => BseBroadcastTransactionMapping::BcastImpliedVolatility(bytes_to_struct(&buf)),
-        2030 => BseBroadcastTransactionMapping::BcastKeepAlive(bytes_to_struct(&buf)),
-        2033 => BseBroadcastTransactionMapping::BcastDebtMbp(bytes_to_struct(&buf)),
-        2034 => BseBroadcastTransactionMapping::BcastLppRange(bytes_to_struct(&buf)),
-        2035 => BseBroadcastTransactionMapping::BcastCallAuctionCxlQtyMsg(bytes_to_struct(&buf)),
-        _ => panic!("Invalid bse transaction id: {}", transaction_id),
-    }
+pub fn build_bse_struct(
+    transaction_id: i16,
+    buf: &[u8],
+) -> Result<Option<BseBroadcastTransactionMapping>, ParseError> {
+    let mapping = match transaction_id {
+        2001 => Some(BseBroadcastTransactionMapping::BcastTimeMessage(
+            bytes_to_struct(buf)?,
+        )),
+        2002 => Some(BseBroadcastTransactionMapping::BcastSessionChange(
+            bytes_to_struct(buf)?,
+        )),
+        2003 => Some(BseBroadcastTransactionMapping::BcastAuctoinSessionChange(
+            bytes_to_struct(buf)?,
+        )),
+        2004 => Some(BseBroadcastTransactionMapping::BcastNewsHeadline(
+            bytes_to_struct(buf)?,
+        )),
+        2011 => Some(BseBroadcastTransactionMapping::BcastIndex1(
+            bytes_to_struct(buf)?,
+        )),
+        2012 => Some(BseBroadcastTransactionMapping::BcastIndex2(
+            bytes_to_struct(buf)?,
+        )),
+        2014 => Some(BseBroadcastTransactionMapping::BcastClosePrice(
+            bytes_to_struct(buf)?,
+        )),
+        2015 => Some(BseBroadcastTransactionMapping::BcastOpenInterestMsg(
+            bytes_to_struct(buf)?,
+        )),
+        2016 => Some(BseBroadcastTransactionMapping::BcastVarPercentage(
+            bytes_to_struct(buf)?,
+        )),
+        2017 => Some(BseBroadcastTransactionMapping::BcastAuctionMbp(
+            bytes_to_struct(buf)?,
+        )),
+        2020 => Some(BseBroadcastTransactionMapping::BcastMbp(bytes_to_struct(
+            buf,
+        )?)),
+        2021 => Some(BseBroadcastTransactionMapping::BcastMbpComplexInst(
+            bytes_to_struct(buf)?,
+        )),
+        2022 => Some(BseBroadcastTransactionMapping::BcastRbiRefRate(
+            bytes_to_struct(buf)?,
+        )),
+        2027 => Some(BseBroadcastTransactionMapping::BcastOddLotMbp(
+            bytes_to_struct(buf)?,
+        )),
+        2028 => Some(BseBroadcastTransactionMapping::BcastImpliedVolatility(
+            bytes_to_struct(buf)?,
+        )),
+        2030 => Some(BseBroadcastTransactionMapping::BcastKeepAlive(
+            bytes_to_struct(buf)?,
+        )),
+        2033 => Some(BseBroadcastTransactionMapping::BcastDebtMbp(
+            bytes_to_struct(buf)?,
+        )),
+        2034 => Some(BseBroadcastTransactionMapping::BcastLppRange(
+            bytes_to_struct(buf)?,
+        )),
+        2035 => Some(BseBroadcastTransactionMapping::BcastCallAuctionCxlQtyMsg(
+            bytes_to_struct(buf)?,
+        )),
+        _ => None,
+    };
+
+    Ok(mapping)
 }
 
 impl BseBroadcastTransactionMapping {
@@ -0,0 +1,33 @@
+use serde::Serialize;
+use twiddler::Twiddle;
+
+use crate::constants::{INSTRUMENT_SERIES_LEN, INSTRUMENT_SYMBOL_LEN, OPTION_TYPE_LEN};
+
+use super::depth_output::{TagMarketPictureBroadcast, TagMessageHeader};
+
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagInstrument {
+    // Null padded
+    pub symbol: [u8; INSTRUMENT_SYMBOL_LEN],
+    pub series: [u8; INSTRUMENT_SERIES_LEN],
+    pub option_type: [u8; OPTION_TYPE_LEN],
+    // As yyyymmdd, 0 if instrument doesn't expire
+    pub expiry: i32,
+    // In exchange price units
+    pub strike_price: i64,
+    pub tick_size: i32,
+    pub lot_size: i32,
+    // Exchange price units per rupee
+    pub price_multiplier: i64,
+}
+
+// Market picture with details of its instrument from security master
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagEnrichedMarketPicture {
+    pub msg_header: TagMessageHeader,
+    pub instrument: TagInstrument,
+    // Last, because depth is trimmed to no of filled levels
+    pub market_picture: TagMarketPictureBroadcast,
+}
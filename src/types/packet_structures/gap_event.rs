@@ -0,0 +1,22 @@
+use serde::Serialize;
+use twiddler::Twiddle;
+
+use super::depth_output::TagMessageHeader;
+
+// Published for nse and mcx only, bse messages carry no seq no
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagGapEvent {
+    pub msg_header: TagMessageHeader,
+    // Stream the gap is in, 0 for nse and SenderCompID for mcx
+    pub stream: u64,
+    pub start_seq_no: u64,
+    pub size: u64,
+    // Messages which arrived later on other feed
+    pub recovered: u64,
+    // Messages which arrived later on the same feed
+    pub reordered: u64,
+    pub lost: u64,
+    // Feed on which the gap was detected
+    pub feed: i16,
+}
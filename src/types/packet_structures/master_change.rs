@@ -0,0 +1,28 @@
+use serde::Serialize;
+use twiddler::Twiddle;
+
+use crate::constants::{PARTICIPANT_ID_LEN, PARTICIPANT_NAME_LEN};
+
+use super::{depth_output::TagMessageHeader, enriched_output::TagInstrument};
+
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagMasterChange {
+    pub msg_header: TagMessageHeader,
+    // 1 security, 2 participant, 3 instrument type, 4 spread
+    pub master: i16,
+    // 1 added, 2 modified, 3 deleted
+    pub action: i16,
+    // Token for security, id for instrument type and first token for spread
+    pub id: i64,
+    // Second token for spread
+    pub id2: i64,
+    // Participant id or instrument type name, null padded
+    pub code: [u8; PARTICIPANT_ID_LEN],
+    // Participant name or instrument type description, null padded
+    pub name: [u8; PARTICIPANT_NAME_LEN],
+    // Participant status
+    pub status: u8,
+    // Details of security, zero for other masters
+    pub instrument: TagInstrument,
+}
@@ -19,16 +19,48 @@ pub enum Message {
     ComplexInstrumentUpdate(ComplexInstrumentUpdate),
 }
 
+impl Message {
+    // Template id as per fast template, 0 for internal messages
+    pub fn template_id(&self) -> i32 {
+        match self {
+            Message::MDPacketHeader(_) => 65,
+            Message::FastReset(_) => 120,
+            Message::DepthSnapshot(_) => 101,
+            Message::DepthIncremental(_) => 102,
+            Message::InstrumentStateChange(_) => 103,
+            Message::MassInstrumentStateChange(_) => 104,
+            Message::ComplexInstrumentUpdate(_) => 105,
+            Message::QuoteRequest(_) => 106,
+            Message::CrossRequest(_) => 107,
+            Message::ProductStateChange(_) => 108,
+            Message::FlexibleInstrumentUpdate(_) => 110,
+            Message::IndexStats(_) => 51,
+            Message::TopOfBookImplied(_) => 513,
+            Message::DepthSnapshotEmpty(_) | Message::MDIncGrp(_) => 0,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FastReset {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MDPacketHeader {
-    SenderCompID: u32,
+    pub SenderCompID: u32,
     #[serde(with = "serde_bytes")]
-    PacketSeqNum: Vec<u8>,
+    pub PacketSeqNum: Vec<u8>,
     #[serde(with = "serde_bytes")]
-    SendingTime: Vec<u8>,
+    pub SendingTime: Vec<u8>,
+}
+
+impl MDPacketHeader {
+    // PacketSeqNum is a fixed 4 byte vector, little endian
+    pub fn packet_seq_num(&self) -> u64 {
+        self.PacketSeqNum
+            .iter()
+            .rev()
+            .fold(0, |seq_no, byte| (seq_no << 8) | *byte as u64)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -141,67 +173,67 @@ impl MDSshGrp {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct MassInstrumentStateChange {
-    MsgType: String,
-    MsgSeqNum: u32,
-    SenderCompID: u32,
-    MarketSegmentID: u32,
-    InstrumentScopeProductComplex: u32,
-    SecurityMassStatus: u32,
-    SecurityMassTradingStatus: Option<u32>,
-    MassMarketCondition: u32,
-    FastMarketIndicator: u32,
-    SecurityMassTradingEvent: Option<u32>,
-    MassSoldOutIndicator: Option<u32>,
-    TransactTime: i64,
-    TESSecurityMassStatus: Option<u32>,
-    SecMassStatGrp: Vec<SecMassStatGrp>, // sequence of sub-structures
-    LastFragment: u32,
+pub struct MassInstrumentStateChange {
+    pub MsgType: String,
+    pub MsgSeqNum: u32,
+    pub SenderCompID: u32,
+    pub MarketSegmentID: u32,
+    pub InstrumentScopeProductComplex: u32,
+    pub SecurityMassStatus: u32,
+    pub SecurityMassTradingStatus: Option<u32>,
+    pub MassMarketCondition: u32,
+    pub FastMarketIndicator: u32,
+    pub SecurityMassTradingEvent: Option<u32>,
+    pub MassSoldOutIndicator: Option<u32>,
+    pub TransactTime: i64,
+    pub TESSecurityMassStatus: Option<u32>,
+    pub SecMassStatGrp: Vec<SecMassStatGrp>, // sequence of sub-structures
+    pub LastFragment: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct SecMassStatGrp {
-    SecurityID: i64,
-    SecurityIDSource: String,
-    SecurityStatus: u32,
-    SecurityTradingStatus: Option<u32>,
-    MarketCondition: u32,
-    SecurityTradingEvent: Option<u32>,
-    SoldOutIndicator: Option<u32>,
-    TESSecurityStatus: Option<u32>,
+pub struct SecMassStatGrp {
+    pub SecurityID: i64,
+    pub SecurityIDSource: String,
+    pub SecurityStatus: u32,
+    pub SecurityTradingStatus: Option<u32>,
+    pub MarketCondition: u32,
+    pub SecurityTradingEvent: Option<u32>,
+    pub SoldOutIndicator: Option<u32>,
+    pub TESSecurityStatus: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct ProductStateChange {
-    MsgType: String,
-    MsgSeqNum: u32,
-    SenderCompID: u32,
-    MarketSegmentID: u32,
-    TradingSessionID: u32,
-    TradingSessionSubID: u32,
-    TradSesStatus: u32,
-    MarketCondition: Option<u32>,
-    FastMarketIndicator: u32,
-    TransactTime: i64,
-    TESTradSesStatus: Option<u32>,
+pub struct ProductStateChange {
+    pub MsgType: String,
+    pub MsgSeqNum: u32,
+    pub SenderCompID: u32,
+    pub MarketSegmentID: u32,
+    pub TradingSessionID: u32,
+    pub TradingSessionSubID: u32,
+    pub TradSesStatus: u32,
+    pub MarketCondition: Option<u32>,
+    pub FastMarketIndicator: u32,
+    pub TransactTime: i64,
+    pub TESTradSesStatus: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct InstrumentStateChange {
-    MsgType: String,
-    MsgSeqNum: u32,
-    SenderCompID: u32,
-    MarketSegmentID: u32,
-    SecurityID: i64,
-    SecurityIDSource: String,
-    SecurityStatus: u32,
-    SecurityTradingStatus: Option<u32>,
-    MarketCondition: u32,
-    FastMarketIndicator: u32,
-    SecurityTradingEvent: Option<u32>,
-    SoldOutIndicator: Option<u32>,
-    TransactTime: i64,
-    TESSecurityStatus: Option<u32>,
+pub struct InstrumentStateChange {
+    pub MsgType: String,
+    pub MsgSeqNum: u32,
+    pub SenderCompID: u32,
+    pub MarketSegmentID: u32,
+    pub SecurityID: i64,
+    pub SecurityIDSource: String,
+    pub SecurityStatus: u32,
+    pub SecurityTradingStatus: Option<u32>,
+    pub MarketCondition: u32,
+    pub FastMarketIndicator: u32,
+    pub SecurityTradingEvent: Option<u32>,
+    pub SoldOutIndicator: Option<u32>,
+    pub TransactTime: i64,
+    pub TESSecurityStatus: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -253,143 +285,143 @@ pub struct TradeEntryGrp {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct ComplexInstrumentUpdate {
-    MsgType: String,
-    MsgSeqNum: u32,
-    SenderCompID: u32,
-    SecurityUpdateAction: String,
-    SecurityID: i64,
-    SecurityIDSource: String,
-    SecurityDesc: String,
-    SecurityType: u32,
-    SecuritySubType: Option<u32>,
-    ProductComplex: u32,
-    LegRatioMultiplier: Option<u32>,
-    InstrmtLegGrp: Vec<InstrmtLegGrp>,       // sequence of legs
-    MarketSegmentGrp: Vec<MarketSegmentGrp>, // sequence of market segments
-    TransactTime: i64,
+pub struct ComplexInstrumentUpdate {
+    pub MsgType: String,
+    pub MsgSeqNum: u32,
+    pub SenderCompID: u32,
+    pub SecurityUpdateAction: String,
+    pub SecurityID: i64,
+    pub SecurityIDSource: String,
+    pub SecurityDesc: String,
+    pub SecurityType: u32,
+    pub SecuritySubType: Option<u32>,
+    pub ProductComplex: u32,
+    pub LegRatioMultiplier: Option<u32>,
+    pub InstrmtLegGrp: Vec<InstrmtLegGrp>, // sequence of legs
+    pub MarketSegmentGrp: Vec<MarketSegmentGrp>, // sequence of market segments
+    pub TransactTime: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct InstrmtLegGrp {
-    LegSymbol: u32,
-    LegSecurityID: i64,
-    LegSecurityIDSource: String,
-    LegSecurityType: u32,
-    LegRatioQty: u32,
-    LegSide: u32,
-    LegPrice: Option<f64>,
+pub struct InstrmtLegGrp {
+    pub LegSymbol: u32,
+    pub LegSecurityID: i64,
+    pub LegSecurityIDSource: String,
+    pub LegSecurityType: u32,
+    pub LegRatioQty: u32,
+    pub LegSide: u32,
+    pub LegPrice: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct MarketSegmentGrp {
-    MarketSegmentID: u32,
-    ImpliedMarketIndicator: u32,
+pub struct MarketSegmentGrp {
+    pub MarketSegmentID: u32,
+    pub ImpliedMarketIndicator: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct QuoteRequest {
-    MsgType: String,
-    MsgSeqNum: u32,
-    SenderCompID: u32,
-    MarketSegmentID: u32,
-    QuotReqGrp: Vec<QuotReqGrp>, // sequence of quote request group
+pub struct QuoteRequest {
+    pub MsgType: String,
+    pub MsgSeqNum: u32,
+    pub SenderCompID: u32,
+    pub MarketSegmentID: u32,
+    pub QuotReqGrp: Vec<QuotReqGrp>, // sequence of quote request group
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct QuotReqGrp {
-    SecurityID: i64,
-    SecurityIDSource: String,
-    Side: Option<u32>,
-    OrderQty: Option<f64>,
-    TransactTime: i64,
+pub struct QuotReqGrp {
+    pub SecurityID: i64,
+    pub SecurityIDSource: String,
+    pub Side: Option<u32>,
+    pub OrderQty: Option<f64>,
+    pub TransactTime: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct CrossRequest {
-    MsgType: String,
-    MsgSeqNum: u32,
-    SenderCompID: u32,
-    MarketSegmentID: u32,
-    SecurityID: i64,
-    SecurityIDSource: String,
-    OrderQty: Option<f64>,
-    CrossRequestType: u32,
-    CrossRequestSideGrp: Option<Vec<CrossRequestSideGrp>>, // optional sequence of side group
-    MDEntryPx: Option<f64>,
-    TransactTime: i64,
+pub struct CrossRequest {
+    pub MsgType: String,
+    pub MsgSeqNum: u32,
+    pub SenderCompID: u32,
+    pub MarketSegmentID: u32,
+    pub SecurityID: i64,
+    pub SecurityIDSource: String,
+    pub OrderQty: Option<f64>,
+    pub CrossRequestType: u32,
+    pub CrossRequestSideGrp: Option<Vec<CrossRequestSideGrp>>, // optional sequence of side group
+    pub MDEntryPx: Option<f64>,
+    pub TransactTime: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct CrossRequestSideGrp {
-    Side: Option<u32>,
-    InputSource: u32,
+pub struct CrossRequestSideGrp {
+    pub Side: Option<u32>,
+    pub InputSource: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct IndexStats {
-    MsgType: String,
-    MsgSeqNum: Option<u32>,
-    SenderCompID: u32,
-    MarketSegmentID: Option<u32>,
-    IndexHigh: Option<f64>,
-    IndexLow: Option<f64>,
-    IndexOpen: Option<f64>,
-    IndexClose: Option<f64>,
-    IndexValue: Option<f64>,
-    IndexLifeHigh: Option<f64>,
-    IndexLifeLow: Option<f64>,
-    Index52WeekHigh: Option<f64>,
-    Index52WeekLow: Option<f64>,
-    CloseIndexFlag: Option<u32>,
-    TransactTime: i64,
+pub struct IndexStats {
+    pub MsgType: String,
+    pub MsgSeqNum: Option<u32>,
+    pub SenderCompID: u32,
+    pub MarketSegmentID: Option<u32>,
+    pub IndexHigh: Option<f64>,
+    pub IndexLow: Option<f64>,
+    pub IndexOpen: Option<f64>,
+    pub IndexClose: Option<f64>,
+    pub IndexValue: Option<f64>,
+    pub IndexLifeHigh: Option<f64>,
+    pub IndexLifeLow: Option<f64>,
+    pub Index52WeekHigh: Option<f64>,
+    pub Index52WeekLow: Option<f64>,
+    pub CloseIndexFlag: Option<u32>,
+    pub TransactTime: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct TopOfBookImplied {
-    MsgType: String,
-    MsgSeqNum: u32,
-    SenderCompID: u32,
-    MarketSegmentID: u32,
-    MDIncGrp: Vec<MDEntry>,
+pub struct TopOfBookImplied {
+    pub MsgType: String,
+    pub MsgSeqNum: u32,
+    pub SenderCompID: u32,
+    pub MarketSegmentID: u32,
+    pub MDIncGrp: Vec<MDEntry>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct MDEntry {
-    MDUpdateAction: u32,
-    MDEntryType: u32,
-    MDBookType: u32,
-    MDSubBookType: u32,
-    SecurityID: i64,
-    SecurityIDSource: String,
-    MDEntryPx: Option<f64>,
-    MDEntrySize: Option<f64>,
-    MDEntryTime: Option<i64>,
-    QuoteCondition: Option<u32>,
+pub struct MDEntry {
+    pub MDUpdateAction: u32,
+    pub MDEntryType: u32,
+    pub MDBookType: u32,
+    pub MDSubBookType: u32,
+    pub SecurityID: i64,
+    pub SecurityIDSource: String,
+    pub MDEntryPx: Option<f64>,
+    pub MDEntrySize: Option<f64>,
+    pub MDEntryTime: Option<i64>,
+    pub QuoteCondition: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct FlexibleInstrumentUpdate {
-    MsgType: String,
-    MsgSeqNum: u32,
-    SenderCompID: u32,
-    SecurityUpdateAction: String,
-    SecurityID: i64,
-    SecurityIDSource: String,
-    SecurityDesc: String,
-    SecurityType: u32,
-    ProductComplex: u32,
-    MaturityDate: u32,
-    StrikePrice: Option<f64>,
-    PutOrCall: Option<u32>,
-    OptAttribute: Option<u32>,
-    ExerciseStyle: Option<u32>,
-    SettlMethod: u32,
-    MarketSegmentGrp: Vec<MarketSegmentGrp2>,
-    TransactTime: i64,
+pub struct FlexibleInstrumentUpdate {
+    pub MsgType: String,
+    pub MsgSeqNum: u32,
+    pub SenderCompID: u32,
+    pub SecurityUpdateAction: String,
+    pub SecurityID: i64,
+    pub SecurityIDSource: String,
+    pub SecurityDesc: String,
+    pub SecurityType: u32,
+    pub ProductComplex: u32,
+    pub MaturityDate: u32,
+    pub StrikePrice: Option<f64>,
+    pub PutOrCall: Option<u32>,
+    pub OptAttribute: Option<u32>,
+    pub ExerciseStyle: Option<u32>,
+    pub SettlMethod: u32,
+    pub MarketSegmentGrp: Vec<MarketSegmentGrp2>,
+    pub TransactTime: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct MarketSegmentGrp2 {
-    MarketSegmentID: u32,
+pub struct MarketSegmentGrp2 {
+    pub MarketSegmentID: u32,
 }
@@ -0,0 +1,97 @@
+use serde::Serialize;
+use twiddler::Twiddle;
+
+use super::depth_output::TagMessageHeader;
+
+// Prices are multiplied by 100, same as depth output
+// Optional fields are 0 when not present
+// seq_no is MsgSeqNum of the message the record was published from
+
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagInstrumentState {
+    pub msg_header: TagMessageHeader,
+    pub seq_no: u32,
+    pub token: i64,
+    pub market_segment_id: u32,
+    pub security_status: u32,
+    pub trading_status: u32,
+    pub market_condition: u32,
+    pub fast_market_indicator: u32,
+    pub trading_event: u32,
+    pub sold_out_indicator: u32,
+    pub transact_time: i64,
+}
+
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagProductState {
+    pub msg_header: TagMessageHeader,
+    pub seq_no: u32,
+    pub market_segment_id: u32,
+    pub trading_session_id: u32,
+    pub trading_session_sub_id: u32,
+    pub trading_session_status: u32,
+    pub market_condition: u32,
+    pub fast_market_indicator: u32,
+    pub transact_time: i64,
+}
+
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagIndexStats {
+    pub msg_header: TagMessageHeader,
+    pub seq_no: u32,
+    pub market_segment_id: u32,
+    pub value: i32,
+    pub open: i32,
+    pub high: i32,
+    pub low: i32,
+    pub close: i32,
+    pub life_high: i32,
+    pub life_low: i32,
+    pub week_52_high: i32,
+    pub week_52_low: i32,
+    pub close_index_flag: u32,
+    pub transact_time: i64,
+}
+
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagImpliedTopOfBook {
+    pub msg_header: TagMessageHeader,
+    pub seq_no: u32,
+    pub token: i64,
+    pub market_segment_id: u32,
+    pub update_action: u32,
+    // 0 for bid, 1 for offer
+    pub entry_type: u32,
+    pub price: i32,
+    pub qty: i64,
+    pub entry_time: i64,
+}
+
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagQuoteRequest {
+    pub msg_header: TagMessageHeader,
+    pub seq_no: u32,
+    pub token: i64,
+    pub market_segment_id: u32,
+    pub side: u32,
+    pub qty: i64,
+    pub transact_time: i64,
+}
+
+#[derive(Debug, Twiddle, Clone, Copy, Serialize)]
+#[repr(C, packed(2))]
+pub struct TagCrossRequest {
+    pub msg_header: TagMessageHeader,
+    pub seq_no: u32,
+    pub token: i64,
+    pub market_segment_id: u32,
+    pub cross_request_type: u32,
+    pub price: i32,
+    pub qty: i64,
+    pub transact_time: i64,
+}
@@ -1,7 +1,5 @@
 use twiddler::Twiddle;
 
-use crate::constants::BUF_SIZE;
-
 pub mod bse;
 pub mod mcx;
 pub mod ncd; // NSE Commodities
@@ -9,6 +7,14 @@ pub mod neq; // NSE Equity
 pub mod nfo; // NSE FAO
              // Custom structure for depth format
 pub mod depth_output;
+// Custom structures for mcx non depth messages
+pub mod mcx_output;
+// Custom structure for sequence gaps
+pub mod gap_event;
+// Custom structure for market picture with instrument details
+pub mod enriched_output;
+// Custom structure for security master changes
+pub mod master_change;
 
 #[derive(Debug, Twiddle, Clone, Copy)]
 #[repr(C, packed(2))]
@@ -18,10 +24,3 @@ pub struct PackData {
     pub no_of_packets: u16,
     pub pack_data: [u8; 512],
 }
-
-#[derive(Debug, Clone, Copy, Twiddle)]
-#[repr(C, packed(2))]
-pub struct CompressionData {
-    pub compression_len: u16,
-    pub broadcast_data: [u8; BUF_SIZE],
-}
@@ -2,7 +2,7 @@ use std::mem::{offset_of, size_of};
 
 use crate::{
     constants::*,
-    utils::byte_utils::{bytes_to_struct, struct_to_bytes},
+    utils::byte_utils::{bytes_to_struct, struct_to_bytes, try_slice, ParseError},
 };
 use twiddler::Twiddle;
 
@@ -43,118 +43,124 @@ pub enum NeqBroadcastTransactionMapping {
     BcastSecurityMstrChg(BcastSecurityMasterUpdateInfo),
 }
 
-pub fn build_neq_struct(transaction_id: i16, buf: &[u8]) -> Option<NeqBroadcastTransactionMapping> {
-    match transaction_id {
+pub fn build_neq_struct(
+    transaction_id: i16,
+    buf: &[u8],
+) -> Result<Option<NeqBroadcastTransactionMapping>, ParseError> {
+    let mapping = match transaction_id {
         5294 => Some(NeqBroadcastTransactionMapping::BcastContMsg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6501 => Some(NeqBroadcastTransactionMapping::BcastJrnlVctMsg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6511 => Some(NeqBroadcastTransactionMapping::BcastOpenMessage(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6521 => Some(NeqBroadcastTransactionMapping::BcastCloseMessage(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6531 => Some(NeqBroadcastTransactionMapping::BcastPreopenShutdownMsg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6541 => Some(NeqBroadcastTransactionMapping::BcastCircuitCheck(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6571 => Some(NeqBroadcastTransactionMapping::BcastNormalMktPreopenEnded(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6581 => Some(NeqBroadcastTransactionMapping::BcastAuctionStatusChange(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7200 => Some(NeqBroadcastTransactionMapping::BcastMboMbpCedtc(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7201 => Some(NeqBroadcastTransactionMapping::BcastMwRoundRobinCedtc(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7206 => Some(NeqBroadcastTransactionMapping::BcastSystemInformationOut(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7207 => Some(NeqBroadcastTransactionMapping::BcastIndices(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7208 => Some(NeqBroadcastTransactionMapping::BcastOnlyMbpCedtc(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
-        7210 => {
-            Some(NeqBroadcastTransactionMapping::BcastCallAuctionOrdCxlUpdate(bytes_to_struct(buf)))
-        }
+        7210 => Some(
+            NeqBroadcastTransactionMapping::BcastCallAuctionOrdCxlUpdate(bytes_to_struct(buf)?),
+        ),
         7214 => Some(NeqBroadcastTransactionMapping::BcastCallAuctionMbpCedtc(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7215 => Some(NeqBroadcastTransactionMapping::BcastCaMwCedtc(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7216 => Some(NeqBroadcastTransactionMapping::BcastIndicesVix(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7306 => Some(NeqBroadcastTransactionMapping::BcastPartMstrChg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7764 => Some(
-            NeqBroadcastTransactionMapping::BcastSymbolStatusChangeAction(bytes_to_struct(buf)),
+            NeqBroadcastTransactionMapping::BcastSymbolStatusChangeAction(bytes_to_struct(buf)?),
         ),
         8207 => Some(NeqBroadcastTransactionMapping::BcastIndicativeIndices(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         9010 => Some(NeqBroadcastTransactionMapping::BcastTurnoverExceeded(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         9011 => Some(NeqBroadcastTransactionMapping::BcastBrokerReactivated(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         18130 => Some(NeqBroadcastTransactionMapping::BcastSecurityStatusChg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         18201 => Some({
-            if buf[40] == b'H' {
+            if try_slice(buf, 40, 1)?[0] == b'H' {
                 NeqBroadcastTransactionMapping::BcastMarketStatsReportDataCedtcH(bytes_to_struct(
                     buf,
-                ))
+                )?)
             } else {
                 NeqBroadcastTransactionMapping::BcastMarketStatsReportDataCedtcR(bytes_to_struct(
                     buf,
-                ))
+                )?)
             }
         }),
         18700 => Some(NeqBroadcastTransactionMapping::BcastAuctionInquiryOut(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         18703 => Some(NeqBroadcastTransactionMapping::BcastTickerAndMktIndex(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         18702 => Some(NeqBroadcastTransactionMapping::BcastMwRoundRobin(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         18705 => Some(NeqBroadcastTransactionMapping::BcastOnlyMbp(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         18707 => Some({
-            NeqBroadcastTransactionMapping::BcastSecurityStatusChgPreopen(bytes_to_struct(buf))
+            NeqBroadcastTransactionMapping::BcastSecurityStatusChgPreopen(bytes_to_struct(buf)?)
         }),
         18708 => Some(NeqBroadcastTransactionMapping::BcastBuyBack(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         18710 => Some(NeqBroadcastTransactionMapping::BcastCallAuctionMbp(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         18720 => Some(NeqBroadcastTransactionMapping::BcastSecurityMstrChg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         _ => None,
-    }
+    };
+
+    Ok(mapping)
 }
 
 impl NeqBroadcastTransactionMapping {
-    pub fn to_bytes(&self, buffer: &mut [u8]) {
+    // Returns no of bytes written
+    pub fn to_bytes(&self, buffer: &mut [u8]) -> usize {
         match self {
             NeqBroadcastTransactionMapping::BcastContMsg(s) => struct_to_bytes(s, buffer),
             NeqBroadcastTransactionMapping::BcastJrnlVctMsg(s) => struct_to_bytes(s, buffer),
@@ -209,7 +215,7 @@ impl NeqBroadcastTransactionMapping {
             NeqBroadcastTransactionMapping::BcastBuyBack(s) => struct_to_bytes(s, buffer),
             NeqBroadcastTransactionMapping::BcastCallAuctionMbp(s) => struct_to_bytes(s, buffer),
             NeqBroadcastTransactionMapping::BcastSecurityMstrChg(s) => struct_to_bytes(s, buffer),
-        };
+        }
     }
 }
 
@@ -272,8 +278,8 @@ pub struct BcastJournalMessage {
 #[derive(Debug, Twiddle, Clone, Copy)]
 #[repr(C, packed(2))]
 pub struct BcastSECInfo {
-    symbol: [u8; SYMBOL_LEN],
-    series: [u8; SERIES_LEN],
+    pub symbol: [u8; SYMBOL_LEN],
+    pub series: [u8; SERIES_LEN],
 }
 
 #[repr(C, packed(2))]
@@ -328,56 +334,56 @@ pub struct BcastPurpose {
 #[derive(Debug, Twiddle, Clone, Copy)]
 #[repr(C, packed(2))]
 pub struct BcastSecurityMasterUpdateInfo {
-    bcast_header: BcastHeaders,
-    token: i32,
-    ec_info: BcastSECInfo,
-    instrument_type: i16,
-    permitted_to_trade: i16,
-    lf_issued_capital: f64,
-    ettlement_type: i16,
-    freeze_percent: i16,
-    credit_rating: [u8; CREDITRATING_LEN_19],
-    reserved1: u8,
-    eligibility_per_market: [BcastSecurityEligibilityPerMarket; 6],
-    urv_ind: i16,
-    issue_start_date: i32,
-    interest_payment_date: i32,
-    issue_maturity_date: i32,
-    board_lot_quantity: i32,
-    tick_size: i32,
-    name: [u8; REMARKS_LEN],
-    reserved2: u8,
-    listing_date: i32,
-    expulsion_date: i32,
-    re_admission_date: i32,
-    record_date: i32,
-    expiry_date: i32,
-    no_delivery_start_date: i32,
-    no_delivery_end_date: i32,
-    eligibility_indicators: BcastEligibilityIndicators,
-    book_closure_start_date: i32,
-    book_closure_end_date: i32,
-    purpose: BcastPurpose,
-    local_update_date_time: i32,
-    delete_flag: u8,
-    remark: [u8; REMARKS_LEN],
-    face_value: i32,
-    isin_number: [u8; ISINNUMBER_LEN],
-    mkt_maker_spread: i32,
-    mkt_maker_min_qty: i32,
-    call_auction1_flag: i16,
+    pub bcast_header: BcastHeaders,
+    pub token: i32,
+    pub ec_info: BcastSECInfo,
+    pub instrument_type: i16,
+    pub permitted_to_trade: i16,
+    pub lf_issued_capital: f64,
+    pub ettlement_type: i16,
+    pub freeze_percent: i16,
+    pub credit_rating: [u8; CREDITRATING_LEN_19],
+    pub reserved1: u8,
+    pub eligibility_per_market: [BcastSecurityEligibilityPerMarket; 6],
+    pub urv_ind: i16,
+    pub issue_start_date: i32,
+    pub interest_payment_date: i32,
+    pub issue_maturity_date: i32,
+    pub board_lot_quantity: i32,
+    pub tick_size: i32,
+    pub name: [u8; REMARKS_LEN],
+    pub reserved2: u8,
+    pub listing_date: i32,
+    pub expulsion_date: i32,
+    pub re_admission_date: i32,
+    pub record_date: i32,
+    pub expiry_date: i32,
+    pub no_delivery_start_date: i32,
+    pub no_delivery_end_date: i32,
+    pub eligibility_indicators: BcastEligibilityIndicators,
+    pub book_closure_start_date: i32,
+    pub book_closure_end_date: i32,
+    pub purpose: BcastPurpose,
+    pub local_update_date_time: i32,
+    pub delete_flag: u8,
+    pub remark: [u8; REMARKS_LEN],
+    pub face_value: i32,
+    pub isin_number: [u8; ISINNUMBER_LEN],
+    pub mkt_maker_spread: i32,
+    pub mkt_maker_min_qty: i32,
+    pub call_auction1_flag: i16,
 }
 
 #[derive(Debug, Twiddle, Clone, Copy)]
 #[repr(C, packed(2))]
 pub struct BcastParticipantMasterUpdateInfo {
-    bcast_header: BcastHeaders,
-    participant_id: [u8; PARTICIPANT_ID_LEN],
-    participant_name: [u8; PARTICIPANT_NAME_LEN],
-    participant_status: u8,
-    participant_update_date_time: i32,
-    delete_flag: u8,
-    reserved: u8,
+    pub bcast_header: BcastHeaders,
+    pub participant_id: [u8; PARTICIPANT_ID_LEN],
+    pub participant_name: [u8; PARTICIPANT_NAME_LEN],
+    pub participant_status: u8,
+    pub participant_update_date_time: i32,
+    pub delete_flag: u8,
+    pub reserved: u8,
 }
 
 #[derive(Debug, Twiddle, Clone, Copy)]
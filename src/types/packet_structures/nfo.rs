@@ -1,6 +1,6 @@
 use crate::{
     constants::*,
-    utils::byte_utils::{bytes_to_struct, struct_to_bytes},
+    utils::byte_utils::{bytes_to_struct, struct_to_bytes, ParseError},
 };
 use twiddler::Twiddle;
 
@@ -41,95 +41,101 @@ pub enum NfoBroadcastTransactionMapping {
     BcastLimitPriceProtectionRange(BcastLPPRange),
 }
 
-pub fn build_nfo_struct(transaction_id: i16, buf: &[u8]) -> Option<NfoBroadcastTransactionMapping> {
-    match transaction_id {
+pub fn build_nfo_struct(
+    transaction_id: i16,
+    buf: &[u8],
+) -> Result<Option<NfoBroadcastTransactionMapping>, ParseError> {
+    let mapping = match transaction_id {
         5294 => Some(NfoBroadcastTransactionMapping::BcastContMsg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6013 => Some(NfoBroadcastTransactionMapping::BcastSecurityOpenPrice(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6501 => Some(NfoBroadcastTransactionMapping::BcastJrnlVctMsg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6511 => Some(NfoBroadcastTransactionMapping::BcastOpenMessage(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6521 => Some(NfoBroadcastTransactionMapping::BcastCloseMessage(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6531 => Some(NfoBroadcastTransactionMapping::BcastPreopenShutdownMsg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6541 => Some(NfoBroadcastTransactionMapping::BcastCircuitCheck(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         6571 => Some(NfoBroadcastTransactionMapping::BcastNormalMktPreopenEnded(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7130 => Some(NfoBroadcastTransactionMapping::BcastMktMvmtCmOiIn(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7200 => Some(NfoBroadcastTransactionMapping::BcastMboMbpUpdate(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7201 => Some(NfoBroadcastTransactionMapping::BcastMwRoundRobin(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7202 => Some(NfoBroadcastTransactionMapping::BcastTickerAndMktIndex(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7203 => Some(NfoBroadcastTransactionMapping::BcastIndustryIndexUpdate(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7206 => Some(NfoBroadcastTransactionMapping::BcastSystemInformationOut(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7208 => Some(NfoBroadcastTransactionMapping::BcastOnlyMbp(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7210 => Some(
-            NfoBroadcastTransactionMapping::BcastSecurityStatusChgPreopen(bytes_to_struct(buf)),
+            NfoBroadcastTransactionMapping::BcastSecurityStatusChgPreopen(bytes_to_struct(buf)?),
         ),
         7211 => Some(NfoBroadcastTransactionMapping::BcastSpdMbpDelta(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7220 => Some({
-            NfoBroadcastTransactionMapping::BcastLimitPriceProtectionRange(bytes_to_struct(buf))
+            NfoBroadcastTransactionMapping::BcastLimitPriceProtectionRange(bytes_to_struct(buf)?)
         }),
         7305 => Some(NfoBroadcastTransactionMapping::BcastSecurityMstrChg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7306 => Some(NfoBroadcastTransactionMapping::BcastPartMstrChg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7320 => Some(NfoBroadcastTransactionMapping::BcastSecurityStatusChg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7324 => Some(NfoBroadcastTransactionMapping::BcastInstrMstrChg(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7340 => Some(NfoBroadcastTransactionMapping::BcastSecMstrChngPeriodic(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         7341 => Some(NfoBroadcastTransactionMapping::BcastSpdMstrChgPeriodic(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         9010 => Some(NfoBroadcastTransactionMapping::BcastTurnoverExceeded(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         9011 => Some(NfoBroadcastTransactionMapping::BcastBrokerReactivated(
-            bytes_to_struct(buf),
+            bytes_to_struct(buf)?,
         )),
         _ => {
             println!("Invalid fao transaction id: {}", transaction_id);
             None
         }
-    }
+    };
+
+    Ok(mapping)
 }
 
 impl NfoBroadcastTransactionMapping {
-    pub fn to_bytes(&self, buffer: &mut [u8]) {
+    // Returns no of bytes written
+    pub fn to_bytes(&self, buffer: &mut [u8]) -> usize {
         match self {
             NfoBroadcastTransactionMapping::BcastContMsg(s) => struct_to_bytes(s, buffer),
             NfoBroadcastTransactionMapping::BcastSecurityOpenPrice(s) => struct_to_bytes(s, buffer),
@@ -184,7 +190,7 @@ impl NfoBroadcastTransactionMapping {
             NfoBroadcastTransactionMapping::BcastLimitPriceProtectionRange(s) => {
                 struct_to_bytes(s, buffer)
             }
-        };
+        }
     }
 }
 
@@ -438,13 +444,13 @@ pub struct BcastJournalMessage {
 #[repr(C, packed(2))]
 #[derive(Debug, Twiddle, Clone, Copy)]
 pub struct BcastSECInfo {
-    instrument_name: [u8; 6],
-    symbol: [u8; SYMBOL_LEN],
-    series: [u8; SERIES_LEN],
-    expiry_date: i32,
-    trike_price: i32,
-    option_type: [u8; 2],
-    ca_level: u8,
+    pub instrument_name: [u8; 6],
+    pub symbol: [u8; SYMBOL_LEN],
+    pub series: [u8; SERIES_LEN],
+    pub expiry_date: i32,
+    pub trike_price: i32,
+    pub option_type: [u8; 2],
+    pub ca_level: u8,
 }
 
 #[repr(C, packed(2))]
@@ -488,72 +494,72 @@ pub struct BcastPurpose {
 #[repr(C, packed(2))]
 #[derive(Debug, Twiddle, Clone, Copy)]
 pub struct BcastSecurityUpdateInfo {
-    bcast_header: BcastHeaders,
-    token: i32,
-    ec_info: BcastSECInfo,
-    permitted_to_trade: i16,
-    lf_issued_capital: f64,
-    warning_qty: i32,
-    freeze_qty: i32,
-    credit_rating: [u8; CREDITRATING_LEN_12],
-    eligibility_per_market: [BcastSecurityEligibilityPerMarket; 4],
-    issue_rate: i16,
-    issue_start_date: i32,
-    interest_payment_date: i32,
-    issue_maturity_date: i32,
-    margin_percene: i32,
-    minimum_lot_quantity: i32,
-    board_lot_quantity: i32,
-    tick_size: i32,
-    name: [u8; REMARKS_LEN],
-    reserved2: u8,
-    listing_date: i32,
-    expulsion_date: i32,
-    re_admission_date: i32,
-    record_date: i32,
-    low_price_range: i32,
-    high_price_range: i32,
-    expiry_date: i32,
-    no_delivery_start_date: i32,
-    no_delivery_end_date: i32,
-    eligibility_indicators: BcastEligibilityIndicators,
-    book_closure_start_date: i32,
-    book_closure_end_date: i32,
-    exercise_start_date: i32,
-    exercise_end_date: i32,
-    old_token: i32,
-    asset_instrument: [u8; ASSET_INSTRUMENT_LEN],
-    asset_name: [u8; ASSET_NAME_LEN],
-    asset_token: i32,
-    intrinsic_value: i32,
-    extrinsic_value: i32,
-    purpose: BcastPurpose,
-    local_update_date_time: i32,
-    delete_flag: u8,
-    remark: [u8; REMARKS_LEN],
-    base_price: i32,
+    pub bcast_header: BcastHeaders,
+    pub token: i32,
+    pub ec_info: BcastSECInfo,
+    pub permitted_to_trade: i16,
+    pub lf_issued_capital: f64,
+    pub warning_qty: i32,
+    pub freeze_qty: i32,
+    pub credit_rating: [u8; CREDITRATING_LEN_12],
+    pub eligibility_per_market: [BcastSecurityEligibilityPerMarket; 4],
+    pub issue_rate: i16,
+    pub issue_start_date: i32,
+    pub interest_payment_date: i32,
+    pub issue_maturity_date: i32,
+    pub margin_percene: i32,
+    pub minimum_lot_quantity: i32,
+    pub board_lot_quantity: i32,
+    pub tick_size: i32,
+    pub name: [u8; REMARKS_LEN],
+    pub reserved2: u8,
+    pub listing_date: i32,
+    pub expulsion_date: i32,
+    pub re_admission_date: i32,
+    pub record_date: i32,
+    pub low_price_range: i32,
+    pub high_price_range: i32,
+    pub expiry_date: i32,
+    pub no_delivery_start_date: i32,
+    pub no_delivery_end_date: i32,
+    pub eligibility_indicators: BcastEligibilityIndicators,
+    pub book_closure_start_date: i32,
+    pub book_closure_end_date: i32,
+    pub exercise_start_date: i32,
+    pub exercise_end_date: i32,
+    pub old_token: i32,
+    pub asset_instrument: [u8; ASSET_INSTRUMENT_LEN],
+    pub asset_name: [u8; ASSET_NAME_LEN],
+    pub asset_token: i32,
+    pub intrinsic_value: i32,
+    pub extrinsic_value: i32,
+    pub purpose: BcastPurpose,
+    pub local_update_date_time: i32,
+    pub delete_flag: u8,
+    pub remark: [u8; REMARKS_LEN],
+    pub base_price: i32,
 }
 
 #[repr(C, packed(2))]
 #[derive(Debug, Twiddle, Clone, Copy)]
 pub struct BcastInstrumentUpdateInfo {
-    bcast_header: BcastHeaders,
-    instrument_id: i16,
-    instrument_name: [u8; INSTRUMENT_NAME_LEN],
-    instrument_description: [u8; INSTRUMENT_DESC_LEN],
-    instrument_update_time: i32,
-    delete_flag: u8,
+    pub bcast_header: BcastHeaders,
+    pub instrument_id: i16,
+    pub instrument_name: [u8; INSTRUMENT_NAME_LEN],
+    pub instrument_description: [u8; INSTRUMENT_DESC_LEN],
+    pub instrument_update_time: i32,
+    pub delete_flag: u8,
 }
 
 #[repr(C, packed(2))]
 #[derive(Debug, Twiddle, Clone, Copy)]
 pub struct BcastParticipantUpdateInfo {
-    bcast_header: BcastHeaders,
-    participant_id: [u8; PARTICIPANT_ID_LEN],
-    participant_name: [u8; PARTICIPANT_NAME_LEN],
-    participant_status: u8,
-    participant_update_date_time: i32,
-    delete_flag: u8,
+    pub bcast_header: BcastHeaders,
+    pub participant_id: [u8; PARTICIPANT_ID_LEN],
+    pub participant_name: [u8; PARTICIPANT_NAME_LEN],
+    pub participant_status: u8,
+    pub participant_update_date_time: i32,
+    pub delete_flag: u8,
 }
 
 #[repr(C, packed(2))]
@@ -893,20 +899,20 @@ pub struct BcastSpreadEligibility {
 #[repr(C, packed(2))]
 #[derive(Debug, Twiddle, Clone, Copy)]
 pub struct BcastSpreadUpdateInfo {
-    bcast_header: BcastHeaders,
-    token1: i32,
-    token2: i32,
-    ec_info1: BcastSECInfo,
-    ec_info2: BcastSECInfo,
-    reference_price: i32,
-    day_low_price_diff_range: i32,
-    day_high_price_diff_range: i32,
-    op_low_price_diff_range: i32,
-    op_high_price_diff_range: i32,
-    eligibility: BcastSpreadEligibility,
-    reserved1: u8,
-    delete_flag: u8,
-    reserved2: u8,
+    pub bcast_header: BcastHeaders,
+    pub token1: i32,
+    pub token2: i32,
+    pub ec_info1: BcastSECInfo,
+    pub ec_info2: BcastSECInfo,
+    pub reference_price: i32,
+    pub day_low_price_diff_range: i32,
+    pub day_high_price_diff_range: i32,
+    pub op_low_price_diff_range: i32,
+    pub op_high_price_diff_range: i32,
+    pub eligibility: BcastSpreadEligibility,
+    pub reserved1: u8,
+    pub delete_flag: u8,
+    pub reserved2: u8,
 }
 
 #[repr(C, packed(2))]
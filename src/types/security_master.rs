@@ -0,0 +1,607 @@
+use std::{
+    collections::HashMap,
+    fs,
+    hash::Hash,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    constants::NSE_EPOCH_START,
+    settings,
+    utils::{
+        price_utils::{instrument_price_multiplier, to_units},
+        time_utils::epoch_days_to_date,
+    },
+};
+
+use super::settings::Exchange;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instrument {
+    pub token: i64,
+    pub symbol: String,
+    pub series: String,
+    // As yyyymmdd, 0 if instrument doesn't expire
+    pub expiry: i32,
+    // In exchange price units, 0 for non options
+    pub strike_price: i64,
+    // CE or PE, empty for non options
+    pub option_type: String,
+    pub lot_size: i32,
+    // In exchange price units
+    pub tick_size: i32,
+    // Exchange price units per rupee
+    pub price_multiplier: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Participant {
+    pub id: String,
+    pub name: String,
+    pub status: u8,
+}
+
+// Instrument type, eg FUTIDX or OPTSTK
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentType {
+    pub id: i16,
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spread {
+    pub token1: i64,
+    pub token2: i64,
+    pub reference_price: i32,
+}
+
+// Master an entry belongs to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MasterKind {
+    Security = 1,
+    Participant = 2,
+    InstrumentType = 3,
+    Spread = 4,
+}
+
+// How an entry was changed by a master update
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MasterAction {
+    Added = 1,
+    Modified = 2,
+    Deleted = 3,
+}
+
+// Column of each field in master file, None if file doesn't have it
+struct Columns {
+    token: Option<usize>,
+    symbol: Option<usize>,
+    series: Option<usize>,
+    expiry: Option<usize>,
+    strike_price: Option<usize>,
+    option_type: Option<usize>,
+    lot_size: Option<usize>,
+    tick_size: Option<usize>,
+    // Instrument name, eg FUTIDX, for price_decimals setting
+    instrument_name: Option<usize>,
+    // Decimal places of contract prices
+    decimal_locator: Option<usize>,
+}
+
+// NSE files have no column names, columns are as per NNF security.txt layout
+const NSE_CM_COLUMNS: Columns = Columns {
+    token: Some(0),
+    symbol: Some(1),
+    series: Some(2),
+    expiry: None,
+    strike_price: None,
+    option_type: None,
+    lot_size: Some(15),
+    tick_size: Some(16),
+    instrument_name: None,
+    decimal_locator: None,
+};
+
+// As per NNF contract.txt layout, same for fo and cd contract files
+const NSE_FO_COLUMNS: Columns = Columns {
+    token: Some(0),
+    symbol: Some(3),
+    series: Some(4),
+    expiry: Some(5),
+    strike_price: Some(6),
+    option_type: Some(7),
+    lot_size: Some(29),
+    tick_size: Some(30),
+    instrument_name: Some(2),
+    decimal_locator: None,
+};
+
+// BSE scrip master and MCX instrument list have column names in first line
+// Names are matched in lowercase, without spaces and underscores
+const TOKEN_NAMES: [&str; 5] = [
+    "token",
+    "scripcode",
+    "securityid",
+    "instrumentid",
+    "securitycode",
+];
+const SYMBOL_NAMES: [&str; 4] = ["symbol", "scripid", "tradingsymbol", "securitysymbol"];
+const SERIES_NAMES: [&str; 3] = ["series", "group", "scripgroup"];
+const EXPIRY_NAMES: [&str; 2] = ["expiry", "expirydate"];
+const STRIKE_PRICE_NAMES: [&str; 2] = ["strike", "strikeprice"];
+const OPTION_TYPE_NAMES: [&str; 2] = ["optiontype", "opttype"];
+const LOT_SIZE_NAMES: [&str; 4] = ["lotsize", "marketlot", "boardlot", "boardlotquantity"];
+const TICK_SIZE_NAMES: [&str; 1] = ["ticksize"];
+const INSTRUMENT_NAME_NAMES: [&str; 3] = ["instrumentname", "instrument", "instrumenttype"];
+const DECIMAL_LOCATOR_NAMES: [&str; 5] = [
+    "decimallocator",
+    "decimalplaces",
+    "decimals",
+    "pricedecimals",
+    "precision",
+];
+
+// Token to instrument table, loaded from exchange contract master
+// Kept current through the day by master change broadcasts
+pub struct SecurityMaster {
+    registry: RwLock<Registry>,
+}
+
+#[derive(Default)]
+struct Registry {
+    instruments: HashMap<i64, Arc<Instrument>>,
+    // Tokens of each symbol, symbols are in uppercase
+    symbols: HashMap<String, Vec<i64>>,
+    participants: HashMap<String, Participant>,
+    instrument_types: HashMap<i16, InstrumentType>,
+    spreads: HashMap<(i64, i64), Spread>,
+}
+
+impl SecurityMaster {
+    pub fn new() -> Self {
+        let settings = settings::get();
+
+        let mut registry = Registry::default();
+
+        if let Some(path) = &settings.security_master_file {
+            let content = fs::read_to_string(path).expect("Cannot find security master file");
+
+            let skipped = registry.load(&content, settings.exchange, &settings.price_decimals);
+
+            println!(
+                "Loaded {} instruments from security master, skipped {} rows",
+                registry.instruments.len(),
+                skipped
+            );
+        }
+
+        Self {
+            registry: RwLock::new(registry),
+        }
+    }
+
+    pub fn get(&self, token: i64) -> Option<Arc<Instrument>> {
+        self.registry
+            .read()
+            .unwrap()
+            .instruments
+            .get(&token)
+            .cloned()
+    }
+
+    // Largest token, for sizing token indexed tables
+    pub fn max_token(&self) -> Option<i64> {
+        self.registry
+            .read()
+            .unwrap()
+            .instruments
+            .keys()
+            .max()
+            .copied()
+    }
+
+    // All contracts of a symbol, eg all futures and options of NIFTY
+    pub fn tokens_for_symbol(&self, symbol: &str) -> Vec<i64> {
+        self.registry
+            .read()
+            .unwrap()
+            .symbols
+            .get(&symbol.to_uppercase())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Apply functions return None if entry is unchanged, periodic broadcasts mostly are
+
+    pub fn apply_instrument(&self, instrument: Instrument, delete: bool) -> Option<MasterAction> {
+        let mut registry = self.registry.write().unwrap();
+
+        let old = registry.instruments.get(&instrument.token).cloned();
+
+        let action = match (&old, delete) {
+            (None, true) => return None,
+            (Some(_), true) => MasterAction::Deleted,
+            (Some(old), false) if **old == instrument => return None,
+            (Some(_), false) => MasterAction::Modified,
+            (None, false) => MasterAction::Added,
+        };
+
+        if let Some(old) = old {
+            registry.remove_symbol(&old);
+        }
+
+        if delete {
+            registry.instruments.remove(&instrument.token);
+        } else {
+            registry.insert(instrument);
+        }
+
+        Some(action)
+    }
+
+    pub fn apply_participant(
+        &self,
+        participant: Participant,
+        delete: bool,
+    ) -> Option<MasterAction> {
+        let mut registry = self.registry.write().unwrap();
+
+        upsert(
+            &mut registry.participants,
+            participant.id.clone(),
+            participant,
+            delete,
+        )
+    }
+
+    pub fn apply_instrument_type(
+        &self,
+        instrument_type: InstrumentType,
+        delete: bool,
+    ) -> Option<MasterAction> {
+        let mut registry = self.registry.write().unwrap();
+
+        upsert(
+            &mut registry.instrument_types,
+            instrument_type.id,
+            instrument_type,
+            delete,
+        )
+    }
+
+    pub fn apply_spread(&self, spread: Spread, delete: bool) -> Option<MasterAction> {
+        let mut registry = self.registry.write().unwrap();
+
+        upsert(
+            &mut registry.spreads,
+            (spread.token1, spread.token2),
+            spread,
+            delete,
+        )
+    }
+
+    pub fn touch(&self) {}
+}
+
+impl Registry {
+    // Returns no of rows skipped
+    fn load(
+        &mut self,
+        content: &str,
+        exchange: Exchange,
+        price_decimals: &HashMap<String, u32>,
+    ) -> usize {
+        let mut lines = content.lines();
+
+        // Parse prices in rupees for files with column names
+        let (columns, delimiter, rupee_prices) = match exchange {
+            Exchange::NEQ | Exchange::NFO | Exchange::NCD => {
+                // First line is file header, not a record
+                lines.next();
+
+                let columns = if exchange == Exchange::NEQ {
+                    NSE_CM_COLUMNS
+                } else {
+                    NSE_FO_COLUMNS
+                };
+
+                (columns, '|', false)
+            }
+            Exchange::BSE | Exchange::MCX => {
+                let header = lines.next().unwrap_or("");
+                let delimiter = if header.contains('|') { '|' } else { ',' };
+
+                (find_columns(header, delimiter), delimiter, true)
+            }
+        };
+
+        if columns.token.is_none() || columns.symbol.is_none() {
+            panic!("Security master file must have token and symbol columns");
+        }
+
+        let mut skipped = 0;
+
+        for line in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line
+                .split(delimiter)
+                .map(|field| field.trim().trim_matches('"'))
+                .collect();
+
+            let price_multiplier = instrument_price_multiplier(
+                exchange,
+                field(&fields, columns.instrument_name),
+                field(&fields, columns.decimal_locator).parse().ok(),
+                price_decimals,
+            );
+
+            match parse_instrument(&fields, &columns, price_multiplier, rupee_prices) {
+                Some(instrument) => self.insert(instrument),
+                None => skipped += 1,
+            }
+        }
+
+        skipped
+    }
+
+    fn insert(&mut self, instrument: Instrument) {
+        self.symbols
+            .entry(instrument.symbol.to_uppercase())
+            .or_default()
+            .push(instrument.token);
+
+        self.instruments
+            .insert(instrument.token, Arc::new(instrument));
+    }
+
+    fn remove_symbol(&mut self, instrument: &Instrument) {
+        let symbol = instrument.symbol.to_uppercase();
+
+        if let Some(tokens) = self.symbols.get_mut(&symbol) {
+            tokens.retain(|token| *token != instrument.token);
+
+            if tokens.is_empty() {
+                self.symbols.remove(&symbol);
+            }
+        }
+    }
+}
+
+fn upsert<K: Hash + Eq, V: PartialEq>(
+    map: &mut HashMap<K, V>,
+    key: K,
+    value: V,
+    delete: bool,
+) -> Option<MasterAction> {
+    if delete {
+        return map.remove(&key).map(|_| MasterAction::Deleted);
+    }
+
+    let action = match map.get(&key) {
+        Some(old) if *old == value => return None,
+        Some(_) => MasterAction::Modified,
+        None => MasterAction::Added,
+    };
+
+    map.insert(key, value);
+
+    Some(action)
+}
+
+fn find_columns(header: &str, delimiter: char) -> Columns {
+    let names: Vec<String> = header
+        .split(delimiter)
+        .map(|name| {
+            name.trim()
+                .trim_matches('"')
+                .to_lowercase()
+                .replace([' ', '_'], "")
+        })
+        .collect();
+
+    let find = |aliases: &[&str]| {
+        names
+            .iter()
+            .position(|name| aliases.contains(&name.as_str()))
+    };
+
+    Columns {
+        token: find(&TOKEN_NAMES),
+        symbol: find(&SYMBOL_NAMES),
+        series: find(&SERIES_NAMES),
+        expiry: find(&EXPIRY_NAMES),
+        strike_price: find(&STRIKE_PRICE_NAMES),
+        option_type: find(&OPTION_TYPE_NAMES),
+        lot_size: find(&LOT_SIZE_NAMES),
+        tick_size: find(&TICK_SIZE_NAMES),
+        instrument_name: find(&INSTRUMENT_NAME_NAMES),
+        decimal_locator: find(&DECIMAL_LOCATOR_NAMES),
+    }
+}
+
+// Empty if column isn't in file, or row is short
+fn field<'a>(fields: &[&'a str], column: Option<usize>) -> &'a str {
+    column
+        .and_then(|idx| fields.get(idx))
+        .copied()
+        .unwrap_or("")
+}
+
+// Returns None if token or symbol is missing
+fn parse_instrument(
+    fields: &[&str],
+    columns: &Columns,
+    price_multiplier: i64,
+    rupee_prices: bool,
+) -> Option<Instrument> {
+    let field = |column: Option<usize>| field(fields, column);
+
+    let price = |column: Option<usize>| -> i64 {
+        if rupee_prices {
+            field(column)
+                .parse::<f64>()
+                .map(|price| to_units(price, price_multiplier))
+                .unwrap_or(0)
+        } else {
+            field(column).parse().unwrap_or(0)
+        }
+    };
+
+    let token = field(columns.token).parse().ok()?;
+    let symbol = field(columns.symbol);
+
+    if symbol.is_empty() {
+        return None;
+    }
+
+    let expiry = if rupee_prices {
+        parse_date(field(columns.expiry))
+    } else {
+        nse_expiry_to_date(field(columns.expiry).parse().unwrap_or(0))
+    };
+
+    // Futures and non derivatives have XX, or no option type
+    let option_type = match field(columns.option_type) {
+        option_type @ ("CE" | "PE" | "CA" | "PA") => option_type.to_string(),
+        _ => String::new(),
+    };
+
+    Some(Instrument {
+        token,
+        symbol: symbol.to_string(),
+        series: field(columns.series).to_string(),
+        expiry,
+        // Futures have -1 as strike price
+        strike_price: price(columns.strike_price).max(0),
+        option_type,
+        lot_size: field(columns.lot_size).parse().unwrap_or(1),
+        tick_size: price(columns.tick_size) as i32,
+        price_multiplier,
+    })
+}
+
+// NSE expiry is in seconds since 1980, returns yyyymmdd or 0 if not set
+pub fn nse_expiry_to_date(seconds: i64) -> i32 {
+    if seconds <= 0 {
+        return 0;
+    }
+
+    let (year, month, day) =
+        epoch_days_to_date((seconds + NSE_EPOCH_START as i64).div_euclid(86400));
+
+    year as i32 * 10000 + month as i32 * 100 + day as i32
+}
+
+// Accepts yyyymmdd, yyyy-mm-dd, dd-mmm-yyyy, dd mmm yyyy and ddmmmyyyy
+// Returns 0 for empty or unknown format
+fn parse_date(date: &str) -> i32 {
+    const MONTHS: [&str; 12] = [
+        "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+    ];
+
+    let date: String = date
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' ' | '/'))
+        .collect::<String>()
+        .to_uppercase();
+
+    if date.len() == 8 {
+        return date.parse().unwrap_or(0);
+    }
+
+    if date.len() != 9 || !date.is_char_boundary(2) || !date.is_char_boundary(5) {
+        return 0;
+    }
+
+    let day: i32 = date[..2].parse().unwrap_or(0);
+    let month = MONTHS
+        .iter()
+        .position(|month| *month == &date[2..5])
+        .map(|month| month as i32 + 1)
+        .unwrap_or(0);
+    let year: i32 = date[5..].parse().unwrap_or(0);
+
+    if day == 0 || month == 0 || year == 0 {
+        return 0;
+    }
+
+    year * 10000 + month * 100 + day
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(content: &str, exchange: Exchange, price_decimals: &[(&str, u32)]) -> Registry {
+        let price_decimals = price_decimals
+            .iter()
+            .map(|(name, decimals)| (name.to_string(), *decimals))
+            .collect();
+
+        let mut registry = Registry::default();
+        assert_eq!(registry.load(content, exchange, &price_decimals), 0);
+
+        registry
+    }
+
+    // Row of NNF contract.txt, fields not set are empty
+    fn contract_row(token: i64, instrument_name: &str, symbol: &str) -> String {
+        let mut fields = vec![String::new(); 31];
+        fields[0] = token.to_string();
+        fields[2] = instrument_name.to_string();
+        fields[3] = symbol.to_string();
+        fields[29] = "1000".to_string();
+        fields[30] = "2500".to_string();
+
+        fields.join("|")
+    }
+
+    #[test]
+    fn decimal_locator_column() {
+        let content = "ScripCode,ScripId,TickSize,DecimalLocator\n\
+                       500325,RELIANCE,0.05,\n\
+                       1100,USDINR,0.0025,4\n";
+
+        let registry = load(content, Exchange::BSE, &[]);
+
+        let equity = &registry.instruments[&500325];
+        assert_eq!(equity.price_multiplier, 100);
+        assert_eq!(equity.tick_size, 5);
+
+        // Tick size is in units of the contract
+        let currency = &registry.instruments[&1100];
+        assert_eq!(currency.price_multiplier, 10_000);
+        assert_eq!(currency.tick_size, 25);
+    }
+
+    #[test]
+    fn price_decimals_by_instrument_name() {
+        let content = format!(
+            "header\n{}\n{}\n",
+            contract_row(1, "FUTCUR", "USDINR"),
+            contract_row(2, "FUTIRD", "GS2033")
+        );
+
+        let registry = load(&content, Exchange::NCD, &[("FUTIRD", 4)]);
+
+        assert_eq!(registry.instruments[&1].price_multiplier, 10_000_000);
+        assert_eq!(registry.instruments[&2].price_multiplier, 10_000);
+        // Nse files are already in exchange units
+        assert_eq!(registry.instruments[&2].tick_size, 2500);
+    }
+
+    #[test]
+    fn mcx_is_always_paise() {
+        let content = "InstrumentId,Symbol,TickSize,Decimals\n\
+                       1,GOLD,1,4\n";
+
+        let registry = load(content, Exchange::MCX, &[]);
+
+        assert_eq!(registry.instruments[&1].price_multiplier, 100);
+        assert_eq!(registry.instruments[&1].tick_size, 100);
+    }
+}
@@ -1,5 +1,5 @@
 use core::fmt;
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use bitflags::bitflags;
 use serde::{
@@ -7,12 +7,13 @@ use serde::{
     Deserialize, Deserializer,
 };
 
+use super::{bounded_queue::QueueKind, message_class::MessageClass};
+
 #[derive(Deserialize, Clone)]
 pub struct Settings {
+    // Gap events and gap statistics are only available for nse and mcx exchanges
     pub exchange: Exchange,
 
-    pub udp_auto_switch: bool,
-    pub udp_switch_timeout: usize,
     pub udp_local_ip: String,
     pub primary_mcast_ip: String,
     pub primary_mcast_port: usize,
@@ -20,26 +21,91 @@ pub struct Settings {
     pub secondary_mcast_port: usize,
     pub source_ip: String,
 
+    // Replay a capture or journal instead of reading multicast
+    pub pcap_file: Option<String>,
+    pub journal_file: Option<String>,
+    #[serde(default)]
+    pub replay_pacing: ReplayPacing,
+
+    // Journal every received datagram to this directory
+    pub record_dir: Option<String>,
+    pub record_max_file_mb: Option<usize>,
+
+    // Only these tokens are processed, empty means all tokens
+    #[serde(default)]
+    pub subscribed_tokens: Vec<i64>,
+    pub subscribed_tokens_file: Option<String>,
+    // Subscribes to all tokens of these symbols, requires security_master_file
+    #[serde(default)]
+    pub subscribed_symbols: Vec<String>,
+
+    // Exchange contract master, eg security.txt for NEQ, contract.txt for NFO and NCD
+    pub security_master_file: Option<String>,
+    // Depth is written with instrument details from security master
+    #[serde(default)]
+    pub enriched_output: bool,
+
+    // Json outputs have decimal prices instead of exchange units, eg 82.1234 for 821234000 in NCD
+    #[serde(default)]
+    pub decimal_prices: bool,
+    // Decimal places of contracts which differ from their exchange, by instrument name, eg {"FUTIRD": 4}
+    // Decimal locator column of security master takes precedence, ignored for mcx which is always in paise
+    #[serde(default)]
+    pub price_decimals: HashMap<String, u32>,
+    // Kafka and stdout write market pictures, gap events and master changes as json instead of binary
+    #[serde(default)]
+    pub json_output: bool,
+
     pub output_udp_ip: String,
     pub output_udp_port: usize,
 
     pub fast_template: Option<String>,
 
     pub ws: Option<String>,
+    // Keepalive and eviction of websocket clients
+    #[serde(default)]
+    pub ws_client: WsClientConfig,
 
     pub steps: usize,
 
+    // No of workers, tokens are sharded across them
     pub thread_count: usize,
 
-    pub kafka_partition_no: usize,
+    // Cores to pin threads to, threads are not pinned if not set
+    pub input_core: Option<usize>,
+    pub distributor_core: Option<usize>,
+    // Worker i is pinned to worker_cores[i % len]
+    #[serde(default)]
+    pub worker_cores: Vec<usize>,
+
+    // How each stage waits when it has nothing to do
+    #[serde(default)]
+    pub wait_strategy: WaitStrategies,
+
+    // Capacity and overflow policy of each queue
+    #[serde(default)]
+    pub queues: QueueConfigs,
+
+    // All records go to this partition, else records are partitioned by token
+    pub kafka_partition_no: Option<usize>,
     pub kafka_brokers: String,
+    // Topic of messages whose class isn't in kafka_topics
     pub kafka_topic_name: String,
+    // Topic for each message class, eg {"depth": "nfo_depth", "status": "nfo_status"}
+    #[serde(default)]
+    pub kafka_topics: HashMap<MessageClass, String>,
+    // What to do when librdkafka queue is full
+    #[serde(default)]
+    pub kafka_queue_full: KafkaQueueFullConfig,
     pub kafka_config_path: String,
 
     pub output_targets: OutputTargets,
+
+    // Address to serve prometheus metrics on, eg 0.0.0.0:9100
+    pub metrics_addr: Option<String>,
 }
 
-#[derive(Deserialize, Clone, PartialEq, Copy)]
+#[derive(Deserialize, Clone, PartialEq, Copy, Debug)]
 pub enum Exchange {
     NEQ,
     NFO,
@@ -48,6 +114,190 @@ pub enum Exchange {
     MCX,
 }
 
+#[derive(Deserialize, Clone, PartialEq, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayPacing {
+    // Push packets as fast as possible
+    #[default]
+    Fast,
+    // Sleep between packets as per capture timestamps
+    Original,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitStrategy {
+    // Lowest latency, keeps a core at 100%
+    BusySpin,
+    // Spins for a while, then yields core to other threads
+    SpinYield,
+    // Spins for a while, then sleeps till producer wakes it
+    Park,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct WaitStrategies {
+    pub distributor: WaitStrategy,
+    pub workers: WaitStrategy,
+    pub ws: WaitStrategy,
+    // Thread of each output
+    pub outputs: WaitStrategy,
+    // Thread of each websocket client
+    pub ws_clients: WaitStrategy,
+}
+
+impl Default for WaitStrategies {
+    fn default() -> Self {
+        Self {
+            distributor: WaitStrategy::BusySpin,
+            workers: WaitStrategy::Park,
+            ws: WaitStrategy::BusySpin,
+            outputs: WaitStrategy::Park,
+            ws_clients: WaitStrategy::Park,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    // Producer waits for space
+    Block,
+    DropOldest,
+    DropNewest,
+    // Keeps latest item of each token, only ws queue items have a token
+    Conflate,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct QueueConfig {
+    pub capacity: usize,
+    pub policy: OverflowPolicy,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct QueueConfigs {
+    pub input: QueueConfig,
+    // Each of PACKET_QUEUES
+    pub packet: QueueConfig,
+    // Incrementals of each mcx token
+    pub mcx: QueueConfig,
+    pub ws: QueueConfig,
+    // Each enabled output
+    pub output: QueueConfig,
+    // Each websocket client, must not block as all clients are fed by one thread
+    pub ws_client: QueueConfig,
+    pub recorder: QueueConfig,
+}
+
+impl QueueConfigs {
+    pub fn get(&self, kind: QueueKind) -> QueueConfig {
+        match kind {
+            QueueKind::Input => self.input,
+            QueueKind::Packet => self.packet,
+            QueueKind::Mcx => self.mcx,
+            QueueKind::Ws => self.ws,
+            QueueKind::Output => self.output,
+            QueueKind::WsClient => self.ws_client,
+            QueueKind::Recorder => self.recorder,
+        }
+    }
+}
+
+impl Default for QueueConfigs {
+    fn default() -> Self {
+        Self {
+            input: QueueConfig {
+                capacity: 65536,
+                policy: OverflowPolicy::Block,
+            },
+            packet: QueueConfig {
+                capacity: 16384,
+                policy: OverflowPolicy::Block,
+            },
+            mcx: QueueConfig {
+                capacity: 1024,
+                policy: OverflowPolicy::Block,
+            },
+            // Slow clients shouldn't hold up workers
+            ws: QueueConfig {
+                capacity: 65536,
+                policy: OverflowPolicy::Conflate,
+            },
+            // Outputs don't block workers, unless configured to
+            output: QueueConfig {
+                capacity: 65536,
+                policy: OverflowPolicy::DropOldest,
+            },
+            // Lagging clients get latest picture of each token
+            ws_client: QueueConfig {
+                capacity: 4096,
+                policy: OverflowPolicy::Conflate,
+            },
+            // Journal must be complete, so a slow disk holds up input
+            recorder: QueueConfig {
+                capacity: 65536,
+                policy: OverflowPolicy::Block,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaQueueFullPolicy {
+    // Waits for room with backoff, kafka output queue fills up meanwhile
+    Retry,
+    Drop,
+    // Appended to spill file, sent in order once queue has room
+    Spill,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct KafkaQueueFullConfig {
+    pub policy: KafkaQueueFullPolicy,
+    // Doubles after each retry, record is dropped after max_retries
+    pub retry_backoff_ms: u64,
+    pub max_retries: u32,
+    // Delivered offset is kept next to it, in <spill_file>.pos
+    pub spill_file: Option<String>,
+}
+
+impl Default for KafkaQueueFullConfig {
+    fn default() -> Self {
+        Self {
+            policy: KafkaQueueFullPolicy::Retry,
+            retry_backoff_ms: 10,
+            max_retries: 100,
+            spill_file: None,
+        }
+    }
+}
+
+// All in seconds
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct WsClientConfig {
+    pub ping_interval: u64,
+    // Client is disconnected if nothing is received for this long
+    pub pong_timeout: u64,
+    // Client is disconnected if its socket stays full for this long
+    pub lag_timeout: u64,
+}
+
+impl Default for WsClientConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: 10,
+            pong_timeout: 30,
+            lag_timeout: 5,
+        }
+    }
+}
+
 // Used for converting string array of outputs in config to bit flags
 bitflags! {
     #[derive(Clone, Debug)]
@@ -3,9 +3,10 @@ use std::sync::{
     Arc,
 };
 
-use crossbeam::queue::SegQueue;
-
-use super::packet::Packet;
+use super::{
+    bounded_queue::{BoundedQueue, QueueKind},
+    packet::Packet,
+};
 
 #[derive(Clone)]
 pub struct NseTokenState {
@@ -14,7 +15,7 @@ pub struct NseTokenState {
 
 #[derive(Debug, Clone)]
 pub struct McxTokenState {
-    pub packet_queue: Arc<SegQueue<Packet>>,
+    pub packet_queue: Arc<BoundedQueue<Packet>>,
     pub work_lock: Arc<AtomicBool>,
     pub ptr: Arc<AtomicPtr<Packet>>,
     pub seq_no: Arc<AtomicU32>,
@@ -23,7 +24,7 @@ pub struct McxTokenState {
 impl McxTokenState {
     pub fn new() -> Self {
         Self {
-            packet_queue: Arc::new(SegQueue::new()),
+            packet_queue: Arc::new(BoundedQueue::new(QueueKind::Mcx)),
             ptr: Arc::new(AtomicPtr::new(std::ptr::null_mut())),
             seq_no: Arc::new(AtomicU32::new(0)),
             work_lock: Arc::new(AtomicBool::new(false)),
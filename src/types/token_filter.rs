@@ -0,0 +1,76 @@
+use std::{collections::HashSet, fs};
+
+use crate::{global::SECURITY_MASTER, settings};
+
+// Tokens to be processed
+pub struct TokenFilter {
+    // No subscription configured, all tokens are processed
+    all: bool,
+    tokens: HashSet<i64>,
+}
+
+impl TokenFilter {
+    pub fn new() -> Self {
+        let settings = settings::get();
+
+        let mut tokens: HashSet<i64> = settings.subscribed_tokens.iter().copied().collect();
+
+        // File has tokens separated by whitespace or commas, # starts a comment
+        if let Some(path) = &settings.subscribed_tokens_file {
+            let content = fs::read_to_string(path).expect("Cannot find subscribed tokens file");
+
+            for line in content.lines() {
+                let line = line.split('#').next().unwrap();
+
+                for token in line.split(|c: char| c == ',' || c.is_whitespace()) {
+                    if token.is_empty() {
+                        continue;
+                    }
+
+                    tokens.insert(
+                        token
+                            .parse()
+                            .unwrap_or_else(|_| panic!("Invalid token in tokens file: {}", token)),
+                    );
+                }
+            }
+        }
+
+        let mut symbols_resolved = false;
+
+        for symbol in &settings.subscribed_symbols {
+            let symbol_tokens = SECURITY_MASTER.tokens_for_symbol(symbol);
+
+            if symbol_tokens.is_empty() {
+                println!("No instruments found for subscribed symbol {}", symbol);
+            } else {
+                symbols_resolved = true;
+            }
+
+            tokens.extend(symbol_tokens);
+        }
+
+        // Else a typo or missing master file would subscribe to all tokens
+        if !settings.subscribed_symbols.is_empty() && !symbols_resolved {
+            panic!("None of the subscribed symbols found in security master");
+        }
+
+        let all = settings.subscribed_tokens.is_empty()
+            && settings.subscribed_tokens_file.is_none()
+            && settings.subscribed_symbols.is_empty();
+
+        if !all {
+            println!("Subscribed to {} tokens", tokens.len());
+        }
+
+        Self { all, tokens }
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.all
+    }
+
+    pub fn contains(&self, token: i64) -> bool {
+        self.all || self.tokens.contains(&token)
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+use crate::{global::SECURITY_MASTER, settings, types::settings::Exchange};
+
+// Larger tokens go to overflow map, so a bad master can't allocate unbounded memory
+// Also slab size if there is no security master, as it covers token range of exchanges
+const MAX_SLAB_TOKENS: usize = 1 << 21;
+
+// State of each token, entries are never removed or moved once inserted
+// Tokens in security master are in slab, so lookup is an index and an atomic load
+// Tokens added at runtime beyond slab are in a locked map
+pub struct TokenMap<V: 'static> {
+    slab: Box<[OnceLock<V>]>,
+    // Values are leaked, so references stay valid while map grows
+    overflow: RwLock<HashMap<usize, &'static V>>,
+}
+
+impl<V> TokenMap<V> {
+    // Sized to cover all tokens in security master
+    // Map is only used by given exchanges, for others it has no slab
+    pub fn new(exchanges: &[Exchange]) -> Self {
+        let size = if exchanges.contains(&settings::get().exchange) {
+            SECURITY_MASTER
+                .max_token()
+                .map(|token| (token.max(0) as usize + 1).min(MAX_SLAB_TOKENS))
+                .unwrap_or(MAX_SLAB_TOKENS)
+        } else {
+            0
+        };
+
+        Self {
+            slab: (0..size).map(|_| OnceLock::new()).collect(),
+            overflow: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, token: usize) -> Option<&V> {
+        match self.slab.get(token) {
+            Some(slot) => slot.get(),
+            None => self.overflow.read().unwrap().get(&token).copied(),
+        }
+    }
+
+    pub fn get_or_insert_with(&self, token: usize, f: impl FnOnce() -> V) -> &V {
+        if let Some(slot) = self.slab.get(token) {
+            return slot.get_or_init(f);
+        }
+
+        if let Some(value) = self.overflow.read().unwrap().get(&token) {
+            return value;
+        }
+
+        self.overflow
+            .write()
+            .unwrap()
+            .entry(token)
+            .or_insert_with(|| Box::leak(Box::new(f())))
+    }
+
+    pub fn touch(&self) {}
+}
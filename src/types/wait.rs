@@ -0,0 +1,122 @@
+use std::{
+    hint,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    thread::{self, Thread},
+    time::Duration,
+};
+
+use super::settings::WaitStrategy;
+
+// Empty polls before spin-yield and park give up the core
+const SPIN_LIMIT: u32 = 128;
+// Max time a parked thread sleeps, so it can notice shutdown without a wake
+const PARK_TIMEOUT: Duration = Duration::from_millis(100);
+
+// Lets producer wake consumer thread parked on an empty queue
+pub struct Wakeup {
+    parked: AtomicBool,
+    thread: OnceLock<Thread>,
+}
+
+impl Wakeup {
+    pub const fn new() -> Self {
+        Self {
+            parked: AtomicBool::new(false),
+            thread: OnceLock::new(),
+        }
+    }
+
+    // Called by consumer thread, before it starts waiting
+    pub fn register(&self) {
+        self.thread
+            .set(thread::current())
+            .expect("Wakeup registered twice");
+    }
+
+    // Called by producer after push, cheap if consumer isn't parked
+    pub fn wake(&self) {
+        if self.parked.load(Ordering::SeqCst) {
+            if let Some(thread) = self.thread.get() {
+                thread.unpark();
+            }
+        }
+    }
+
+    fn park(&self, ready: impl Fn() -> bool) {
+        self.parked.store(true, Ordering::SeqCst);
+
+        // Recheck, push may have happened before flag was set
+        if !ready() {
+            thread::park_timeout(PARK_TIMEOUT);
+        }
+
+        self.parked.store(false, Ordering::SeqCst);
+    }
+}
+
+// Waits as per strategy when there is nothing to do, one per consumer thread
+pub struct Waiter {
+    strategy: WaitStrategy,
+    idle_count: u32,
+}
+
+impl Waiter {
+    pub fn new(strategy: WaitStrategy) -> Self {
+        Self {
+            strategy,
+            idle_count: 0,
+        }
+    }
+
+    // Called when work was found
+    pub fn reset(&mut self) {
+        self.idle_count = 0;
+    }
+
+    // Called on every empty poll, ready tells if work has arrived
+    // Park falls back to yield if there is no wakeup
+    pub fn wait(&mut self, wakeup: Option<&Wakeup>, ready: impl Fn() -> bool) {
+        if self.strategy == WaitStrategy::BusySpin || self.idle_count < SPIN_LIMIT {
+            // Busy spin polls forever, so count must not overflow
+            self.idle_count = self.idle_count.saturating_add(1);
+            hint::spin_loop();
+            return;
+        }
+
+        match (self.strategy, wakeup) {
+            (WaitStrategy::Park, Some(wakeup)) => wakeup.park(ready),
+            _ => thread::yield_now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn busy_spin_count_saturates() {
+        let mut waiter = Waiter::new(WaitStrategy::BusySpin);
+        waiter.idle_count = u32::MAX - 1;
+
+        waiter.wait(None, || false);
+        waiter.wait(None, || false);
+        assert_eq!(waiter.idle_count, u32::MAX);
+
+        waiter.reset();
+        assert_eq!(waiter.idle_count, 0);
+    }
+
+    #[test]
+    fn yield_after_spin_limit() {
+        let mut waiter = Waiter::new(WaitStrategy::SpinYield);
+
+        for _ in 0..SPIN_LIMIT * 2 {
+            waiter.wait(None, || false);
+        }
+        assert_eq!(waiter.idle_count, SPIN_LIMIT);
+    }
+}
@@ -20,8 +20,8 @@ pub enum WorkType {
     NseUncompressed,
     SegmentWise(u8),
     TokenWise(i32),
-    McxDepthSnapshot,
-    McxDepthIncr,
+    McxDepthSnapshot(i64),
+    McxDepthIncr(i64),
     McxOther,
 }
 
@@ -33,9 +33,19 @@ impl WorkType {
             Self::NseUncompressed => 2, // First element of queue is for uncompressed
             Self::SegmentWise(i) => 3 + *i as usize, // each segment has its own queue
             Self::TokenWise(i) => *i as usize, // Shouldn't be used on queue, only on map
-            Self::McxDepthSnapshot => 0,
-            Self::McxDepthIncr => 0,
+            Self::McxDepthSnapshot(_) => 0,
+            Self::McxDepthIncr(_) => 0,
             Self::McxOther => 0,
         }
     }
+
+    // Work with same key is always processed by same worker
+    pub fn shard_key(&self) -> u64 {
+        match self {
+            Self::TokenWise(token) => *token as u64,
+            Self::McxDepthSnapshot(token) | Self::McxDepthIncr(token) => *token as u64,
+            // Queue work is keyed by queue, so packets of a queue stay in order
+            _ => self.get_id() as u64,
+        }
+    }
 }
@@ -0,0 +1,49 @@
+use crossbeam::queue::SegQueue;
+
+use super::{
+    wait::{Waiter, Wakeup},
+    work::Work,
+};
+
+// Work of one shard of tokens, owned by a single worker thread
+// Not bounded, each token or packet queue has at most one pending work
+pub struct WorkerQueue {
+    queue: SegQueue<Work>,
+    wakeup: Wakeup,
+}
+
+impl WorkerQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: SegQueue::new(),
+            wakeup: Wakeup::new(),
+        }
+    }
+
+    // Called by owning worker, before it starts popping
+    pub fn register(&self) {
+        self.wakeup.register();
+    }
+
+    pub fn push(&self, work: Work) {
+        self.queue.push(work);
+        self.wakeup.wake();
+    }
+
+    pub fn pop(&self) -> Option<Work> {
+        self.queue.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    // Called by owning worker on empty queue
+    pub fn wait(&self, waiter: &mut Waiter) {
+        waiter.wait(Some(&self.wakeup), || !self.queue.is_empty());
+    }
+}
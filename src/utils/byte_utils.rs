@@ -1,9 +1,63 @@
 use std::{
+    fmt,
     mem::{self, MaybeUninit},
     ptr,
 };
 
-use crate::constants::BUF_SIZE;
+#[derive(Debug)]
+pub enum ParseError {
+    // Read of `needed` bytes at `offset` goes past received `len`
+    Truncated {
+        offset: usize,
+        needed: usize,
+        len: usize,
+    },
+    // Count from wire is more than structure can hold
+    InvalidCount {
+        field: &'static str,
+        count: i64,
+        max: usize,
+    },
+    Decompress,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Truncated {
+                offset,
+                needed,
+                len,
+            } => write!(
+                f,
+                "truncated, {} bytes needed at offset {} of {}",
+                needed, offset, len
+            ),
+            ParseError::InvalidCount { field, count, max } => {
+                write!(f, "invalid {} {}, max {}", field, count, max)
+            }
+            ParseError::Decompress => write!(f, "decompression failed"),
+        }
+    }
+}
+
+// Returns `needed` bytes at `offset`, or error if buffer is shorter
+pub fn try_slice(buf: &[u8], offset: usize, needed: usize) -> Result<&[u8], ParseError> {
+    match offset.checked_add(needed) {
+        Some(end) if end <= buf.len() => Ok(&buf[offset..end]),
+        _ => Err(ParseError::Truncated {
+            offset,
+            needed,
+            len: buf.len(),
+        }),
+    }
+}
+
+pub fn try_bytes_to_struct<T>(buf: &[u8], offset: usize) -> Result<T, ParseError> {
+    let buf = try_slice(buf, offset, mem::size_of::<T>())?;
+
+    Ok(unsafe { ptr::read_unaligned(buf.as_ptr() as *const T) })
+}
 
 pub fn struct_to_bytes<T: Copy>(s: &T, buffer: &mut [u8]) -> usize {
     let mut size = std::mem::size_of::<T>();
@@ -41,14 +95,16 @@ pub fn struct_to_bytes_heap<T>(src: T, dst: &mut [u8]) -> usize {
     size
 }
 
-pub fn bytes_to_struct<T>(buff: &[u8]) -> T {
-    let buff_ptr = buff.as_ptr() as *const T;
-
-    unsafe { std::ptr::read(buff_ptr) }
+// Error if buffer is shorter than struct
+pub fn bytes_to_struct<T>(buff: &[u8]) -> Result<T, ParseError> {
+    try_bytes_to_struct(buff, 0)
 }
 
-pub fn uninit_to_buf(src: &[MaybeUninit<u8>; BUF_SIZE]) -> [u8; BUF_SIZE] {
-    unsafe { std::mem::transmute::<[MaybeUninit<u8>; BUF_SIZE], [u8; BUF_SIZE]>(*src) }
+// Exchange strings are padded with spaces or nulls
+pub fn bytes_to_string(buf: &[u8]) -> String {
+    String::from_utf8_lossy(buf)
+        .trim_matches(|c: char| c == ' ' || c == '\0')
+        .to_string()
 }
 
 pub fn create_empty<T>() -> T {
@@ -77,10 +133,70 @@ pub fn bytes_to_partial_struct<T: Copy>(s: &mut T, buffer: &[u8]) {
     };
 }
 
-pub fn bytes_to_struct_mut<T>(buf: &mut [u8]) -> &mut T {
-    unsafe { &mut *(buf.as_mut_ptr() as *mut T) }
+pub fn bytes_to_struct_mut<T>(buf: &mut [u8]) -> Result<&mut T, ParseError> {
+    try_slice(buf, 0, mem::size_of::<T>())?;
+
+    Ok(unsafe { &mut *(buf.as_mut_ptr() as *mut T) })
+}
+
+pub fn bytes_to_struct_ptr<T>(buf: &[u8]) -> Result<&T, ParseError> {
+    try_slice(buf, 0, mem::size_of::<T>())?;
+
+    Ok(unsafe { &*(buf.as_ptr() as *const T) })
 }
 
-pub fn bytes_to_struct_ptr<T>(buf: &[u8]) -> &T {
-    unsafe { &*(buf.as_ptr() as *const T) }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slice_within_buffer() {
+        let buf = [1, 2, 3, 4];
+
+        assert_eq!(try_slice(&buf, 1, 3).unwrap(), &[2, 3, 4]);
+        assert_eq!(try_slice(&buf, 4, 0).unwrap(), &[] as &[u8]);
+    }
+
+    #[test]
+    fn slice_past_end() {
+        let buf = [1, 2, 3, 4];
+
+        assert!(matches!(
+            try_slice(&buf, 2, 3),
+            Err(ParseError::Truncated {
+                offset: 2,
+                needed: 3,
+                len: 4
+            })
+        ));
+        assert!(try_slice(&buf, 5, 0).is_err());
+    }
+
+    #[test]
+    fn slice_offset_overflow() {
+        let buf = [1, 2, 3, 4];
+
+        assert!(try_slice(&buf, usize::MAX, 2).is_err());
+        assert!(try_slice(&buf, 2, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn struct_from_truncated_buffer() {
+        let buf = 0x0102_0304u32.to_le_bytes();
+
+        assert_eq!(try_bytes_to_struct::<u32>(&buf, 0).unwrap(), 0x0102_0304);
+        assert!(try_bytes_to_struct::<u32>(&buf, 1).is_err());
+        assert!(try_bytes_to_struct::<u64>(&buf, 0).is_err());
+    }
+
+    #[test]
+    fn struct_refs_from_truncated_buffer() {
+        let mut buf = [0u8; 8];
+
+        assert!(bytes_to_struct::<u64>(&buf).is_ok());
+        assert!(bytes_to_struct::<u64>(&buf[..7]).is_err());
+        assert!(bytes_to_struct_ptr::<u64>(&buf[..7]).is_err());
+        assert!(bytes_to_struct_mut::<u64>(&mut buf[..7]).is_err());
+        assert!(bytes_to_struct_mut::<u32>(&mut buf[4..]).is_ok());
+    }
 }
@@ -1,4 +1,7 @@
 pub mod atomic_utils;
 pub mod byte_utils;
+pub mod pcap_utils;
+pub mod price_utils;
+pub mod thread_utils;
 pub mod time_utils;
 pub mod udp_utils;
@@ -0,0 +1,704 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, ErrorKind, Read},
+    net::Ipv4Addr,
+};
+
+// Pcap magic numbers, in file byte order
+const PCAP_MAGIC_US: u32 = 0xa1b2c3d4;
+const PCAP_MAGIC_NS: u32 = 0xa1b23c4d;
+
+// Pcapng block types
+const PCAPNG_SHB: u32 = 0x0a0d0d0a;
+const PCAPNG_IDB: u32 = 0x00000001;
+const PCAPNG_SPB: u32 = 0x00000003;
+const PCAPNG_EPB: u32 = 0x00000006;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+const PCAPNG_OPT_IF_TSRESOL: u16 = 9;
+
+// Larger records are treated as corrupt, so a bad length can't allocate gigabytes
+// Covers default snaplen of 262144, plus pcapng block overhead
+const MAX_RECORD_LEN: usize = 256 * 1024;
+const MAX_BLOCK_LEN: usize = MAX_RECORD_LEN + 1024;
+
+// Link types
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+const IP_PROTO_UDP: u8 = 17;
+
+pub struct PcapRecord {
+    // Capture timestamp in nanoseconds since epoch
+    pub ts_ns: u64,
+    pub link_type: u32,
+    pub data: Vec<u8>,
+}
+
+pub struct UdpDatagram<'a> {
+    pub src_ip: Ipv4Addr,
+    pub dst_ip: Ipv4Addr,
+    pub dst_port: u16,
+    pub payload: &'a [u8],
+}
+
+struct Interface {
+    link_type: u32,
+    // Timestamp units per second, from if_tsresol
+    units_per_sec: u64,
+}
+
+enum Format {
+    Pcap { link_type: u32, ts_unit_ns: u64 },
+    Pcapng { interfaces: Vec<Interface> },
+}
+
+// Reads pcap and pcapng captures one record at a time
+pub struct PcapReader<R = BufReader<File>> {
+    reader: R,
+    format: Format,
+    big_endian: bool,
+}
+
+impl PcapReader {
+    pub fn open(path: &str) -> io::Result<PcapReader> {
+        PcapReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> PcapReader<R> {
+    pub fn new(mut reader: R) -> io::Result<PcapReader<R>> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+
+        let le_magic = u32::from_le_bytes(magic);
+        let be_magic = u32::from_be_bytes(magic);
+
+        if le_magic == PCAPNG_SHB {
+            let mut pcap_reader = PcapReader {
+                reader,
+                format: Format::Pcapng { interfaces: vec![] },
+                big_endian: false,
+            };
+            // Magic is already consumed, read rest of section header
+            pcap_reader.read_section_header()?;
+
+            return Ok(pcap_reader);
+        }
+
+        let (big_endian, ts_unit_ns) = match (le_magic, be_magic) {
+            (PCAP_MAGIC_US, _) => (false, 1000),
+            (PCAP_MAGIC_NS, _) => (false, 1),
+            (_, PCAP_MAGIC_US) => (true, 1000),
+            (_, PCAP_MAGIC_NS) => (true, 1),
+            _ => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "Not a pcap or pcapng file",
+                ))
+            }
+        };
+
+        // Rest of global header: version, thiszone, sigfigs, snaplen, network
+        let mut header = [0u8; 20];
+        reader.read_exact(&mut header)?;
+
+        let network: [u8; 4] = header[16..20].try_into().unwrap();
+        let link_type = if big_endian {
+            u32::from_be_bytes(network)
+        } else {
+            u32::from_le_bytes(network)
+        };
+
+        Ok(PcapReader {
+            reader,
+            format: Format::Pcap {
+                link_type,
+                ts_unit_ns,
+            },
+            big_endian,
+        })
+    }
+
+    // Returns None at end of file
+    pub fn next_record(&mut self) -> io::Result<Option<PcapRecord>> {
+        match self.format {
+            Format::Pcap { .. } => self.next_pcap_record(),
+            Format::Pcapng { .. } => self.next_pcapng_record(),
+        }
+    }
+
+    fn next_pcap_record(&mut self) -> io::Result<Option<PcapRecord>> {
+        let (link_type, ts_unit_ns) = match self.format {
+            Format::Pcap {
+                link_type,
+                ts_unit_ns,
+            } => (link_type, ts_unit_ns),
+            _ => unreachable!(),
+        };
+
+        // ts_sec, ts_frac, incl_len, orig_len
+        let mut header = [0u8; 16];
+        if !self.read_or_eof(&mut header)? {
+            return Ok(None);
+        }
+
+        let ts_sec = self.u32_at(&header, 0) as u64;
+        let ts_frac = self.u32_at(&header, 4) as u64;
+        let incl_len = self.u32_at(&header, 8) as usize;
+
+        if incl_len > MAX_RECORD_LEN {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Invalid pcap record length",
+            ));
+        }
+
+        let mut data = vec![0u8; incl_len];
+        self.reader.read_exact(&mut data)?;
+
+        Ok(Some(PcapRecord {
+            ts_ns: ts_sec * 1_000_000_000 + ts_frac * ts_unit_ns,
+            link_type,
+            data,
+        }))
+    }
+
+    fn next_pcapng_record(&mut self) -> io::Result<Option<PcapRecord>> {
+        loop {
+            let mut header = [0u8; 8];
+            if !self.read_or_eof(&mut header)? {
+                return Ok(None);
+            }
+
+            let block_type = self.u32_at(&header, 0);
+
+            if block_type == PCAPNG_SHB {
+                // New section, byte order and interfaces are reset
+                self.read_section_header_body(&header)?;
+                continue;
+            }
+
+            let block_len = self.u32_at(&header, 4) as usize;
+            if !(12..=MAX_BLOCK_LEN).contains(&block_len) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "Invalid pcapng block length",
+                ));
+            }
+
+            // Body excludes header and trailing length
+            let mut body = vec![0u8; block_len - 12];
+            self.reader.read_exact(&mut body)?;
+            let mut trailer = [0u8; 4];
+            self.reader.read_exact(&mut trailer)?;
+
+            match block_type {
+                PCAPNG_IDB => self.add_interface(&body)?,
+                PCAPNG_EPB => return self.parse_enhanced_packet(&body).map(Some),
+                PCAPNG_SPB => return self.parse_simple_packet(&body).map(Some),
+                // Skip name resolution, statistics and custom blocks
+                _ => continue,
+            }
+        }
+    }
+
+    fn read_section_header(&mut self) -> io::Result<()> {
+        let mut rest = [0u8; 4];
+        self.reader.read_exact(&mut rest)?;
+
+        let mut header = [0u8; 8];
+        header[..4].copy_from_slice(&PCAPNG_SHB.to_le_bytes());
+        header[4..].copy_from_slice(&rest);
+
+        self.read_section_header_body(&header)
+    }
+
+    fn read_section_header_body(&mut self, header: &[u8; 8]) -> io::Result<()> {
+        let mut byte_order = [0u8; 4];
+        self.reader.read_exact(&mut byte_order)?;
+
+        self.big_endian = if u32::from_le_bytes(byte_order) == PCAPNG_BYTE_ORDER_MAGIC {
+            false
+        } else if u32::from_be_bytes(byte_order) == PCAPNG_BYTE_ORDER_MAGIC {
+            true
+        } else {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Invalid pcapng byte order magic",
+            ));
+        };
+
+        // Block length can only be read after byte order is known
+        let block_len = self.u32_at(header, 4) as usize;
+        if !(16..=MAX_BLOCK_LEN).contains(&block_len) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Invalid pcapng section header length",
+            ));
+        }
+
+        // Skip version, section length, options and trailing length
+        let mut rest = vec![0u8; block_len - 12];
+        self.reader.read_exact(&mut rest)?;
+
+        self.format = Format::Pcapng { interfaces: vec![] };
+
+        Ok(())
+    }
+
+    fn add_interface(&mut self, body: &[u8]) -> io::Result<()> {
+        if body.len() < 8 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Invalid pcapng interface block",
+            ));
+        }
+
+        let link_type = self.u16_at(body, 0) as u32;
+        // Microseconds unless if_tsresol says otherwise
+        let mut units_per_sec = 1_000_000;
+
+        // Walk options looking for timestamp resolution
+        let mut offset = 8;
+        while offset + 4 <= body.len() {
+            let code = self.u16_at(body, offset);
+            let len = self.u16_at(body, offset + 2) as usize;
+            offset += 4;
+
+            if code == 0 || offset + len > body.len() {
+                break;
+            }
+
+            if code == PCAPNG_OPT_IF_TSRESOL && len >= 1 {
+                let tsresol = body[offset];
+                let exp = (tsresol & 0x7f) as u32;
+
+                // Msb set means power of 2, otherwise power of 10
+                units_per_sec = if tsresol & 0x80 != 0 {
+                    2u64.checked_pow(exp)
+                } else {
+                    10u64.checked_pow(exp)
+                }
+                .unwrap_or(1_000_000_000)
+                .max(1);
+            }
+
+            // Options are padded to 4 bytes
+            offset += (len + 3) & !3;
+        }
+
+        if let Format::Pcapng { interfaces } = &mut self.format {
+            interfaces.push(Interface {
+                link_type,
+                units_per_sec,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn parse_enhanced_packet(&self, body: &[u8]) -> io::Result<PcapRecord> {
+        if body.len() < 20 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Invalid pcapng enhanced packet block",
+            ));
+        }
+
+        let interface_id = self.u32_at(body, 0) as usize;
+        let ts = ((self.u32_at(body, 4) as u64) << 32) | self.u32_at(body, 8) as u64;
+        let captured_len = self.u32_at(body, 12) as usize;
+
+        let interface = self.interface(interface_id)?;
+        let end = (20 + captured_len).min(body.len());
+
+        Ok(PcapRecord {
+            // Wide multiply, so power of 2 resolutions don't lose precision
+            ts_ns: (ts as u128 * 1_000_000_000 / interface.units_per_sec as u128) as u64,
+            link_type: interface.link_type,
+            data: body[20..end].to_vec(),
+        })
+    }
+
+    fn parse_simple_packet(&self, body: &[u8]) -> io::Result<PcapRecord> {
+        if body.len() < 4 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Invalid pcapng simple packet block",
+            ));
+        }
+
+        let original_len = self.u32_at(body, 0) as usize;
+        let end = (4 + original_len).min(body.len());
+
+        // Simple packets carry no timestamp and always belong to first interface
+        Ok(PcapRecord {
+            ts_ns: 0,
+            link_type: self.interface(0)?.link_type,
+            data: body[4..end].to_vec(),
+        })
+    }
+
+    fn interface(&self, id: usize) -> io::Result<&Interface> {
+        match &self.format {
+            Format::Pcapng { interfaces } => interfaces.get(id),
+            _ => None,
+        }
+        .ok_or(io::Error::new(
+            ErrorKind::InvalidData,
+            "Packet refers to unknown pcapng interface",
+        ))
+    }
+
+    // Returns false on clean end of file
+    fn read_or_eof(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        match self.reader.read_exact(buf) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn u32_at(&self, buf: &[u8], offset: usize) -> u32 {
+        let bytes = buf[offset..offset + 4].try_into().unwrap();
+
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    fn u16_at(&self, buf: &[u8], offset: usize) -> u16 {
+        let bytes = buf[offset..offset + 2].try_into().unwrap();
+
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+}
+
+// Extracts udp over ipv4 from captured frame, None for anything else
+pub fn parse_udp(link_type: u32, frame: &[u8]) -> Option<UdpDatagram<'_>> {
+    let ip = match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ether_type =
+                u16::from_be_bytes(frame.get(offset..offset + 2)?.try_into().ok()?);
+
+            // Skip vlan tags
+            while ether_type == ETHERTYPE_VLAN || ether_type == ETHERTYPE_QINQ {
+                offset += 4;
+                ether_type = u16::from_be_bytes(frame.get(offset..offset + 2)?.try_into().ok()?);
+            }
+
+            if ether_type != ETHERTYPE_IPV4 {
+                return None;
+            }
+
+            frame.get(offset + 2..)?
+        }
+        LINKTYPE_LINUX_SLL => {
+            let protocol = u16::from_be_bytes(frame.get(14..16)?.try_into().ok()?);
+            if protocol != ETHERTYPE_IPV4 {
+                return None;
+            }
+
+            frame.get(16..)?
+        }
+        LINKTYPE_LINUX_SLL2 => {
+            let protocol = u16::from_be_bytes(frame.get(0..2)?.try_into().ok()?);
+            if protocol != ETHERTYPE_IPV4 {
+                return None;
+            }
+
+            frame.get(20..)?
+        }
+        // Loopback has 4 byte address family in host order
+        LINKTYPE_NULL => frame.get(4..)?,
+        LINKTYPE_RAW | LINKTYPE_IPV4 => frame,
+        _ => return None,
+    };
+
+    // Ipv4 header
+    if ip.len() < 20 || ip[0] >> 4 != 4 || ip[9] != IP_PROTO_UDP {
+        return None;
+    }
+
+    // Fragmented datagrams are not reassembled
+    let flags_offset = u16::from_be_bytes([ip[6], ip[7]]);
+    if flags_offset & 0x3fff != 0 {
+        return None;
+    }
+
+    let header_len = ((ip[0] & 0x0f) as usize) * 4;
+    let total_len = (u16::from_be_bytes([ip[2], ip[3]]) as usize).min(ip.len());
+
+    let src_ip = Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]);
+    let dst_ip = Ipv4Addr::new(ip[16], ip[17], ip[18], ip[19]);
+
+    // Udp header
+    let udp = ip.get(header_len..total_len)?;
+    if udp.len() < 8 {
+        return None;
+    }
+
+    let dst_port = u16::from_be_bytes([udp[2], udp[3]]);
+    let udp_len = (u16::from_be_bytes([udp[4], udp[5]]) as usize).clamp(8, udp.len());
+
+    Some(UdpDatagram {
+        src_ip,
+        dst_ip,
+        dst_port,
+        payload: &udp[8..udp_len],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Classic capture with given magic, records are (ts_sec, ts_frac, data)
+    fn pcap(big_endian: bool, magic: u32, records: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let u32_bytes = |value: u32| {
+            if big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        };
+
+        let mut bytes = u32_bytes(magic).to_vec();
+        // Version 2.4, thiszone, sigfigs
+        bytes.extend_from_slice(&[0; 12]);
+        bytes.extend_from_slice(&u32_bytes(65535));
+        bytes.extend_from_slice(&u32_bytes(LINKTYPE_RAW));
+
+        for (ts_sec, ts_frac, data) in records {
+            bytes.extend_from_slice(&u32_bytes(*ts_sec));
+            bytes.extend_from_slice(&u32_bytes(*ts_frac));
+            bytes.extend_from_slice(&u32_bytes(data.len() as u32));
+            bytes.extend_from_slice(&u32_bytes(data.len() as u32));
+            bytes.extend_from_slice(data);
+        }
+
+        bytes
+    }
+
+    // Pcapng block in little endian, body is padded to 4 bytes
+    fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let padded = (body.len() + 3) & !3;
+        let len = (12 + padded) as u32;
+
+        let mut bytes = block_type.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(body);
+        bytes.resize(8 + padded, 0);
+        bytes.extend_from_slice(&len.to_le_bytes());
+
+        bytes
+    }
+
+    fn section_header() -> Vec<u8> {
+        let mut body = PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        // Version 1.0, unknown section length
+        body.extend_from_slice(&[1, 0, 0, 0]);
+        body.extend_from_slice(&u64::MAX.to_le_bytes());
+
+        block(PCAPNG_SHB, &body)
+    }
+
+    fn interface(tsresol: Option<u8>) -> Vec<u8> {
+        let mut body = (LINKTYPE_ETHERNET as u16).to_le_bytes().to_vec();
+        body.extend_from_slice(&[0, 0]);
+        body.extend_from_slice(&65535u32.to_le_bytes());
+
+        if let Some(tsresol) = tsresol {
+            body.extend_from_slice(&PCAPNG_OPT_IF_TSRESOL.to_le_bytes());
+            body.extend_from_slice(&1u16.to_le_bytes());
+            body.extend_from_slice(&[tsresol, 0, 0, 0]);
+            // End of options
+            body.extend_from_slice(&[0; 4]);
+        }
+
+        block(PCAPNG_IDB, &body)
+    }
+
+    fn enhanced_packet(ts: u64, data: &[u8]) -> Vec<u8> {
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ts as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+
+        block(PCAPNG_EPB, &body)
+    }
+
+    fn read_all(bytes: Vec<u8>) -> Vec<PcapRecord> {
+        let mut reader = PcapReader::new(&bytes[..]).unwrap();
+        let mut records = vec![];
+
+        while let Some(record) = reader.next_record().unwrap() {
+            records.push(record);
+        }
+
+        records
+    }
+
+    #[test]
+    fn pcap_little_endian_microseconds() {
+        let records = read_all(pcap(false, PCAP_MAGIC_US, &[(10, 5, b"ab"), (11, 0, b"c")]));
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].ts_ns, 10_000_005_000);
+        assert_eq!(records[0].link_type, LINKTYPE_RAW);
+        assert_eq!(records[0].data, b"ab");
+        assert_eq!(records[1].ts_ns, 11_000_000_000);
+        assert_eq!(records[1].data, b"c");
+    }
+
+    #[test]
+    fn pcap_big_endian() {
+        let records = read_all(pcap(true, PCAP_MAGIC_US, &[(10, 5, b"ab")]));
+
+        assert_eq!(records[0].ts_ns, 10_000_005_000);
+        assert_eq!(records[0].link_type, LINKTYPE_RAW);
+        assert_eq!(records[0].data, b"ab");
+    }
+
+    #[test]
+    fn pcap_nanoseconds() {
+        let records = read_all(pcap(false, PCAP_MAGIC_NS, &[(10, 5, b"ab")]));
+        assert_eq!(records[0].ts_ns, 10_000_000_005);
+
+        let records = read_all(pcap(true, PCAP_MAGIC_NS, &[(10, 5, b"ab")]));
+        assert_eq!(records[0].ts_ns, 10_000_000_005);
+    }
+
+    #[test]
+    fn pcap_truncated_last_record() {
+        let mut bytes = pcap(false, PCAP_MAGIC_US, &[(10, 0, b"ab"), (11, 0, b"cdef")]);
+        bytes.truncate(bytes.len() - 2);
+
+        let mut reader = PcapReader::new(&bytes[..]).unwrap();
+
+        assert_eq!(reader.next_record().unwrap().unwrap().data, b"ab");
+        assert!(reader.next_record().is_err());
+    }
+
+    #[test]
+    fn pcap_record_length_bounded() {
+        let mut bytes = pcap(false, PCAP_MAGIC_US, &[(10, 0, b"ab")]);
+        // Incl len of record
+        bytes[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut reader = PcapReader::new(&bytes[..]).unwrap();
+        let error = reader.next_record().err().unwrap();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn not_a_capture() {
+        assert!(PcapReader::new(&b"journal of some kind"[..]).is_err());
+    }
+
+    #[test]
+    fn pcapng_default_resolution_is_microseconds() {
+        let mut bytes = section_header();
+        bytes.extend(interface(None));
+        bytes.extend(enhanced_packet(1_500_000, b"abc"));
+
+        let records = read_all(bytes);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].ts_ns, 1_500_000_000);
+        assert_eq!(records[0].link_type, LINKTYPE_ETHERNET);
+        assert_eq!(records[0].data, b"abc");
+    }
+
+    #[test]
+    fn pcapng_tsresol() {
+        // Nanoseconds
+        let mut bytes = section_header();
+        bytes.extend(interface(Some(9)));
+        bytes.extend(enhanced_packet(1_500_000_001, b"abc"));
+        assert_eq!(read_all(bytes)[0].ts_ns, 1_500_000_001);
+
+        // Power of 2, 1024 units per second
+        let mut bytes = section_header();
+        bytes.extend(interface(Some(0x80 | 10)));
+        bytes.extend(enhanced_packet(2048, b"abc"));
+        assert_eq!(read_all(bytes)[0].ts_ns, 2_000_000_000);
+
+        // Finer than nanosecond
+        let mut bytes = section_header();
+        bytes.extend(interface(Some(12)));
+        bytes.extend(enhanced_packet(1_500_000_001_000, b"abc"));
+        assert_eq!(read_all(bytes)[0].ts_ns, 1_500_000_001);
+    }
+
+    #[test]
+    fn pcapng_skips_unknown_blocks() {
+        let mut bytes = section_header();
+        bytes.extend(interface(None));
+        // Name resolution block
+        bytes.extend(block(4, &[0; 4]));
+        bytes.extend(enhanced_packet(1, b"abc"));
+
+        assert_eq!(read_all(bytes)[0].data, b"abc");
+    }
+
+    #[test]
+    fn pcapng_truncated_last_record() {
+        let mut bytes = section_header();
+        bytes.extend(interface(None));
+        bytes.extend(enhanced_packet(1, b"abc"));
+        bytes.extend(enhanced_packet(2, b"defgh"));
+        bytes.truncate(bytes.len() - 6);
+
+        let mut reader = PcapReader::new(&bytes[..]).unwrap();
+
+        assert_eq!(reader.next_record().unwrap().unwrap().data, b"abc");
+        assert!(reader.next_record().is_err());
+    }
+
+    #[test]
+    fn pcapng_block_length_bounded() {
+        let mut bytes = section_header();
+        let start = bytes.len();
+        bytes.extend(interface(None));
+        bytes[start + 4..start + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut reader = PcapReader::new(&bytes[..]).unwrap();
+        let error = reader.next_record().err().unwrap();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn udp_from_raw_ip() {
+        let mut frame = vec![0x45, 0, 0, 31, 0, 0, 0, 0, 64, IP_PROTO_UDP, 0, 0];
+        frame.extend_from_slice(&[10, 0, 0, 1, 239, 1, 1, 1]);
+        // Udp header, ports 1000 to 2000, length 11
+        frame.extend_from_slice(&[3, 232, 7, 208, 0, 11, 0, 0]);
+        frame.extend_from_slice(b"abc");
+
+        let datagram = parse_udp(LINKTYPE_RAW, &frame).unwrap();
+
+        assert_eq!(datagram.src_ip, Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(datagram.dst_ip, Ipv4Addr::new(239, 1, 1, 1));
+        assert_eq!(datagram.dst_port, 2000);
+        assert_eq!(datagram.payload, b"abc");
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use crate::{global::SECURITY_MASTER, types::settings::Exchange};
+
+// Decimal places of prices in exchange units
+pub fn decimal_locator(exchange: Exchange) -> u32 {
+    match exchange {
+        // Currency and interest rate derivatives are in 10^7
+        Exchange::NCD => 7,
+        // Paise, mcx prices are converted to paise while decoding
+        Exchange::NEQ | Exchange::NFO | Exchange::BSE | Exchange::MCX => 2,
+    }
+}
+
+// Exchange price units per rupee
+pub fn price_multiplier(exchange: Exchange) -> i64 {
+    10i64.pow(decimal_locator(exchange))
+}
+
+// Exchange price units per rupee of a contract
+// Decimal locator from contract master is used if present, else price_decimals setting of its instrument name
+// MCX is always in paise, as prices are converted to paise while decoding
+pub fn instrument_price_multiplier(
+    exchange: Exchange,
+    instrument_name: &str,
+    decimals: Option<u32>,
+    price_decimals: &HashMap<String, u32>,
+) -> i64 {
+    if exchange == Exchange::MCX {
+        return price_multiplier(exchange);
+    }
+
+    let decimals = decimals
+        .or_else(|| price_decimals.get(instrument_name.trim()).copied())
+        .unwrap_or_else(|| decimal_locator(exchange));
+
+    // Prices are i32 on the wire, more decimals can't be represented
+    10i64.pow(decimals.min(9))
+}
+
+// Security master has multiplier of each contract, else exchange default is used
+pub fn token_price_multiplier(token: i64, exchange: Exchange) -> i64 {
+    SECURITY_MASTER
+        .get(token)
+        .map(|instrument| instrument.price_multiplier)
+        .unwrap_or_else(|| price_multiplier(exchange))
+}
+
+// Rounds to nearest unit, because decimal prices like 101.15 aren't exact in f64
+pub fn to_units(price: f64, multiplier: i64) -> i64 {
+    (price * multiplier as f64).round() as i64
+}
+
+pub fn to_decimal(units: i64, multiplier: i64) -> f64 {
+    units as f64 / multiplier as f64
+}
@@ -0,0 +1,18 @@
+use core_affinity::CoreId;
+
+// Pins current thread to core, does nothing if core is not set
+pub fn pin_to_core(core: Option<usize>) {
+    let Some(core) = core else {
+        return;
+    };
+
+    if core_affinity::set_for_current(CoreId { id: core }) {
+        println!(
+            "Pinned {:?} to core {}",
+            std::thread::current().name(),
+            core
+        );
+    } else {
+        println!("Unable to pin thread to core {}", core);
+    }
+}
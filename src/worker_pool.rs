@@ -0,0 +1,185 @@
+use std::{
+    ptr,
+    sync::atomic::Ordering,
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    constants::MAX_INCR_TO_PROCESS,
+    global::{DISTRIBUTOR_STOPPED, PACKET_QUEUES, WORKER_QUEUES, WORK_LOCKS},
+    settings,
+    types::{
+        settings::WaitStrategy,
+        wait::Waiter,
+        work::{Work, WorkType},
+        worker_queue::WorkerQueue,
+    },
+    utils::thread_utils::pin_to_core,
+};
+
+// Fixed set of workers, each owning a shard of tokens
+// Updates of a token are always processed on same thread, in order
+pub struct WorkerPool {}
+
+impl WorkerPool {
+    pub fn new() -> Self {
+        if settings::get().thread_count == 0 {
+            panic!("thread_count must be at least 1");
+        }
+
+        Self {}
+    }
+
+    pub fn start_workers(self) -> Vec<JoinHandle<()>> {
+        let worker_cores = &settings::get().worker_cores;
+        let wait_strategy = settings::get().wait_strategy.workers;
+
+        (0..WORKER_QUEUES.len())
+            .map(|shard| {
+                // Cores are reused if there are less cores than workers
+                let core = if worker_cores.is_empty() {
+                    None
+                } else {
+                    Some(worker_cores[shard % worker_cores.len()])
+                };
+
+                thread::Builder::new()
+                    .name(format!("worker-{}", shard))
+                    .spawn(move || run_worker(&WORKER_QUEUES[shard], core, wait_strategy))
+                    .expect("Unable to spawn worker thread")
+            })
+            .collect()
+    }
+}
+
+fn run_worker(worker: &WorkerQueue, core: Option<usize>, wait_strategy: WaitStrategy) {
+    pin_to_core(core);
+    worker.register();
+
+    let mut waiter = Waiter::new(wait_strategy);
+
+    loop {
+        if let Some(work) = worker.pop() {
+            waiter.reset();
+
+            match work.work_type {
+                // Work on map for token wise
+                WorkType::TokenWise(_) => work_on_map(work),
+                WorkType::McxDepthSnapshot(_) | WorkType::McxDepthIncr(_) => {
+                    work_on_mcx(work, worker)
+                }
+                // Work on queue for other types
+                _ => work_on_queue(work, worker),
+            }
+        } else if DISTRIBUTOR_STOPPED.load(Ordering::SeqCst) {
+            // Work is only pushed back to own queue, so nothing more will arrive
+            break;
+        } else {
+            worker.wait(&mut waiter);
+        }
+    }
+}
+
+// Pushes work to worker owning its shard
+pub fn dispatch(work: Work) {
+    WORKER_QUEUES[shard_of(&work.work_type)].push(work);
+}
+
+fn shard_of(work_type: &WorkType) -> usize {
+    // Fibonacci hashing, so consecutive tokens are spread across workers
+    let hash = work_type.shard_key().wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+
+    hash as usize % WORKER_QUEUES.len()
+}
+
+pub fn work_on_map(work: Work) {
+    // We assume that work.atomic_ptr is not null
+    let atomic_ptr = unsafe { work.atomic_ptr.clone().unwrap_unchecked() };
+
+    let old_packet_ptr = atomic_ptr.swap(ptr::null_mut(), Ordering::SeqCst);
+
+    // Creating box from raw ptr is unsafe, because it could be null
+    // however, we only ensure that this value is not null
+    let mut old_packet = unsafe { Box::from_raw(old_packet_ptr) };
+
+    // Call associated function
+    (work.processing_fn)(&mut *old_packet, &work);
+}
+
+pub fn work_on_mcx(work: Work, worker: &WorkerQueue) {
+    let mcx_state = work
+        .mcx_state
+        .clone()
+        .expect("MCX state required for processing mcx work");
+    let packet_queue = mcx_state.packet_queue;
+    let work_lock = mcx_state.work_lock;
+    let mut incremental_processed = 0;
+
+    while let Some(mut packet) = packet_queue.pop() {
+        let processed = (work.processing_fn)(&mut packet, &work);
+
+        // If packet was not processed, try processing another packet
+        if !processed {
+            continue;
+        }
+
+        incremental_processed += 1;
+
+        if !packet_queue.is_empty() {
+            if worker.is_empty() || incremental_processed <= MAX_INCR_TO_PROCESS {
+                // If no other work for this worker
+                // Or less than max packets have been processed
+                // continue current work
+                continue;
+            } else {
+                // If other tokens are waiting, requeue current work and exit
+                worker.push(work);
+                return;
+            }
+        }
+    }
+
+    // No more work of same type
+    work_lock.store(false, Ordering::SeqCst);
+
+    // Check if queue has more work and we can still acquire lock
+    if !packet_queue.is_empty()
+        && work_lock
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    {
+        worker.push(work);
+    }
+}
+
+pub fn work_on_queue(work: Work, worker: &WorkerQueue) {
+    let packet_queue = &PACKET_QUEUES[work.work_type.get_id()];
+    let work_lock = &WORK_LOCKS[work.work_type.get_id()];
+
+    while let Some(mut packet) = packet_queue.pop() {
+        (work.processing_fn)(&mut packet, &work);
+
+        if !packet_queue.is_empty() {
+            if worker.is_empty() {
+                // If no other work for this worker, continue current work
+                continue;
+            } else {
+                // If other work is waiting, requeue current work and exit
+                worker.push(work);
+                return;
+            }
+        }
+    }
+
+    // No more work of same type
+    work_lock.store(false, Ordering::SeqCst);
+
+    // Check if queue has more work and we can still acquire lock
+    if !packet_queue.is_empty()
+        && work_lock
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    {
+        worker.push(work);
+    }
+}
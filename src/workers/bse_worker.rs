@@ -3,10 +3,11 @@ use std::mem::size_of;
 use crate::{
     constants::{
         ALPHA_CHAR_LEN, BEST_BID_VALUE, BEST_OFFER_VALUE, BSE_BCAST_MBP,
-        COMPLEX_MBP_UNCOMPRESSED_DATA_LEN, DEBT_MBP_UNCOMPRESSED_DATA_LEN, MAX_MARKET_DEPTH_IDX,
-        MBP_UNCOMPRESSED_DATA_LEN, MBP_UNCOMPRESSED_HEADER_LEN, TIMESTAMP_LEN, U16_MAX,
+        COMPLEX_MBP_UNCOMPRESSED_DATA_LEN, DEBT_MBP_UNCOMPRESSED_DATA_LEN, MAX_BSE_MBP_DATA_IDX,
+        MAX_BSE_MBP_DEATIL_IDX, MAX_MARKET_DEPTH_IDX, MBP_UNCOMPRESSED_DATA_LEN,
+        MBP_UNCOMPRESSED_HEADER_LEN, TIMESTAMP_LEN, U16_MAX,
     },
-    global::OUTPUT,
+    global::{OUTPUT, STATISTICS, TOKEN_FILTER},
     types::{
         packet::Packet,
         packet_structures::{
@@ -19,43 +20,92 @@ use crate::{
         work::Work,
     },
     utils::{
-        byte_utils::{bytes_to_partial_struct, bytes_to_struct, create_empty, struct_to_bytes},
+        byte_utils::{
+            bytes_to_partial_struct, bytes_to_struct, create_empty, struct_to_bytes,
+            try_bytes_to_struct, try_slice, ParseError,
+        },
         time_utils::get_epoch_us,
     },
 };
 
 pub fn process_bse_compressed(packet: &mut Packet, _work: &Work) -> bool {
-    let mut trans_code: i32 = bytes_to_struct(&packet.0);
-    // Twiddle
-    trans_code = trans_code.to_be();
+    let Some(trans_code) = get_trans_code(packet) else {
+        return false;
+    };
 
     // Decompress packet according to transcode
-    match trans_code {
+    let result = match trans_code {
         2020 => decompress_bcast_mbp(packet),
         2021 => decompress_bcast_mbp_complex_list(packet),
         2033 => decompress_bcast_debt_mbp(packet),
-        _ => panic!("Invalid transcode {trans_code} for compressed bse packet"),
+        _ => {
+            if STATISTICS.record_unknown_code(trans_code) {
+                println!("Invalid transcode {trans_code} for compressed bse packet");
+            }
+            STATISTICS.get().dropped_count.inc();
+            return false;
+        }
     };
 
+    // Skip rest of a corrupt packet
+    if let Err(e) = result {
+        println!("Malformed bse packet {trans_code}: {e}");
+        STATISTICS.get().decompress_failed_count.inc();
+        STATISTICS.record_malformed();
+        return false;
+    }
+
+    STATISTICS.get().decompressed_count.inc();
+
     true
 }
 
 pub fn process_bse_uncompressed(packet: &mut Packet, _work: &Work) -> bool {
-    let mut trans_code: i32 = bytes_to_struct(&packet.0);
-    // Twiddle
-    trans_code = trans_code.to_be();
+    let Some(trans_code) = get_trans_code(packet) else {
+        return false;
+    };
 
-    let mut bse_struct = build_bse_struct(trans_code as i16, &packet.0);
-    bse_struct.twiddle();
+    // Only received bytes are read, a message shorter than its struct is dropped
+    match build_bse_struct(trans_code as i16, &packet.0[..packet.1]) {
+        Ok(Some(mut bse_struct)) => {
+            bse_struct.twiddle();
 
-    bse_struct.to_bytes(&mut packet.0);
+            bse_struct.to_bytes(&mut packet.0);
+        }
+        // Unknown messages are passed through as received
+        Ok(None) => {
+            if STATISTICS.record_unknown_code(trans_code) {
+                println!("Invalid bse transaction id: {}", trans_code);
+            }
+        }
+        Err(e) => {
+            println!("Malformed bse packet {trans_code}: {e}");
+            STATISTICS.record_malformed();
+            return false;
+        }
+    }
 
     OUTPUT.write(&packet);
 
     true
 }
 
-pub fn decompress_bcast_mbp(packet: &mut Packet) {
+fn get_trans_code(packet: &Packet) -> Option<i32> {
+    match bytes_to_struct::<i32>(&packet.0[..packet.1]) {
+        // Twiddle
+        Ok(trans_code) => Some(trans_code.to_be()),
+        Err(e) => {
+            println!("Malformed bse packet: {e}");
+            STATISTICS.record_malformed();
+            None
+        }
+    }
+}
+
+pub fn decompress_bcast_mbp(packet: &mut Packet) -> Result<(), ParseError> {
+    // Only received bytes are read
+    let buf = &packet.0[..packet.1];
+
     // Load uncompressed header
     let mut bcast_market_picture: BcastMarketPicture = create_empty();
     let mut offset = MBP_UNCOMPRESSED_HEADER_LEN;
@@ -63,80 +113,56 @@ pub fn decompress_bcast_mbp(packet: &mut Packet) {
     let mut sell_count = 0;
 
     // Only cast header
-    bytes_to_partial_struct(&mut bcast_market_picture, &packet.0[..offset]);
+    bytes_to_partial_struct(&mut bcast_market_picture, try_slice(buf, 0, offset)?);
 
     bcast_market_picture.twiddle();
 
+    check_count(
+        "no_of_records",
+        bcast_market_picture.no_of_records,
+        MAX_BSE_MBP_DEATIL_IDX,
+    )?;
+
     for i in 0..bcast_market_picture.no_of_records as usize {
         // Copy uncompressed data
         bytes_to_partial_struct(
             &mut bcast_market_picture.mbp_details[i],
-            &packet.0[offset..offset + MBP_UNCOMPRESSED_DATA_LEN],
+            try_slice(buf, offset, MBP_UNCOMPRESSED_DATA_LEN)?,
         );
         bcast_market_picture.mbp_details[i].twiddle();
 
+        check_count(
+            "no_of_price_points",
+            bcast_market_picture.mbp_details[i].no_of_price_points,
+            MAX_BSE_MBP_DATA_IDX,
+        )?;
+
         offset += MBP_UNCOMPRESSED_DATA_LEN;
 
-        bcast_market_picture.mbp_details[i].open_rate = decompress_field(
-            bcast_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].prev_close_rate = decompress_field(
-            bcast_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].high_rate = decompress_field(
-            bcast_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].low_rate = decompress_field(
-            bcast_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].block_deal_ref_rate = decompress_field(
-            bcast_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].indicative_equilibrium_price = decompress_field(
-            bcast_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].indicative_equilibrium_qty = decompress_field(
-            bcast_market_picture.mbp_details[i].ltq,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].total_bid_qty = decompress_field(
-            bcast_market_picture.mbp_details[i].ltq,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].total_offer_qty = decompress_field(
-            bcast_market_picture.mbp_details[i].ltq,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].lower_price_band = decompress_field(
-            bcast_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].upper_price_band = decompress_field(
-            bcast_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        bcast_market_picture.mbp_details[i].weighted_avg_price = decompress_field(
-            bcast_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
+        bcast_market_picture.mbp_details[i].open_rate =
+            decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].prev_close_rate =
+            decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].high_rate =
+            decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].low_rate =
+            decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].block_deal_ref_rate =
+            decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].indicative_equilibrium_price =
+            decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].indicative_equilibrium_qty =
+            decompress_field(bcast_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].total_bid_qty =
+            decompress_field(bcast_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].total_offer_qty =
+            decompress_field(bcast_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].lower_price_band =
+            decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].upper_price_band =
+            decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        bcast_market_picture.mbp_details[i].weighted_avg_price =
+            decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
 
         // For buy
         for count in 0..bcast_market_picture.mbp_details[i].no_of_price_points as usize {
@@ -144,11 +170,7 @@ pub fn decompress_bcast_mbp(packet: &mut Packet) {
 
             if count == 0 {
                 bcast_market_picture.mbp_details[i].mbp_data[count].best_bid_rate =
-                    decompress_field(
-                        bcast_market_picture.mbp_details[i].ltp,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
 
                 // This is last packet
                 if bcast_market_picture.mbp_details[i].mbp_data[count].best_bid_rate
@@ -158,30 +180,18 @@ pub fn decompress_bcast_mbp(packet: &mut Packet) {
                     break;
                 }
                 bcast_market_picture.mbp_details[i].mbp_data[count].total_bid_qty =
-                    decompress_field(
-                        bcast_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(bcast_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 bcast_market_picture.mbp_details[i].mbp_data[count].no_of_bid_at_price_point =
-                    decompress_field(
-                        bcast_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(bcast_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 bcast_market_picture.mbp_details[i].mbp_data[count].implied_buy_qty =
-                    decompress_field(
-                        bcast_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(bcast_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
             } else {
                 bcast_market_picture.mbp_details[i].mbp_data[count].best_bid_rate =
                     decompress_field(
                         bcast_market_picture.mbp_details[i].mbp_data[count - 1].best_bid_rate,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
 
                 // This is last packet
                 if bcast_market_picture.mbp_details[i].mbp_data[count].best_bid_rate
@@ -193,22 +203,22 @@ pub fn decompress_bcast_mbp(packet: &mut Packet) {
                 bcast_market_picture.mbp_details[i].mbp_data[count].total_bid_qty =
                     decompress_field(
                         bcast_market_picture.mbp_details[i].mbp_data[count - 1].total_bid_qty,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 bcast_market_picture.mbp_details[i].mbp_data[count].no_of_bid_at_price_point =
                     decompress_field(
                         bcast_market_picture.mbp_details[i].mbp_data[count - 1]
                             .no_of_bid_at_price_point,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 bcast_market_picture.mbp_details[i].mbp_data[count].implied_buy_qty =
                     decompress_field(
                         bcast_market_picture.mbp_details[i].mbp_data[count - 1].implied_buy_qty,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
             }
         } // Buy loop end
 
@@ -218,11 +228,7 @@ pub fn decompress_bcast_mbp(packet: &mut Packet) {
 
             if count == 0 {
                 bcast_market_picture.mbp_details[i].mbp_data[count].best_offer_rate =
-                    decompress_field(
-                        bcast_market_picture.mbp_details[i].ltp,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(bcast_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
 
                 // This is last packet
                 if bcast_market_picture.mbp_details[i].mbp_data[count].best_offer_rate
@@ -232,30 +238,18 @@ pub fn decompress_bcast_mbp(packet: &mut Packet) {
                     break;
                 }
                 bcast_market_picture.mbp_details[i].mbp_data[count].total_offer_qty =
-                    decompress_field(
-                        bcast_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(bcast_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 bcast_market_picture.mbp_details[i].mbp_data[count].no_of_offer_at_price_point =
-                    decompress_field(
-                        bcast_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(bcast_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 bcast_market_picture.mbp_details[i].mbp_data[count].implied_sell_qty =
-                    decompress_field(
-                        bcast_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(bcast_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
             } else {
                 bcast_market_picture.mbp_details[i].mbp_data[count].best_offer_rate =
                     decompress_field(
                         bcast_market_picture.mbp_details[i].mbp_data[count - 1].best_offer_rate,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
 
                 // This is last packet
                 if bcast_market_picture.mbp_details[i].mbp_data[count].best_offer_rate
@@ -267,27 +261,33 @@ pub fn decompress_bcast_mbp(packet: &mut Packet) {
                 bcast_market_picture.mbp_details[i].mbp_data[count].total_offer_qty =
                     decompress_field(
                         bcast_market_picture.mbp_details[i].mbp_data[count - 1].total_offer_qty,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 bcast_market_picture.mbp_details[i].mbp_data[count].no_of_offer_at_price_point =
                     decompress_field(
                         bcast_market_picture.mbp_details[i].mbp_data[count - 1]
                             .no_of_offer_at_price_point,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 bcast_market_picture.mbp_details[i].mbp_data[count].implied_sell_qty =
                     decompress_field(
                         bcast_market_picture.mbp_details[i].mbp_data[count - 1].implied_sell_qty,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
             }
         } // sell loop end
     }
 
     for i in 0..bcast_market_picture.no_of_records {
+        // Records are compressed, so tokens can only be filtered after decompression
+        if !TOKEN_FILTER.contains(bcast_market_picture.mbp_details[i as usize].instrument as i64) {
+            STATISTICS.get().unsubscribed_packets_count.inc();
+            continue;
+        }
+
         let mut bcast_market_picture = bcast_market_picture.clone();
         bcast_market_picture.no_of_records = i;
 
@@ -298,98 +298,75 @@ pub fn decompress_bcast_mbp(packet: &mut Packet) {
 
         OUTPUT.write(&packet);
     }
+
+    Ok(())
 }
 
-pub fn decompress_bcast_mbp_complex_list(packet: &mut Packet) {
+pub fn decompress_bcast_mbp_complex_list(packet: &mut Packet) -> Result<(), ParseError> {
+    // Only received bytes are read
+    let buf = &packet.0[..packet.1];
+
     // Load uncompressed header
     let mut complex_market_picture: BcastComplexMarketPicture = create_empty();
     let mut offset = MBP_UNCOMPRESSED_HEADER_LEN;
 
     // Only cast header
-    bytes_to_partial_struct(&mut complex_market_picture, &packet.0[..offset]);
+    bytes_to_partial_struct(&mut complex_market_picture, try_slice(buf, 0, offset)?);
 
     complex_market_picture.twiddle();
 
+    check_count(
+        "no_of_records",
+        complex_market_picture.no_of_records,
+        MAX_BSE_MBP_DEATIL_IDX,
+    )?;
+
     for i in 0..complex_market_picture.no_of_records as usize {
         // Copy uncompressed data
         bytes_to_partial_struct(
             &mut complex_market_picture.mbp_details[i],
-            &packet.0[offset..offset + COMPLEX_MBP_UNCOMPRESSED_DATA_LEN],
+            try_slice(buf, offset, COMPLEX_MBP_UNCOMPRESSED_DATA_LEN)?,
         );
         complex_market_picture.mbp_details[i].twiddle();
 
+        check_count(
+            "no_of_price_points",
+            complex_market_picture.mbp_details[i].no_of_price_points,
+            MAX_BSE_MBP_DATA_IDX,
+        )?;
+
         offset += COMPLEX_MBP_UNCOMPRESSED_DATA_LEN;
 
-        complex_market_picture.mbp_details[i].open_rate = decompress_field(
-            complex_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].prev_close_rate = decompress_field(
-            complex_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].high_rate = decompress_field(
-            complex_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].low_rate = decompress_field(
-            complex_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].block_deal_ref_rate = decompress_field(
-            complex_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].indicative_equilibrium_price = decompress_field(
-            complex_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].indicative_equilibrium_qty = decompress_field(
-            complex_market_picture.mbp_details[i].ltq,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].total_bid_qty = decompress_field(
-            complex_market_picture.mbp_details[i].ltq,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].total_offer_qty = decompress_field(
-            complex_market_picture.mbp_details[i].ltq,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].lower_price_band = decompress_field(
-            complex_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].upper_price_band = decompress_field(
-            complex_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        complex_market_picture.mbp_details[i].weighted_avg_price = decompress_field(
-            complex_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
+        complex_market_picture.mbp_details[i].open_rate =
+            decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].prev_close_rate =
+            decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].high_rate =
+            decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].low_rate =
+            decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].block_deal_ref_rate =
+            decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].indicative_equilibrium_price =
+            decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].indicative_equilibrium_qty =
+            decompress_field(complex_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].total_bid_qty =
+            decompress_field(complex_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].total_offer_qty =
+            decompress_field(complex_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].lower_price_band =
+            decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].upper_price_band =
+            decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        complex_market_picture.mbp_details[i].weighted_avg_price =
+            decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
 
         // For buy
         for count in 0..complex_market_picture.mbp_details[i].no_of_price_points as usize {
             if count == 0 {
                 complex_market_picture.mbp_details[i].mbp_data[count].best_bid_rate =
-                    decompress_field(
-                        complex_market_picture.mbp_details[i].ltp,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
 
                 // This is last packet
                 if complex_market_picture.mbp_details[i].mbp_data[count].best_bid_rate
@@ -399,30 +376,18 @@ pub fn decompress_bcast_mbp_complex_list(packet: &mut Packet) {
                     break;
                 }
                 complex_market_picture.mbp_details[i].mbp_data[count].total_bid_qty =
-                    decompress_field(
-                        complex_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(complex_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 complex_market_picture.mbp_details[i].mbp_data[count].no_of_bid_at_price_point =
-                    decompress_field(
-                        complex_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(complex_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 complex_market_picture.mbp_details[i].mbp_data[count].implied_buy_qty =
-                    decompress_field(
-                        complex_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(complex_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
             } else {
                 complex_market_picture.mbp_details[i].mbp_data[count].best_bid_rate =
                     decompress_field(
                         complex_market_picture.mbp_details[i].mbp_data[count - 1].best_bid_rate,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
 
                 // This is last packet
                 if complex_market_picture.mbp_details[i].mbp_data[count].best_bid_rate
@@ -434,22 +399,22 @@ pub fn decompress_bcast_mbp_complex_list(packet: &mut Packet) {
                 complex_market_picture.mbp_details[i].mbp_data[count].total_bid_qty =
                     decompress_field(
                         complex_market_picture.mbp_details[i].mbp_data[count - 1].total_bid_qty,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 complex_market_picture.mbp_details[i].mbp_data[count].no_of_bid_at_price_point =
                     decompress_field(
                         complex_market_picture.mbp_details[i].mbp_data[count - 1]
                             .no_of_bid_at_price_point,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 complex_market_picture.mbp_details[i].mbp_data[count].implied_buy_qty =
                     decompress_field(
                         complex_market_picture.mbp_details[i].mbp_data[count - 1].implied_buy_qty,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
             }
         } // Buy loop end
 
@@ -457,11 +422,7 @@ pub fn decompress_bcast_mbp_complex_list(packet: &mut Packet) {
         for count in 0..complex_market_picture.mbp_details[i].no_of_price_points as usize {
             if count == 0 {
                 complex_market_picture.mbp_details[i].mbp_data[count].best_offer_rate =
-                    decompress_field(
-                        complex_market_picture.mbp_details[i].ltp,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(complex_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
 
                 // This is last packet
                 if complex_market_picture.mbp_details[i].mbp_data[count].best_offer_rate
@@ -471,30 +432,18 @@ pub fn decompress_bcast_mbp_complex_list(packet: &mut Packet) {
                     break;
                 }
                 complex_market_picture.mbp_details[i].mbp_data[count].total_offer_qty =
-                    decompress_field(
-                        complex_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(complex_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 complex_market_picture.mbp_details[i].mbp_data[count].no_of_offer_at_price_point =
-                    decompress_field(
-                        complex_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(complex_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 complex_market_picture.mbp_details[i].mbp_data[count].implied_sell_qty =
-                    decompress_field(
-                        complex_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(complex_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
             } else {
                 complex_market_picture.mbp_details[i].mbp_data[count].best_offer_rate =
                     decompress_field(
                         complex_market_picture.mbp_details[i].mbp_data[count - 1].best_offer_rate,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
 
                 // This is last packet
                 if complex_market_picture.mbp_details[i].mbp_data[count].best_offer_rate
@@ -506,27 +455,33 @@ pub fn decompress_bcast_mbp_complex_list(packet: &mut Packet) {
                 complex_market_picture.mbp_details[i].mbp_data[count].total_offer_qty =
                     decompress_field(
                         complex_market_picture.mbp_details[i].mbp_data[count - 1].total_offer_qty,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 complex_market_picture.mbp_details[i].mbp_data[count].no_of_offer_at_price_point =
                     decompress_field(
                         complex_market_picture.mbp_details[i].mbp_data[count - 1]
                             .no_of_offer_at_price_point,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 complex_market_picture.mbp_details[i].mbp_data[count].implied_sell_qty =
                     decompress_field(
                         complex_market_picture.mbp_details[i].mbp_data[count - 1].implied_sell_qty,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
             }
         } // sell loop end
     }
 
     for i in 0..complex_market_picture.no_of_records {
+        // Records are compressed, so tokens can only be filtered after decompression
+        if !TOKEN_FILTER.contains(complex_market_picture.mbp_details[i as usize].contract_code) {
+            STATISTICS.get().unsubscribed_packets_count.inc();
+            continue;
+        }
+
         let mut complex_market_picture = complex_market_picture.clone();
         complex_market_picture.no_of_records = i;
 
@@ -534,97 +489,75 @@ pub fn decompress_bcast_mbp_complex_list(packet: &mut Packet) {
 
         OUTPUT.write(&packet);
     }
+
+    Ok(())
 }
 
-pub fn decompress_bcast_debt_mbp(packet: &mut Packet) {
+pub fn decompress_bcast_debt_mbp(packet: &mut Packet) -> Result<(), ParseError> {
+    // Only received bytes are read
+    let buf = &packet.0[..packet.1];
+
     // Load uncompressed header
     let mut debt_market_picture: BcastDebtMarketPicture = create_empty();
     let mut offset = MBP_UNCOMPRESSED_HEADER_LEN;
 
     // Only cast header
-    bytes_to_partial_struct(&mut debt_market_picture, &packet.0[..offset]);
+    bytes_to_partial_struct(&mut debt_market_picture, try_slice(buf, 0, offset)?);
 
     debt_market_picture.twiddle();
 
+    check_count(
+        "no_of_records",
+        debt_market_picture.no_of_records,
+        MAX_BSE_MBP_DEATIL_IDX,
+    )?;
+
     for i in 0..debt_market_picture.no_of_records as usize {
         // Copy uncompressed data
         bytes_to_partial_struct(
             &mut debt_market_picture.mbp_details[i],
-            &packet.0[offset..offset + DEBT_MBP_UNCOMPRESSED_DATA_LEN],
+            try_slice(buf, offset, DEBT_MBP_UNCOMPRESSED_DATA_LEN)?,
         );
         debt_market_picture.mbp_details[i].twiddle();
 
+        check_count(
+            "no_of_price_points",
+            debt_market_picture.mbp_details[i].no_of_price_points,
+            MAX_BSE_MBP_DATA_IDX,
+        )?;
+
         offset += DEBT_MBP_UNCOMPRESSED_DATA_LEN;
 
-        debt_market_picture.mbp_details[i].open_rate = decompress_field(
-            debt_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].prev_close_rate = decompress_field(
-            debt_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].high_rate = decompress_field(
-            debt_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].low_rate = decompress_field(
-            debt_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].reserved11 = decompress_field(
-            debt_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].indicative_equilibrium_price = decompress_field(
-            debt_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].indicative_equilibrium_qty = decompress_field(
-            debt_market_picture.mbp_details[i].ltq,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].total_bid_qty = decompress_field(
-            debt_market_picture.mbp_details[i].ltq,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].total_offer_qty = decompress_field(
-            debt_market_picture.mbp_details[i].ltq,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].lower_price_band = decompress_field(
-            debt_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].upper_price_band = decompress_field(
-            debt_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
-        debt_market_picture.mbp_details[i].weighted_avg_price = decompress_field(
-            debt_market_picture.mbp_details[i].ltp,
-            &packet.0,
-            &mut offset,
-        );
+        debt_market_picture.mbp_details[i].open_rate =
+            decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].prev_close_rate =
+            decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].high_rate =
+            decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].low_rate =
+            decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].reserved11 =
+            decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].indicative_equilibrium_price =
+            decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].indicative_equilibrium_qty =
+            decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].total_bid_qty =
+            decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].total_offer_qty =
+            decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].lower_price_band =
+            decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].upper_price_band =
+            decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
+        debt_market_picture.mbp_details[i].weighted_avg_price =
+            decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
 
         // For buy
         for count in 0..debt_market_picture.mbp_details[i].no_of_price_points as usize {
             if count == 0 {
-                debt_market_picture.mbp_details[i].mbp_data[count].best_bid_rate = decompress_field(
-                    debt_market_picture.mbp_details[i].ltp,
-                    &packet.0,
-                    &mut offset,
-                );
+                debt_market_picture.mbp_details[i].mbp_data[count].best_bid_rate =
+                    decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
 
                 // This is last packet
                 if debt_market_picture.mbp_details[i].mbp_data[count].best_bid_rate
@@ -633,43 +566,25 @@ pub fn decompress_bcast_debt_mbp(packet: &mut Packet) {
                     debt_market_picture.mbp_details[i].mbp_data[count].best_bid_rate = 0;
                     break;
                 }
-                debt_market_picture.mbp_details[i].mbp_data[count].total_bid_qty = decompress_field(
-                    debt_market_picture.mbp_details[i].ltq,
-                    &packet.0,
-                    &mut offset,
-                );
-                debt_market_picture.mbp_details[i].mbp_data[count].buy_ytm = decompress_field(
-                    debt_market_picture.mbp_details[i].ltq,
-                    &packet.0,
-                    &mut offset,
-                );
-                debt_market_picture.mbp_details[i].mbp_data[count].buy_ytp = decompress_field(
-                    debt_market_picture.mbp_details[i].ltq,
-                    &packet.0,
-                    &mut offset,
-                );
-                debt_market_picture.mbp_details[i].mbp_data[count].buy_ytc = decompress_field(
-                    debt_market_picture.mbp_details[i].ltq,
-                    &packet.0,
-                    &mut offset,
-                );
+                debt_market_picture.mbp_details[i].mbp_data[count].total_bid_qty =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+                debt_market_picture.mbp_details[i].mbp_data[count].buy_ytm =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+                debt_market_picture.mbp_details[i].mbp_data[count].buy_ytp =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+                debt_market_picture.mbp_details[i].mbp_data[count].buy_ytc =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 debt_market_picture.mbp_details[i].mbp_data[count].no_of_bid_at_price_point =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+                debt_market_picture.mbp_details[i].mbp_data[count].filler1 =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+            } else {
+                debt_market_picture.mbp_details[i].mbp_data[count].best_bid_rate =
                     decompress_field(
-                        debt_market_picture.mbp_details[i].ltq,
-                        &packet.0,
+                        debt_market_picture.mbp_details[i].mbp_data[count - 1].best_bid_rate,
+                        buf,
                         &mut offset,
-                    );
-                debt_market_picture.mbp_details[i].mbp_data[count].filler1 = decompress_field(
-                    debt_market_picture.mbp_details[i].ltq,
-                    &packet.0,
-                    &mut offset,
-                );
-            } else {
-                debt_market_picture.mbp_details[i].mbp_data[count].best_bid_rate = decompress_field(
-                    debt_market_picture.mbp_details[i].mbp_data[count - 1].best_bid_rate,
-                    &packet.0,
-                    &mut offset,
-                );
+                    )?;
 
                 // This is last packet
                 if debt_market_picture.mbp_details[i].mbp_data[count].best_bid_rate
@@ -678,38 +593,39 @@ pub fn decompress_bcast_debt_mbp(packet: &mut Packet) {
                     debt_market_picture.mbp_details[i].mbp_data[count].best_bid_rate = 0;
                     break;
                 }
-                debt_market_picture.mbp_details[i].mbp_data[count].total_bid_qty = decompress_field(
-                    debt_market_picture.mbp_details[i].mbp_data[count - 1].total_bid_qty,
-                    &packet.0,
-                    &mut offset,
-                );
+                debt_market_picture.mbp_details[i].mbp_data[count].total_bid_qty =
+                    decompress_field(
+                        debt_market_picture.mbp_details[i].mbp_data[count - 1].total_bid_qty,
+                        buf,
+                        &mut offset,
+                    )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].buy_ytm = decompress_field(
                     debt_market_picture.mbp_details[i].mbp_data[count - 1].buy_ytm,
-                    &packet.0,
+                    buf,
                     &mut offset,
-                );
+                )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].buy_ytp = decompress_field(
                     debt_market_picture.mbp_details[i].mbp_data[count - 1].buy_ytp,
-                    &packet.0,
+                    buf,
                     &mut offset,
-                );
+                )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].buy_ytc = decompress_field(
                     debt_market_picture.mbp_details[i].mbp_data[count - 1].buy_ytc,
-                    &packet.0,
+                    buf,
                     &mut offset,
-                );
+                )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].no_of_bid_at_price_point =
                     decompress_field(
                         debt_market_picture.mbp_details[i].mbp_data[count - 1]
                             .no_of_bid_at_price_point,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].filler1 = decompress_field(
                     debt_market_picture.mbp_details[i].mbp_data[count - 1].filler1,
-                    &packet.0,
+                    buf,
                     &mut offset,
-                );
+                )?;
             }
         } // Buy loop end
 
@@ -717,11 +633,7 @@ pub fn decompress_bcast_debt_mbp(packet: &mut Packet) {
         for count in 0..debt_market_picture.mbp_details[i].no_of_price_points as usize {
             if count == 0 {
                 debt_market_picture.mbp_details[i].mbp_data[count].best_offer_rate =
-                    decompress_field(
-                        debt_market_picture.mbp_details[i].ltp,
-                        &packet.0,
-                        &mut offset,
-                    );
+                    decompress_field(debt_market_picture.mbp_details[i].ltp, buf, &mut offset)?;
 
                 // This is last packet
                 if debt_market_picture.mbp_details[i].mbp_data[count].best_offer_rate
@@ -731,44 +643,24 @@ pub fn decompress_bcast_debt_mbp(packet: &mut Packet) {
                     break;
                 }
                 debt_market_picture.mbp_details[i].mbp_data[count].total_offer_qty =
-                    decompress_field(
-                        debt_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
-                debt_market_picture.mbp_details[i].mbp_data[count].sell_ytm = decompress_field(
-                    debt_market_picture.mbp_details[i].ltq,
-                    &packet.0,
-                    &mut offset,
-                );
-                debt_market_picture.mbp_details[i].mbp_data[count].sell_ytp = decompress_field(
-                    debt_market_picture.mbp_details[i].ltq,
-                    &packet.0,
-                    &mut offset,
-                );
-                debt_market_picture.mbp_details[i].mbp_data[count].sell_ytc = decompress_field(
-                    debt_market_picture.mbp_details[i].ltq,
-                    &packet.0,
-                    &mut offset,
-                );
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+                debt_market_picture.mbp_details[i].mbp_data[count].sell_ytm =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+                debt_market_picture.mbp_details[i].mbp_data[count].sell_ytp =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+                debt_market_picture.mbp_details[i].mbp_data[count].sell_ytc =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
                 debt_market_picture.mbp_details[i].mbp_data[count].no_of_offer_at_price_point =
-                    decompress_field(
-                        debt_market_picture.mbp_details[i].ltq,
-                        &packet.0,
-                        &mut offset,
-                    );
-                debt_market_picture.mbp_details[i].mbp_data[count].filler2 = decompress_field(
-                    debt_market_picture.mbp_details[i].ltq,
-                    &packet.0,
-                    &mut offset,
-                );
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
+                debt_market_picture.mbp_details[i].mbp_data[count].filler2 =
+                    decompress_field(debt_market_picture.mbp_details[i].ltq, buf, &mut offset)?;
             } else {
                 debt_market_picture.mbp_details[i].mbp_data[count].best_offer_rate =
                     decompress_field(
                         debt_market_picture.mbp_details[i].mbp_data[count - 1].best_offer_rate,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
 
                 // This is last packet
                 if debt_market_picture.mbp_details[i].mbp_data[count].best_offer_rate
@@ -780,41 +672,47 @@ pub fn decompress_bcast_debt_mbp(packet: &mut Packet) {
                 debt_market_picture.mbp_details[i].mbp_data[count].total_offer_qty =
                     decompress_field(
                         debt_market_picture.mbp_details[i].mbp_data[count - 1].total_offer_qty,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].sell_ytm = decompress_field(
                     debt_market_picture.mbp_details[i].mbp_data[count - 1].sell_ytm,
-                    &packet.0,
+                    buf,
                     &mut offset,
-                );
+                )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].sell_ytp = decompress_field(
                     debt_market_picture.mbp_details[i].mbp_data[count - 1].sell_ytp,
-                    &packet.0,
+                    buf,
                     &mut offset,
-                );
+                )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].sell_ytc = decompress_field(
                     debt_market_picture.mbp_details[i].mbp_data[count - 1].sell_ytc,
-                    &packet.0,
+                    buf,
                     &mut offset,
-                );
+                )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].no_of_offer_at_price_point =
                     decompress_field(
                         debt_market_picture.mbp_details[i].mbp_data[count - 1]
                             .no_of_offer_at_price_point,
-                        &packet.0,
+                        buf,
                         &mut offset,
-                    );
+                    )?;
                 debt_market_picture.mbp_details[i].mbp_data[count].filler2 = decompress_field(
                     debt_market_picture.mbp_details[i].mbp_data[count - 1].filler2,
-                    &packet.0,
+                    buf,
                     &mut offset,
-                );
+                )?;
             }
         } // sell loop end
     }
 
     for i in 0..debt_market_picture.no_of_records {
+        // Records are compressed, so tokens can only be filtered after decompression
+        if !TOKEN_FILTER.contains(debt_market_picture.mbp_details[i as usize].instrument as i64) {
+            STATISTICS.get().unsubscribed_packets_count.inc();
+            continue;
+        }
+
         let mut debt_market_picture = debt_market_picture.clone();
         debt_market_picture.no_of_records = i;
 
@@ -822,22 +720,28 @@ pub fn decompress_bcast_debt_mbp(packet: &mut Packet) {
 
         OUTPUT.write(&packet);
     }
+
+    Ok(())
 }
 
-pub fn decompress_field(base_value: i32, buf: &[u8], offset: &mut usize) -> i32 {
+pub fn decompress_field(
+    base_value: i32,
+    buf: &[u8],
+    offset: &mut usize,
+) -> Result<i32, ParseError> {
     let final_value: i32;
-    let mut stop_bit_value: i16 = bytes_to_struct(&buf[*offset..*offset + size_of::<i16>()]);
+    let mut stop_bit_value: i16 = try_bytes_to_struct(buf, *offset)?;
     // Twiddle
     stop_bit_value = stop_bit_value.to_be();
 
     *offset += size_of::<i16>();
 
-    match stop_bit_value {
+    let value = match stop_bit_value {
         // This is last field
         BEST_BID_VALUE | BEST_OFFER_VALUE => stop_bit_value as i32,
         // Bytes exceed, read next 4 bytes
         U16_MAX => {
-            final_value = bytes_to_struct(&buf[*offset..*offset + size_of::<i32>()]);
+            final_value = try_bytes_to_struct(buf, *offset)?;
 
             *offset += size_of::<i32>();
 
@@ -846,7 +750,22 @@ pub fn decompress_field(base_value: i32, buf: &[u8], offset: &mut usize) -> i32
         }
         // Add stop bit value to base value
         _ => stop_bit_value as i32 + base_value,
+    };
+
+    Ok(value)
+}
+
+// Counts from wire must fit in fixed size arrays
+fn check_count(field: &'static str, count: i16, max: usize) -> Result<(), ParseError> {
+    if count < 0 || count as usize > max {
+        return Err(ParseError::InvalidCount {
+            field,
+            count: count as i64,
+            max,
+        });
     }
+
+    Ok(())
 }
 
 fn bcast_mbp_to_market_picture(
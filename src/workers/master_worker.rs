@@ -0,0 +1,237 @@
+use std::mem::size_of;
+
+use crate::{
+    constants::{ALPHA_CHAR_LEN, MASTER_CHANGE_CODE, TIMESTAMP_LEN},
+    global::{OUTPUT, SECURITY_MASTER, STATISTICS},
+    output::enrich::{to_fixed, to_tag_instrument},
+    settings,
+    types::{
+        packet::Packet,
+        packet_structures::{
+            depth_output::TagMessageHeader,
+            master_change::TagMasterChange,
+            ncd::NcdBroadcastTransactionMapping,
+            neq::{self, NeqBroadcastTransactionMapping},
+            nfo::{self, NfoBroadcastTransactionMapping},
+        },
+        security_master::{
+            nse_expiry_to_date, Instrument, InstrumentType, MasterAction, MasterKind, Participant,
+            Spread,
+        },
+    },
+    utils::{
+        byte_utils::{bytes_to_string, create_empty, struct_to_bytes},
+        price_utils::instrument_price_multiplier,
+        time_utils::get_epoch_us,
+    },
+};
+
+// Set in delete flag of master broadcasts for removed entries
+const DELETED: u8 = b'Y';
+
+// Applies master change broadcasts to security master
+// Structs are expected to be twiddled
+pub fn apply_neq(neq_struct: &NeqBroadcastTransactionMapping) {
+    match neq_struct {
+        NeqBroadcastTransactionMapping::BcastSecurityMstrChg(s) => apply_neq_security(s),
+        NeqBroadcastTransactionMapping::BcastPartMstrChg(s) => apply_participant(
+            &{ s.participant_id },
+            &{ s.participant_name },
+            s.participant_status,
+            s.delete_flag,
+        ),
+        _ => {}
+    }
+}
+
+pub fn apply_nfo(nfo_struct: &NfoBroadcastTransactionMapping) {
+    match nfo_struct {
+        NfoBroadcastTransactionMapping::BcastSecurityMstrChg(s)
+        | NfoBroadcastTransactionMapping::BcastSecMstrChngPeriodic(s) => apply_security(s),
+        NfoBroadcastTransactionMapping::BcastPartMstrChg(s) => apply_nfo_participant(s),
+        NfoBroadcastTransactionMapping::BcastInstrMstrChg(s) => apply_instrument_type(s),
+        NfoBroadcastTransactionMapping::BcastSpdMstrChgPeriodic(s) => apply_spread(s),
+        _ => {}
+    }
+}
+
+// Same structures as nfo
+pub fn apply_ncd(ncd_struct: &NcdBroadcastTransactionMapping) {
+    match ncd_struct {
+        NcdBroadcastTransactionMapping::BcastSecurityMstrChg(s)
+        | NcdBroadcastTransactionMapping::BcastSecMstrChngPeriodic(s) => apply_security(s),
+        NcdBroadcastTransactionMapping::BcastPartMstrChg(s) => apply_nfo_participant(s),
+        NcdBroadcastTransactionMapping::BcastInstrMstrChg(s) => apply_instrument_type(s),
+        NcdBroadcastTransactionMapping::BcastSpdMstrChgPeriodic(s) => apply_spread(s),
+        _ => {}
+    }
+}
+
+fn apply_neq_security(s: &neq::BcastSecurityMasterUpdateInfo) {
+    let ec_info = s.ec_info;
+
+    let instrument = Instrument {
+        token: s.token as i64,
+        symbol: bytes_to_string(&ec_info.symbol),
+        series: bytes_to_string(&ec_info.series),
+        expiry: 0,
+        strike_price: 0,
+        option_type: String::new(),
+        lot_size: s.board_lot_quantity,
+        tick_size: s.tick_size,
+        price_multiplier: contract_price_multiplier(s.token as i64, ""),
+    };
+
+    publish_instrument(instrument, s.delete_flag == DELETED);
+}
+
+fn apply_security(s: &nfo::BcastSecurityUpdateInfo) {
+    let ec_info = s.ec_info;
+
+    // Futures have XX as option type
+    let option_type = match &ec_info.option_type {
+        b"CE" | b"PE" | b"CA" | b"PA" => bytes_to_string(&ec_info.option_type),
+        _ => String::new(),
+    };
+
+    let instrument = Instrument {
+        token: s.token as i64,
+        symbol: bytes_to_string(&ec_info.symbol),
+        series: bytes_to_string(&ec_info.series),
+        expiry: nse_expiry_to_date(ec_info.expiry_date as i64),
+        strike_price: (ec_info.trike_price as i64).max(0),
+        option_type,
+        lot_size: s.board_lot_quantity,
+        tick_size: s.tick_size,
+        price_multiplier: contract_price_multiplier(
+            s.token as i64,
+            &bytes_to_string(&ec_info.instrument_name),
+        ),
+    };
+
+    publish_instrument(instrument, s.delete_flag == DELETED);
+}
+
+// Broadcasts have no decimal locator, so multiplier of contract master is kept for known tokens
+fn contract_price_multiplier(token: i64, instrument_name: &str) -> i64 {
+    let settings = settings::get();
+
+    SECURITY_MASTER
+        .get(token)
+        .map(|instrument| instrument.price_multiplier)
+        .unwrap_or_else(|| {
+            instrument_price_multiplier(
+                settings.exchange,
+                instrument_name,
+                None,
+                &settings.price_decimals,
+            )
+        })
+}
+
+fn publish_instrument(instrument: Instrument, delete: bool) {
+    let mut change = master_change(MasterKind::Security);
+    change.id = instrument.token;
+    change.instrument = to_tag_instrument(&instrument);
+
+    publish(SECURITY_MASTER.apply_instrument(instrument, delete), change);
+}
+
+fn apply_nfo_participant(s: &nfo::BcastParticipantUpdateInfo) {
+    apply_participant(
+        &{ s.participant_id },
+        &{ s.participant_name },
+        s.participant_status,
+        s.delete_flag,
+    );
+}
+
+fn apply_participant(id: &[u8], name: &[u8], status: u8, delete_flag: u8) {
+    let participant = Participant {
+        id: bytes_to_string(id),
+        name: bytes_to_string(name),
+        status,
+    };
+
+    let mut change = master_change(MasterKind::Participant);
+    change.code = to_fixed(&participant.id);
+    change.name = to_fixed(&participant.name);
+    change.status = status;
+
+    publish(
+        SECURITY_MASTER.apply_participant(participant, delete_flag == DELETED),
+        change,
+    );
+}
+
+fn apply_instrument_type(s: &nfo::BcastInstrumentUpdateInfo) {
+    let instrument_type = InstrumentType {
+        id: s.instrument_id,
+        name: bytes_to_string(&{ s.instrument_name }),
+        description: bytes_to_string(&{ s.instrument_description }),
+    };
+
+    let mut change = master_change(MasterKind::InstrumentType);
+    change.id = instrument_type.id as i64;
+    change.code = to_fixed(&instrument_type.name);
+    change.name = to_fixed(&instrument_type.description);
+
+    publish(
+        SECURITY_MASTER.apply_instrument_type(instrument_type, s.delete_flag == DELETED),
+        change,
+    );
+}
+
+fn apply_spread(s: &nfo::BcastSpreadUpdateInfo) {
+    let spread = Spread {
+        token1: s.token1 as i64,
+        token2: s.token2 as i64,
+        reference_price: s.reference_price,
+    };
+
+    let mut change = master_change(MasterKind::Spread);
+    change.id = spread.token1;
+    change.id2 = spread.token2;
+
+    publish(
+        SECURITY_MASTER.apply_spread(spread, s.delete_flag == DELETED),
+        change,
+    );
+}
+
+fn master_change(master: MasterKind) -> TagMasterChange {
+    let mut change: TagMasterChange = create_empty();
+
+    change.msg_header = TagMessageHeader {
+        message_code: MASTER_CHANGE_CODE,
+        transaction_type: 0,
+        log_time: 0,
+        alpha_char: [0; ALPHA_CHAR_LEN],
+        trader_id: 0,
+        error_code: 0,
+        timestamp: get_epoch_us() as u64,
+        timestamp1: [0; TIMESTAMP_LEN],
+        timestamp2: [0; TIMESTAMP_LEN],
+        message_length: size_of::<TagMasterChange>() as i16,
+    };
+    change.master = master as i16;
+
+    change
+}
+
+// Writes event only if entry was changed
+fn publish(action: Option<MasterAction>, mut change: TagMasterChange) {
+    let Some(action) = action else {
+        return;
+    };
+
+    change.action = action as i16;
+
+    STATISTICS.get().master_changes_count.inc();
+
+    let mut packet = Packet::new(size_of::<TagMasterChange>());
+    packet.1 = struct_to_bytes(&change, &mut packet.0);
+    packet.2 = change.msg_header.timestamp;
+
+    OUTPUT.write(&packet);
+}
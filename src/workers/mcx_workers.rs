@@ -1,29 +1,38 @@
-use std::{
-    ptr::{self, drop_in_place},
-    sync::atomic::Ordering,
-    u32,
-};
+use std::{ptr, sync::atomic::Ordering, u32};
 
 use crate::{
-    constants::{ALPHA_CHAR_LEN, MAX_MARKET_DEPTH_IDX, SNAPSHOT_TEMPLATE_ID, TIMESTAMP_LEN},
-    global::OUTPUT,
+    constants::{
+        ALPHA_CHAR_LEN, CROSS_REQUEST_TEMPLATE_ID, INDEX_STATS_TEMPLATE_ID,
+        INSTRUMENT_STATE_TEMPLATE_ID, MASS_INSTRUMENT_STATE_TEMPLATE_ID, MAX_MARKET_DEPTH_IDX,
+        PRODUCT_STATE_TEMPLATE_ID, QUOTE_REQUEST_TEMPLATE_ID, SNAPSHOT_TEMPLATE_ID, TIMESTAMP_LEN,
+        TOP_OF_BOOK_IMPLIED_TEMPLATE_ID,
+    },
+    global::{OUTPUT, TOKEN_FILTER},
     types::{
         packet::Packet,
         packet_structures::{
             depth_output::{TagMarketDepthInfo, TagMarketPictureBroadcast, TagMessageHeader},
             mcx::{DepthSnapshot, MDIncGrp, MDSshGrp, Message},
+            mcx_output::{
+                TagCrossRequest, TagImpliedTopOfBook, TagIndexStats, TagInstrumentState,
+                TagProductState, TagQuoteRequest,
+            },
         },
+        settings::Exchange,
         work::Work,
     },
     utils::{
         atomic_utils::compare_and_swap_gte,
         byte_utils::{bytes_to_struct, bytes_to_struct_mut, create_empty, struct_to_bytes},
+        price_utils::{price_multiplier, to_units},
         time_utils::get_epoch_us,
     },
 };
 
 pub fn process_mcx_depth_snapshot(packet: &mut Packet, work: &Work) -> bool {
-    let message: Message = bytes_to_struct(&packet.0[..]);
+    let Ok(message) = bytes_to_struct::<Message>(&packet.0[..]) else {
+        return false;
+    };
 
     if let Message::DepthSnapshotEmpty(()) = message {
         // Swap atomic ptr with null, and add atomic ptr to work
@@ -33,12 +42,8 @@ pub fn process_mcx_depth_snapshot(packet: &mut Packet, work: &Work) -> bool {
         let mut ptr = unsafe { Box::from_raw(raw_ptr) };
 
         // Cast packet as depth snapshot
-        let snapshot: &mut DepthSnapshot = bytes_to_struct_mut(&mut ptr.0[..]);
-
-        let target_market_picture = snapshot_to_market_picture(snapshot);
-
-        packet.1 = target_market_picture.msg_header.message_length as usize;
-        struct_to_bytes(&target_market_picture, &mut packet.0);
+        let target_market_picture = bytes_to_struct_mut::<DepthSnapshot>(&mut ptr.0[..])
+            .map(|snapshot| snapshot_to_market_picture(snapshot));
 
         // Put ptr back into atomic ptr if it is null
         let swapped = mcx_state.ptr.compare_exchange(
@@ -51,10 +56,18 @@ pub fn process_mcx_depth_snapshot(packet: &mut Packet, work: &Work) -> bool {
         // De allocate if current swap doesn't succeed
         if swapped.is_err() {
             unsafe {
-                drop_in_place(raw_ptr);
+                drop(Box::from_raw(raw_ptr));
             }
         }
 
+        // Snapshot buffer is too short for its struct
+        let Ok(target_market_picture) = target_market_picture else {
+            return false;
+        };
+
+        packet.1 = target_market_picture.msg_header.message_length as usize;
+        struct_to_bytes(&target_market_picture, &mut packet.0);
+
         OUTPUT.write(&packet);
 
         return true;
@@ -65,7 +78,9 @@ pub fn process_mcx_depth_snapshot(packet: &mut Packet, work: &Work) -> bool {
 }
 
 pub fn process_mcx_depth_incremental(packet: &mut Packet, work: &Work) -> bool {
-    let message: Message = bytes_to_struct(&packet.0[..]);
+    let Ok(message) = bytes_to_struct::<Message>(&packet.0[..]) else {
+        return false;
+    };
 
     if let Message::MDIncGrp(md_incr_grp) = message {
         let mcx_state = work.mcx_state.clone().unwrap();
@@ -81,31 +96,29 @@ pub fn process_mcx_depth_incremental(packet: &mut Packet, work: &Work) -> bool {
         let mut ptr = unsafe { Box::from_raw(raw_ptr) };
 
         // Cast packet as depth snapshot
-        let snapshot: &mut DepthSnapshot = bytes_to_struct_mut(&mut ptr.0[..]);
-
-        // Perform update based on MDUpdateAction
-        match md_incr_grp.MDUpdateAction {
-            0 if md_incr_grp.MDEntryType == 2 => do_trade(snapshot, &md_incr_grp),
-            0 => add_depth(snapshot, &md_incr_grp),
-            1 => change_depth(snapshot, &md_incr_grp),
-            2 => del_depth(snapshot, &md_incr_grp),
-            3 => del_thru_depth(snapshot, &md_incr_grp),
-            4 => del_from_depth(snapshot, &md_incr_grp),
-            5 => overlay_depth(snapshot, &md_incr_grp),
-            _ => {
-                println!(
-                    "Ignoring MDUpdateAction: {} Entry Type {:?}",
-                    md_incr_grp.MDUpdateAction, md_incr_grp.MDEntryType
-                )
-            }
-        };
-
-        snapshot.MsgSeqNum = Some(work.seq_no as u32);
-
-        let target_market_picture = snapshot_to_market_picture(snapshot);
-
-        packet.1 = target_market_picture.msg_header.message_length as usize;
-        struct_to_bytes(&target_market_picture, &mut packet.0);
+        let target_market_picture =
+            bytes_to_struct_mut::<DepthSnapshot>(&mut ptr.0[..]).map(|snapshot| {
+                // Perform update based on MDUpdateAction
+                match md_incr_grp.MDUpdateAction {
+                    0 if md_incr_grp.MDEntryType == 2 => do_trade(snapshot, &md_incr_grp),
+                    0 => add_depth(snapshot, &md_incr_grp),
+                    1 => change_depth(snapshot, &md_incr_grp),
+                    2 => del_depth(snapshot, &md_incr_grp),
+                    3 => del_thru_depth(snapshot, &md_incr_grp),
+                    4 => del_from_depth(snapshot, &md_incr_grp),
+                    5 => overlay_depth(snapshot, &md_incr_grp),
+                    _ => {
+                        println!(
+                            "Ignoring MDUpdateAction: {} Entry Type {:?}",
+                            md_incr_grp.MDUpdateAction, md_incr_grp.MDEntryType
+                        )
+                    }
+                };
+
+                snapshot.MsgSeqNum = Some(work.seq_no as u32);
+
+                snapshot_to_market_picture(snapshot)
+            });
 
         // Put ptr back into atomic ptr if it is null
         let swapped = mcx_state.ptr.compare_exchange(
@@ -118,10 +131,18 @@ pub fn process_mcx_depth_incremental(packet: &mut Packet, work: &Work) -> bool {
         // De allocate if current swap doesn't succeed
         if swapped.is_err() {
             unsafe {
-                drop_in_place(raw_ptr);
+                drop(Box::from_raw(raw_ptr));
             }
         }
 
+        // Snapshot buffer is too short for its struct
+        let Ok(target_market_picture) = target_market_picture else {
+            return false;
+        };
+
+        packet.1 = target_market_picture.msg_header.message_length as usize;
+        struct_to_bytes(&target_market_picture, &mut packet.0);
+
         OUTPUT.write(&packet);
 
         return true;
@@ -130,11 +151,196 @@ pub fn process_mcx_depth_incremental(packet: &mut Packet, work: &Work) -> bool {
     false
 }
 
-pub fn process_mcx_depth_others(_packet: &mut Packet, _work: &Work) -> bool {
-    // OUTPUT.write(&packet);
+pub fn process_mcx_depth_others(packet: &mut Packet, _work: &Work) -> bool {
+    let Ok(message) = bytes_to_struct::<Message>(&packet.0[..]) else {
+        return false;
+    };
+
+    match message {
+        Message::InstrumentStateChange(state) => {
+            if !TOKEN_FILTER.contains(state.SecurityID) {
+                return false;
+            }
+
+            let instrument_state = TagInstrumentState {
+                msg_header: mcx_header::<TagInstrumentState>(INSTRUMENT_STATE_TEMPLATE_ID),
+                seq_no: state.MsgSeqNum,
+                token: state.SecurityID,
+                market_segment_id: state.MarketSegmentID,
+                security_status: state.SecurityStatus,
+                trading_status: state.SecurityTradingStatus.unwrap_or(0),
+                market_condition: state.MarketCondition,
+                fast_market_indicator: state.FastMarketIndicator,
+                trading_event: state.SecurityTradingEvent.unwrap_or(0),
+                sold_out_indicator: state.SoldOutIndicator.unwrap_or(0),
+                transact_time: state.TransactTime,
+            };
+
+            write_output(packet, &instrument_state);
+        }
+        Message::MassInstrumentStateChange(mass_state) => {
+            // Published as one instrument state per security
+            for state in &mass_state.SecMassStatGrp {
+                if !TOKEN_FILTER.contains(state.SecurityID) {
+                    continue;
+                }
+
+                let instrument_state = TagInstrumentState {
+                    msg_header: mcx_header::<TagInstrumentState>(MASS_INSTRUMENT_STATE_TEMPLATE_ID),
+                    seq_no: mass_state.MsgSeqNum,
+                    token: state.SecurityID,
+                    market_segment_id: mass_state.MarketSegmentID,
+                    security_status: state.SecurityStatus,
+                    trading_status: state
+                        .SecurityTradingStatus
+                        .or(mass_state.SecurityMassTradingStatus)
+                        .unwrap_or(0),
+                    market_condition: state.MarketCondition,
+                    fast_market_indicator: mass_state.FastMarketIndicator,
+                    trading_event: state
+                        .SecurityTradingEvent
+                        .or(mass_state.SecurityMassTradingEvent)
+                        .unwrap_or(0),
+                    sold_out_indicator: state
+                        .SoldOutIndicator
+                        .or(mass_state.MassSoldOutIndicator)
+                        .unwrap_or(0),
+                    transact_time: mass_state.TransactTime,
+                };
+
+                write_output(packet, &instrument_state);
+            }
+        }
+        Message::ProductStateChange(state) => {
+            let product_state = TagProductState {
+                msg_header: mcx_header::<TagProductState>(PRODUCT_STATE_TEMPLATE_ID),
+                seq_no: state.MsgSeqNum,
+                market_segment_id: state.MarketSegmentID,
+                trading_session_id: state.TradingSessionID,
+                trading_session_sub_id: state.TradingSessionSubID,
+                trading_session_status: state.TradSesStatus,
+                market_condition: state.MarketCondition.unwrap_or(0),
+                fast_market_indicator: state.FastMarketIndicator,
+                transact_time: state.TransactTime,
+            };
+
+            write_output(packet, &product_state);
+        }
+        Message::IndexStats(stats) => {
+            let index_stats = TagIndexStats {
+                msg_header: mcx_header::<TagIndexStats>(INDEX_STATS_TEMPLATE_ID),
+                seq_no: stats.MsgSeqNum.unwrap_or(0),
+                market_segment_id: stats.MarketSegmentID.unwrap_or(0),
+                value: to_price(stats.IndexValue),
+                open: to_price(stats.IndexOpen),
+                high: to_price(stats.IndexHigh),
+                low: to_price(stats.IndexLow),
+                close: to_price(stats.IndexClose),
+                life_high: to_price(stats.IndexLifeHigh),
+                life_low: to_price(stats.IndexLifeLow),
+                week_52_high: to_price(stats.Index52WeekHigh),
+                week_52_low: to_price(stats.Index52WeekLow),
+                close_index_flag: stats.CloseIndexFlag.unwrap_or(0),
+                transact_time: stats.TransactTime,
+            };
+
+            write_output(packet, &index_stats);
+        }
+        Message::TopOfBookImplied(top_of_book) => {
+            for entry in &top_of_book.MDIncGrp {
+                if !TOKEN_FILTER.contains(entry.SecurityID) {
+                    continue;
+                }
+
+                let implied_top_of_book = TagImpliedTopOfBook {
+                    msg_header: mcx_header::<TagImpliedTopOfBook>(TOP_OF_BOOK_IMPLIED_TEMPLATE_ID),
+                    seq_no: top_of_book.MsgSeqNum,
+                    token: entry.SecurityID,
+                    market_segment_id: top_of_book.MarketSegmentID,
+                    update_action: entry.MDUpdateAction,
+                    entry_type: entry.MDEntryType,
+                    price: to_price(entry.MDEntryPx),
+                    qty: entry.MDEntrySize.unwrap_or(0.) as i64,
+                    entry_time: entry.MDEntryTime.unwrap_or(0),
+                };
+
+                write_output(packet, &implied_top_of_book);
+            }
+        }
+        Message::QuoteRequest(quote_request) => {
+            for request in &quote_request.QuotReqGrp {
+                if !TOKEN_FILTER.contains(request.SecurityID) {
+                    continue;
+                }
+
+                let quote_request = TagQuoteRequest {
+                    msg_header: mcx_header::<TagQuoteRequest>(QUOTE_REQUEST_TEMPLATE_ID),
+                    seq_no: quote_request.MsgSeqNum,
+                    token: request.SecurityID,
+                    market_segment_id: quote_request.MarketSegmentID,
+                    side: request.Side.unwrap_or(0),
+                    qty: request.OrderQty.unwrap_or(0.) as i64,
+                    transact_time: request.TransactTime,
+                };
+
+                write_output(packet, &quote_request);
+            }
+        }
+        Message::CrossRequest(request) => {
+            if !TOKEN_FILTER.contains(request.SecurityID) {
+                return false;
+            }
+
+            let cross_request = TagCrossRequest {
+                msg_header: mcx_header::<TagCrossRequest>(CROSS_REQUEST_TEMPLATE_ID),
+                seq_no: request.MsgSeqNum,
+                token: request.SecurityID,
+                market_segment_id: request.MarketSegmentID,
+                cross_request_type: request.CrossRequestType,
+                price: to_price(request.MDEntryPx),
+                qty: request.OrderQty.unwrap_or(0.) as i64,
+                transact_time: request.TransactTime,
+            };
+
+            write_output(packet, &cross_request);
+        }
+        // Instrument definitions are not published
+        _ => return false,
+    }
+
     true
 }
 
+fn mcx_header<T>(message_code: i32) -> TagMessageHeader {
+    TagMessageHeader {
+        message_code,
+        transaction_type: 0,
+        log_time: 0,
+        alpha_char: [0; ALPHA_CHAR_LEN],
+        trader_id: 0,
+        error_code: 0,
+        timestamp: get_epoch_us() as u64,
+        timestamp1: [0; TIMESTAMP_LEN],
+        timestamp2: [0; TIMESTAMP_LEN],
+        message_length: size_of::<T>() as i16,
+    }
+}
+
+fn to_price(price: Option<f64>) -> i32 {
+    to_mcx_price(price.unwrap_or(0.))
+}
+
+// Prices are decoded as decimals, and are sent in paise
+fn to_mcx_price(price: f64) -> i32 {
+    to_units(price, price_multiplier(Exchange::MCX)) as i32
+}
+
+fn write_output<T: Copy>(packet: &mut Packet, output: &T) {
+    packet.1 = struct_to_bytes(output, &mut packet.0);
+
+    OUTPUT.write(packet);
+}
+
 fn snapshot_to_market_picture(depth_snapshot: &DepthSnapshot) -> TagMarketPictureBroadcast {
     let msg_header = TagMessageHeader {
         message_code: SNAPSHOT_TEMPLATE_ID,
@@ -167,10 +373,11 @@ fn snapshot_to_market_picture(depth_snapshot: &DepthSnapshot) -> TagMarketPictur
                 .expect("MDEntrySize must be present for MDEntryType B(9) (TradeVolume)")
                 as i64;
 
-            atp = (md_ssh_grp
-                .AverageTradedPrice
-                .expect("AveragePrice must be present for B")
-                * 100.0) as i32;
+            atp = to_mcx_price(
+                md_ssh_grp
+                    .AverageTradedPrice
+                    .expect("AveragePrice must be present for B"),
+            );
         } else if md_ssh_grp.MDEntryType == 2 {
             // Set ohlc
             let trade_condition = md_ssh_grp
@@ -179,22 +386,22 @@ fn snapshot_to_market_picture(depth_snapshot: &DepthSnapshot) -> TagMarketPictur
 
             // Set ltp, ltq, ltt
             if trade_condition & 1 == 1 {
-                ltp = (md_ssh_grp.MDEntryPx.unwrap() * 100.0) as i32;
+                ltp = to_mcx_price(md_ssh_grp.MDEntryPx.unwrap());
                 ltq = md_ssh_grp.MDEntrySize.unwrap() as i32;
                 ltt = (md_ssh_grp.MDEntryTime.unwrap() / 1000000000) as i32;
             }
             // Set open, high, low, close
             if trade_condition & 2 == 2 {
-                open_price = (md_ssh_grp.MDEntryPx.unwrap() * 100.0) as i32;
+                open_price = to_mcx_price(md_ssh_grp.MDEntryPx.unwrap());
             }
             if trade_condition & 4 == 4 {
-                high_price = (md_ssh_grp.MDEntryPx.unwrap() * 100.0) as i32;
+                high_price = to_mcx_price(md_ssh_grp.MDEntryPx.unwrap());
             }
             if trade_condition & 8 == 8 {
-                low_price = (md_ssh_grp.MDEntryPx.unwrap() * 100.0) as i32;
+                low_price = to_mcx_price(md_ssh_grp.MDEntryPx.unwrap());
             }
             if trade_condition & 128 == 128 {
-                close_price = (md_ssh_grp.MDEntryPx.unwrap() * 100.0) as i32;
+                close_price = to_mcx_price(md_ssh_grp.MDEntryPx.unwrap());
             }
         }
     });
@@ -237,7 +444,7 @@ fn snapshot_to_market_picture(depth_snapshot: &DepthSnapshot) -> TagMarketPictur
         // Add market depth
         tag_market_picture_broadcast.market_depth_info[idx] = TagMarketDepthInfo {
             qty: ssh_grp.MDEntrySize.unwrap() as i64,
-            price: (ssh_grp.MDEntryPx.unwrap() * 100.0) as i32,
+            price: to_mcx_price(ssh_grp.MDEntryPx.unwrap()),
             number_of_orders: ssh_grp.NumberOfOrders.unwrap() as i16,
         };
 
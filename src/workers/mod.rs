@@ -1,6 +1,7 @@
 use crate::types::work::{ProcessingFn, WorkType};
 
 pub mod bse_worker;
+pub mod master_worker;
 pub mod mcx_workers;
 pub mod nse_worker;
 
@@ -41,8 +42,8 @@ pub fn get_bse_processing_fn(work_type: &WorkType) -> ProcessingFn {
 
 pub fn get_mcx_processing_fn(work_type: &WorkType) -> ProcessingFn {
     match work_type {
-        WorkType::McxDepthSnapshot => mcx_workers::process_mcx_depth_snapshot,
-        WorkType::McxDepthIncr => mcx_workers::process_mcx_depth_incremental,
+        WorkType::McxDepthSnapshot(_) => mcx_workers::process_mcx_depth_snapshot,
+        WorkType::McxDepthIncr(_) => mcx_workers::process_mcx_depth_incremental,
         WorkType::McxOther => mcx_workers::process_mcx_depth_others,
         _ => panic!("Invalid work type for MCX processing function"),
     }
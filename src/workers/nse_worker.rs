@@ -5,7 +5,7 @@ use crate::{
         BCAST_MBO_MBP, BCAST_ONLY_MBP, BCAST_ONLY_MBP_EQ, MAX_BUY_SELL_DEPTH_IDX,
         MAX_MARKET_DEPTH_IDX, MAX_MBPINFO_IDX, NSE_EPOCH_START, SKIP_BYTES,
     },
-    global::{EXCHANGE, NSE_HEADER_SIZE, OUTPUT},
+    global::{EXCHANGE, NSE_HEADER_SIZE, OUTPUT, STATISTICS},
     types::{
         packet::Packet,
         packet_structures::{
@@ -26,12 +26,27 @@ use crate::{
     },
 };
 
+use super::master_worker;
+
 pub fn cast_and_twiddle_nfo(packet: &mut Packet, _work: &Work) -> bool {
     let trans_code = BcastHeaders::get_trans_code(&packet.0);
 
-    if let Some(mut nfo_struct) = build_nfo_struct(trans_code, &packet.0[SKIP_BYTES..]) {
+    // Buffer is zeroed past message up to largest struct, so short messages are read as zero filled
+    let nfo_struct = match build_nfo_struct(trans_code, &packet.0[SKIP_BYTES..]) {
+        Ok(nfo_struct) => nfo_struct,
+        Err(e) => {
+            println!("Malformed fao packet {}: {}", trans_code, e);
+            STATISTICS.record_malformed();
+            return false;
+        }
+    };
+
+    if let Some(mut nfo_struct) = nfo_struct {
         nfo_struct.twiddle();
 
+        // Keep security master current
+        master_worker::apply_nfo(&nfo_struct);
+
         // Convert struct to custom struct for 7208 and 7200
         if let NfoBroadcastTransactionMapping::BcastMboMbpUpdate(s) = &mut nfo_struct {
             let st = convert_mbo_mbp(s, &mut packet.1);
@@ -40,7 +55,7 @@ pub fn cast_and_twiddle_nfo(packet: &mut Packet, _work: &Work) -> bool {
             let st = convert_only_mbp(s, &mut packet.1);
             packet.1 = struct_to_bytes(&st, &mut packet.0);
         } else {
-            nfo_struct.to_bytes(&mut packet.0);
+            packet.1 = nfo_struct.to_bytes(&mut packet.0);
         }
     };
 
@@ -52,9 +67,21 @@ pub fn cast_and_twiddle_nfo(packet: &mut Packet, _work: &Work) -> bool {
 pub fn cast_and_twiddle_neq(packet: &mut Packet, _work: &Work) -> bool {
     let trans_code = BcastHeaders::get_trans_code(&packet.0);
 
-    if let Some(mut neq_struct) = build_neq_struct(trans_code, &packet.0[SKIP_BYTES..]) {
+    let neq_struct = match build_neq_struct(trans_code, &packet.0[SKIP_BYTES..]) {
+        Ok(neq_struct) => neq_struct,
+        Err(e) => {
+            println!("Malformed eq packet {}: {}", trans_code, e);
+            STATISTICS.record_malformed();
+            return false;
+        }
+    };
+
+    if let Some(mut neq_struct) = neq_struct {
         neq_struct.twiddle();
 
+        // Keep security master current
+        master_worker::apply_neq(&neq_struct);
+
         // Convert struct to custom struct for 7208 and 7200
         if let NeqBroadcastTransactionMapping::BcastMboMbpCedtc(s) = &mut neq_struct {
             let st = convert_mbo_mbp_eq(s, &mut packet.1);
@@ -66,7 +93,7 @@ pub fn cast_and_twiddle_neq(packet: &mut Packet, _work: &Work) -> bool {
             let st = convert_only_mbp_eq(s, &mut packet.1);
             packet.1 = struct_to_bytes(&st, &mut packet.0);
         } else {
-            neq_struct.to_bytes(&mut packet.0);
+            packet.1 = neq_struct.to_bytes(&mut packet.0);
         };
     }
 
@@ -78,9 +105,21 @@ pub fn cast_and_twiddle_neq(packet: &mut Packet, _work: &Work) -> bool {
 pub fn cast_and_twiddle_ncd(packet: &mut Packet, _work: &Work) -> bool {
     let trans_code = BcastHeaders::get_trans_code(&packet.0);
 
-    if let Some(mut ncd_struct) = build_ncd_struct(trans_code, &packet.0[SKIP_BYTES..]) {
+    let ncd_struct = match build_ncd_struct(trans_code, &packet.0[SKIP_BYTES..]) {
+        Ok(ncd_struct) => ncd_struct,
+        Err(e) => {
+            println!("Malformed cd packet {}: {}", trans_code, e);
+            STATISTICS.record_malformed();
+            return false;
+        }
+    };
+
+    if let Some(mut ncd_struct) = ncd_struct {
         ncd_struct.twiddle();
 
+        // Keep security master current
+        master_worker::apply_ncd(&ncd_struct);
+
         // Convert struct to custom struct for 7208 and 7200
         if let NcdBroadcastTransactionMapping::BcastMboMbpUpdate(s) = &mut ncd_struct {
             let st = convert_mbo_mbp(s, &mut packet.1);
@@ -91,7 +130,7 @@ pub fn cast_and_twiddle_ncd(packet: &mut Packet, _work: &Work) -> bool {
 
             packet.1 = struct_to_bytes(&st, &mut packet.0);
         } else {
-            ncd_struct.to_bytes(&mut packet.0);
+            packet.1 = ncd_struct.to_bytes(&mut packet.0);
         };
     }
 